@@ -2,7 +2,7 @@ use egui::epaint::text::{FontInsert, InsertFontFamily};
 use huozi::{
     Huozi,
     constant::TEXTURE_SIZE,
-    layout::{ColorSpace, LayoutDirection, LayoutStyle, Vertex},
+    layout::{ColorSpace, LayoutDirection, LayoutResult, LayoutStyle, Vertex},
     parser::{Segment, TextStyle},
 };
 use log::{error, info};
@@ -357,7 +357,7 @@ impl State {
             num_indices: None,
             texture,
             texture_bind_group,
-            current_font: get_builtin_fonts().get(0).unwrap().0.to_string(),
+            current_font: get_builtin_fonts().first().unwrap().0.to_string(),
             huozi: None,
             egui_context,
             egui_state,
@@ -374,7 +374,7 @@ impl State {
                 box_width: Some(1280.),
                 box_height: Some(600.),
                 glyph_grid_size: 32.,
-                punctuation: Default::default(),
+                ..Default::default()
             },
             text_config: text_style_default(),
             stroke_enabled: true,
@@ -437,7 +437,7 @@ impl State {
 
             let font_data = get_builtin_fonts()
                 .iter()
-                .find(|(name, _)| *name == &self.current_font)
+                .find(|(name, _)| *name == self.current_font)
                 .map(|(_, data)| *data)
                 .expect("Failed to find font data for the current font");
 
@@ -462,7 +462,19 @@ impl State {
             ColorSpace::SRGB,
             None,
         ) {
-            Ok((glyphs, _, total_width, total_height)) => {
+            Ok((
+                LayoutResult {
+                    glyphs,
+                    width: total_width,
+                    height: total_height,
+                    ..
+                },
+                diagnostics,
+            )) => {
+                for diagnostic in diagnostics {
+                    log::warn!("{}", diagnostic);
+                }
+
                 info!(
                     "text layouting finished, {}ms",
                     SystemTime::now().duration_since(t).unwrap().as_millis()
@@ -552,7 +564,7 @@ impl State {
                 label: Some("Render Encoder"),
             });
 
-        if self.vertex_buffer.is_some() {
+        if let Some(vertex_buffer) = &self.vertex_buffer {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
@@ -568,11 +580,10 @@ impl State {
                 ..Default::default()
             });
 
-            let vertex_buffer = self.vertex_buffer.as_ref().unwrap();
             let index_buffer = self.index_buffer.as_ref().unwrap();
             let num_indices = self.num_indices.unwrap();
 
-            if self.vertex_buffer.as_ref().unwrap().size() > 0 {
+            if vertex_buffer.size() > 0 {
                 render_pass.set_pipeline(&self.render_pipeline);
                 render_pass.set_bind_group(0, &self.mvp_bind_group, &[]);
                 render_pass.set_bind_group(1, &self.texture_bind_group, &[]);
@@ -786,6 +797,8 @@ pub fn run(event_loop: EventLoop<()>) {
     let _ = event_loop.run_app(&mut app);
 }
 
+// unused when this file is built as the `render-lib` cdylib
+#[allow(dead_code)]
 fn main() {
     cfg_if::cfg_if! {
         if #[cfg(target_arch = "wasm32")] {
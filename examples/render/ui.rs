@@ -19,7 +19,7 @@ pub fn render_control_panel_ui(state: &mut State, window: &Window) -> FullOutput
         egui::Panel::bottom("text_input_panel")
             .resizable(false)
             .default_size(360.0)
-            .show_inside(ui, |ui| {
+            .show(ui, |ui| {
                 ui.add_space(6.);
 
                 ui.horizontal(|ui| {
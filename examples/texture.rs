@@ -11,9 +11,8 @@ fn main() {
     huozi.preload(
         &ASCII
             .chars()
-            .into_iter()
-            .chain(CJK_SYMBOL.chars().into_iter())
-            .chain(CHS.chars().into_iter())
+            .chain(CJK_SYMBOL.chars())
+            .chain(CHS.chars())
             .take(1024)
             .collect::<String>(),
     );
@@ -26,7 +25,6 @@ fn main() {
     // copy red channel to green and blue channel, then fill alpha channel with 255 for easier viewing
     let mut img = huozi.texture_image().clone();
     img.chunks_exact_mut(4).for_each(|chunk| {
-        chunk[0] = chunk[0];
         chunk[1] = chunk[0];
         chunk[2] = chunk[0];
         chunk[3] = 255;
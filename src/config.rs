@@ -0,0 +1,142 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::constant::{BUFFER, CUTOFF, FONT_SIZE, GRID_SIZE, RADIUS, TEXTURE_SIZE};
+
+/// Parameters of the glyph atlas, which trade texture memory for rendering quality.
+///
+/// The default value matches the constants in [`crate::constant`], so that
+/// `Huozi::new(font_data)` behaves the same as `Huozi::with_config(font_data, HuoziConfig::default())`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HuoziConfig {
+    /// The size of each grid in the atlas, in pixels.
+    pub grid_size: f64,
+    /// The font size used to rasterize glyphs into the atlas.
+    pub font_size: f64,
+    /// The width and height of the atlas texture, in pixels.
+    pub texture_size: u32,
+    /// The padding around each glyph for the signed distance field.
+    pub buffer: f64,
+    /// The radius of the signed distance field.
+    pub radius: f64,
+    /// The cutoff of the signed distance field, between 0 and 1.
+    pub cutoff: f64,
+}
+
+impl Default for HuoziConfig {
+    fn default() -> Self {
+        Self {
+            grid_size: GRID_SIZE,
+            font_size: FONT_SIZE,
+            texture_size: TEXTURE_SIZE,
+            buffer: BUFFER,
+            radius: RADIUS,
+            cutoff: CUTOFF,
+        }
+    }
+}
+
+impl HuoziConfig {
+    /// Check that the parameters make a usable atlas.
+    ///
+    /// The sizes and the radius must be positive, the buffer must not be negative, the cutoff
+    /// must be between 0 and 1, and a glyph with the buffer around it must fit in a grid,
+    /// which must fit in the texture.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let positive = [
+            ("gridSize", self.grid_size),
+            ("fontSize", self.font_size),
+            ("textureSize", self.texture_size as f64),
+            ("radius", self.radius),
+        ];
+        for (name, value) in positive {
+            if !(value.is_finite() && value > 0.) {
+                return Err(ConfigError::InvalidValue { name, value });
+            }
+        }
+        if !(self.buffer.is_finite() && self.buffer >= 0.) {
+            return Err(ConfigError::InvalidValue {
+                name: "buffer",
+                value: self.buffer,
+            });
+        }
+        if !(0. ..=1.).contains(&self.cutoff) {
+            return Err(ConfigError::InvalidValue {
+                name: "cutoff",
+                value: self.cutoff,
+            });
+        }
+
+        if self.font_size + 2. * self.buffer > self.grid_size {
+            return Err(ConfigError::GlyphExceedsGrid {
+                font_size: self.font_size,
+                buffer: self.buffer,
+                grid_size: self.grid_size,
+            });
+        }
+        if self.grid_size > self.texture_size as f64 {
+            return Err(ConfigError::GridExceedsTexture {
+                grid_size: self.grid_size,
+                texture_size: self.texture_size,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// The number of grids in a row (or a column) of the atlas texture.
+    pub fn grids_per_line(&self) -> u32 {
+        self.texture_size / self.grid_size as u32
+    }
+
+    /// The number of glyphs the atlas can hold, counting all four channels as pages.
+    pub fn cache_capacity(&self) -> usize {
+        self.grids_per_line().pow(2) as usize * 4
+    }
+}
+
+/// A parameter of [`HuoziConfig`] which cannot make a usable atlas.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    /// a parameter is not a finite number in its range, by its serialized name
+    InvalidValue { name: &'static str, value: f64 },
+    /// a glyph in the font size with the buffer on both sides is larger than a grid
+    GlyphExceedsGrid {
+        font_size: f64,
+        buffer: f64,
+        grid_size: f64,
+    },
+    /// a grid is larger than the texture
+    GridExceedsTexture { grid_size: f64, texture_size: u32 },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::InvalidValue { name, value } => {
+                write!(f, "invalid value {} of `{}`", value, name)
+            }
+            ConfigError::GlyphExceedsGrid {
+                font_size,
+                buffer,
+                grid_size,
+            } => write!(
+                f,
+                "font size {} with buffer {} does not fit in grid size {}",
+                font_size, buffer, grid_size
+            ),
+            ConfigError::GridExceedsTexture {
+                grid_size,
+                texture_size,
+            } => write!(
+                f,
+                "grid size {} is larger than texture size {}",
+                grid_size, texture_size
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
@@ -4,7 +4,7 @@ pub const BUFFER: f64 = 16.;
 pub const RADIUS: f64 = 24.;
 pub const CUTOFF: f64 = 0.25;
 pub const TEXTURE_SIZE: u32 = 2048;
-// 112 is just a magic number, may should be replaced by more reasonable algorithm
+#[deprecated(note = "glyphs are placed by the vertical metrics of their fonts, see `LineMetrics`")]
 pub const ASCENT: f64 = 112.;
 pub const GAMMA_COEFFICIENT: f32 = 0.06;
 
@@ -6,6 +6,7 @@ mod font_kit;
 #[cfg(feature = "fontdue")]
 mod fontdue;
 // pub mod rusttype;
+mod tables;
 
 #[cfg(feature = "ab_glyph")]
 pub use self::ab_glyph::*;
@@ -14,3 +15,4 @@ pub use self::fontdue::*;
 pub use common::*;
 #[cfg(feature = "font_kit")]
 pub use font_kit::*;
+pub use tables::VerticalExtent;
@@ -1,17 +1,19 @@
-use ab_glyph::{Font, FontVec, PxScale, ScaleFont};
+use ab_glyph::{Font, FontVec, GlyphId, PxScale, ScaleFont};
 
 use super::common::{FontHMetrics, GlyphExtractorTrait, GlyphMetrics};
+use super::tables::FontTables;
 
 pub struct GlyphExtractor {
     font: FontVec,
     scale: PxScale,
     font_size: f32,
+    tables: FontTables,
 }
 
 impl GlyphExtractor {
-    fn get_glyph_metrics_inner(&self, ch: char, scale: Option<PxScale>) -> GlyphMetrics {
+    fn get_glyph_metrics_inner(&self, glyph_id: GlyphId, scale: Option<PxScale>) -> GlyphMetrics {
         let font = self.font.as_scaled(scale.unwrap_or(self.scale));
-        let glyph = font.glyph_id(ch).with_scale(scale.unwrap_or(self.scale));
+        let glyph = glyph_id.with_scale(scale.unwrap_or(self.scale));
         let h_advance = font.h_advance(glyph.id);
         let v_advance = font.v_advance(glyph.id);
 
@@ -49,6 +51,7 @@ impl GlyphExtractor {
 
 impl GlyphExtractorTrait for GlyphExtractor {
     fn new(font_data: Vec<u8>, font_size: f32) -> Self {
+        let tables = FontTables::read(&font_data);
         let font = FontVec::try_from_vec(font_data).unwrap();
         let scale =
             PxScale::from(font_size * font.height_unscaled() / font.units_per_em().unwrap());
@@ -56,6 +59,7 @@ impl GlyphExtractorTrait for GlyphExtractor {
             font,
             scale,
             font_size,
+            tables,
         }
     }
     fn set_font_size(&mut self, font_size: f32) {
@@ -66,24 +70,33 @@ impl GlyphExtractorTrait for GlyphExtractor {
         self.font_size = font_size;
     }
     fn get_glyph_metrics(&self, ch: char) -> GlyphMetrics {
-        self.get_glyph_metrics_inner(ch, None)
+        self.get_glyph_metrics_inner(self.font.glyph_id(ch), None)
     }
 
     fn font_metrics(&self) -> FontHMetrics {
         let font = self.font.as_scaled(self.scale);
+        let (typo, typo_line_gap) = self.tables.typo(self.font_size).unzip();
         FontHMetrics {
             ascent: font.ascent().ceil() as i32,
             descent: font.descent().ceil() as i32,
             line_gap: font.line_gap().ceil() as i32,
             line_height: (font.ascent() - font.descent() + font.line_gap()).ceil() as i32,
             content_height: (font.ascent() - font.descent()).ceil() as i32,
+            typo,
+            typo_line_gap,
+            ideographic_em_box: self.tables.ideographic_em_box(self.font_size),
         }
     }
 
     fn get_bitmap_and_metrics(&self, ch: char) -> (Vec<u8>, GlyphMetrics) {
-        let metrics = self.get_glyph_metrics(ch);
+        self.get_bitmap_and_metrics_by_id(self.font.glyph_id(ch).0)
+    }
+
+    fn get_bitmap_and_metrics_by_id(&self, glyph_id: u16) -> (Vec<u8>, GlyphMetrics) {
+        let glyph_id = GlyphId(glyph_id);
+        let metrics = self.get_glyph_metrics_inner(glyph_id, None);
 
-        let glyph = self.font.glyph_id(ch).with_scale(self.scale);
+        let glyph = glyph_id.with_scale(self.scale);
 
         let capacity = (metrics.width * metrics.height) as usize;
         let mut bitmap = vec![0u8; capacity];
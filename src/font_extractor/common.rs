@@ -12,6 +12,8 @@ pub struct GlyphMetrics {
     pub y_scale: Option<f32>,
 }
 
+use super::tables::VerticalExtent;
+
 #[derive(Debug, Clone)]
 pub struct FontHMetrics {
     pub ascent: i32,
@@ -19,6 +21,12 @@ pub struct FontHMetrics {
     pub line_gap: i32,
     pub line_height: i32,
     pub content_height: i32,
+    /// the typographic ascent and descent of `OS/2`, if the font has the table.
+    pub typo: Option<VerticalExtent>,
+    /// the typographic line gap of `OS/2`, if the font has the table.
+    pub typo_line_gap: Option<i32>,
+    /// the ideographic em box of `BASE`, if the font has the table with the `ideo` baseline.
+    pub ideographic_em_box: Option<VerticalExtent>,
 }
 
 pub trait GlyphExtractorTrait {
@@ -33,4 +41,7 @@ pub trait GlyphExtractorTrait {
     fn font_metrics(&self) -> FontHMetrics;
 
     fn get_bitmap_and_metrics(&self, ch: char) -> (Vec<u8>, GlyphMetrics);
+
+    /// Same as `get_bitmap_and_metrics`, but by the glyph id in the font, e.g. from a text shaper.
+    fn get_bitmap_and_metrics_by_id(&self, glyph_id: u16) -> (Vec<u8>, GlyphMetrics);
 }
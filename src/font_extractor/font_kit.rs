@@ -8,10 +8,12 @@ use pathfinder_geometry::transform2d::Transform2F;
 use pathfinder_geometry::vector::Vector2I;
 
 use super::common::{FontHMetrics, GlyphExtractorTrait, GlyphMetrics};
+use super::tables::FontTables;
 
 pub struct GlyphExtractor {
     font: Font,
     font_size: f32,
+    tables: FontTables,
 }
 
 impl Into<GlyphMetrics> for RectI {
@@ -35,11 +37,16 @@ impl Into<GlyphMetrics> for RectI {
 
 impl GlyphExtractorTrait for GlyphExtractor {
     fn new(font_data: Vec<u8>, font_size: f32) -> Self {
+        let tables = FontTables::read(&font_data);
         let font = font_kit::handle::Handle::from_memory(Arc::new(font_data), 0)
             .load()
             .unwrap();
 
-        Self { font, font_size }
+        Self {
+            font,
+            font_size,
+            tables,
+        }
     }
     fn set_font_size(&mut self, font_size: f32) {
         self.font_size = font_size;
@@ -49,6 +56,7 @@ impl GlyphExtractorTrait for GlyphExtractor {
     }
     fn font_metrics(&self) -> FontHMetrics {
         let h_metrics = self.font.metrics();
+        let (typo, typo_line_gap) = self.tables.typo(self.font_size).unzip();
 
         FontHMetrics {
             ascent: h_metrics.ascent.ceil() as i32,
@@ -56,15 +64,42 @@ impl GlyphExtractorTrait for GlyphExtractor {
             line_gap: h_metrics.line_gap.ceil() as i32,
             line_height: (h_metrics.ascent - h_metrics.descent + h_metrics.line_gap).ceil() as i32,
             content_height: (h_metrics.ascent - h_metrics.descent).ceil() as i32,
+            typo,
+            typo_line_gap,
+            ideographic_em_box: self.tables.ideographic_em_box(self.font_size),
         }
     }
     fn get_bitmap_and_metrics(&self, ch: char) -> (Vec<u8>, GlyphMetrics) {
-        if let Some(glyph_id) = self.font.glyph_for_char(ch) {
-            let mut transform = Transform2F::default();
-            let hinting_options = HintingOptions::None;
-            let rasterization_options = RasterizationOptions::GrayscaleAa;
+        match self.font.glyph_for_char(ch) {
+            Some(glyph_id) => self.get_bitmap_and_metrics_by_id(glyph_id as u16),
+            None => (vec![], GlyphMetrics::default()),
+        }
+    }
+
+    fn get_bitmap_and_metrics_by_id(&self, glyph_id: u16) -> (Vec<u8>, GlyphMetrics) {
+        let glyph_id = glyph_id as u32;
+        let mut transform = Transform2F::default();
+        let hinting_options = HintingOptions::None;
+        let rasterization_options = RasterizationOptions::GrayscaleAa;
 
-            let mut rect = self
+        let mut rect = self
+            .font
+            .raster_bounds(
+                glyph_id,
+                self.font_size,
+                transform,
+                hinting_options,
+                rasterization_options,
+            )
+            .unwrap();
+
+        let mut metrics: GlyphMetrics = rect.into();
+
+        if rect.width() as f32 > self.font_size {
+            let x_scale = self.font_size / rect.width() as f32;
+            transform = transform.scale(x_scale);
+
+            rect = self
                 .font
                 .raster_bounds(
                     glyph_id,
@@ -75,77 +110,55 @@ impl GlyphExtractorTrait for GlyphExtractor {
                 )
                 .unwrap();
 
-            let mut metrics: GlyphMetrics = rect.into();
-
-            if rect.width() as f32 > self.font_size {
-                let x_scale = self.font_size / rect.width() as f32;
-                transform = transform.scale(x_scale);
-
-                rect = self
-                    .font
-                    .raster_bounds(
-                        glyph_id,
-                        self.font_size,
-                        transform,
-                        hinting_options,
-                        rasterization_options,
-                    )
-                    .unwrap();
-
-                metrics.width = rect.width() as u32;
-                metrics.height = rect.height() as u32;
-                metrics.x_scale = Some(x_scale);
-                metrics.y_scale = Some(x_scale);
-            } else if rect.height() as f32 > self.font_size {
-                let y_scale = self.font_size / rect.height() as f32;
-                transform = transform.scale(y_scale);
-
-                rect = self
-                    .font
-                    .raster_bounds(
-                        glyph_id,
-                        self.font_size,
-                        transform,
-                        hinting_options,
-                        rasterization_options,
-                    )
-                    .unwrap();
-
-                metrics.width = rect.width() as u32;
-                metrics.height = rect.height() as u32;
-                metrics.x_scale = Some(y_scale);
-                metrics.y_scale = Some(y_scale);
-            };
-
-            let mut canvas = Canvas::new(
-                Vector2I::new(
-                    (metrics.width as i32).max(1),
-                    (metrics.height as i32).max(1),
-                ),
-                Format::A8,
-            );
-
-            let advance = self.font.advance(glyph_id).unwrap();
-            metrics.h_advance =
-                advance.x() / self.font.metrics().units_per_em as f32 * self.font_size;
-            metrics.v_advance =
-                advance.y() / self.font.metrics().units_per_em as f32 * self.font_size;
-
-            transform = transform.translate(-rect.origin().to_f32());
-
-            let _ = self.font.rasterize_glyph(
-                &mut canvas,
-                glyph_id,
-                self.font_size,
-                transform,
-                hinting_options,
-                rasterization_options,
-            );
+            metrics.width = rect.width() as u32;
+            metrics.height = rect.height() as u32;
+            metrics.x_scale = Some(x_scale);
+            metrics.y_scale = Some(x_scale);
+        } else if rect.height() as f32 > self.font_size {
+            let y_scale = self.font_size / rect.height() as f32;
+            transform = transform.scale(y_scale);
 
-            (canvas.pixels, metrics)
-        } else {
-            (vec![], GlyphMetrics::default())
-        }
+            rect = self
+                .font
+                .raster_bounds(
+                    glyph_id,
+                    self.font_size,
+                    transform,
+                    hinting_options,
+                    rasterization_options,
+                )
+                .unwrap();
+
+            metrics.width = rect.width() as u32;
+            metrics.height = rect.height() as u32;
+            metrics.x_scale = Some(y_scale);
+            metrics.y_scale = Some(y_scale);
+        };
+
+        let mut canvas = Canvas::new(
+            Vector2I::new(
+                (metrics.width as i32).max(1),
+                (metrics.height as i32).max(1),
+            ),
+            Format::A8,
+        );
+
+        let advance = self.font.advance(glyph_id).unwrap();
+        metrics.h_advance = advance.x() / self.font.metrics().units_per_em as f32 * self.font_size;
+        metrics.v_advance = advance.y() / self.font.metrics().units_per_em as f32 * self.font_size;
+
+        transform = transform.translate(-rect.origin().to_f32());
+
+        let _ = self.font.rasterize_glyph(
+            &mut canvas,
+            glyph_id,
+            self.font_size,
+            transform,
+            hinting_options,
+            rasterization_options,
+        );
+
+        (canvas.pixels, metrics)
     }
 
     fn exist(&self, ch: char) -> bool {
@@ -1,10 +1,12 @@
 use fontdue::{Font, Metrics};
 
 use super::common::{FontHMetrics, GlyphExtractorTrait, GlyphMetrics};
+use super::tables::FontTables;
 
 pub struct GlyphExtractor {
     font: Font,
     font_size: f32,
+    tables: FontTables,
 }
 
 impl Into<GlyphMetrics> for Metrics {
@@ -28,9 +30,14 @@ impl Into<GlyphMetrics> for Metrics {
 
 impl GlyphExtractorTrait for GlyphExtractor {
     fn new(font_data: Vec<u8>, font_size: f32) -> Self {
+        let tables = FontTables::read(&font_data);
         let font = fontdue::Font::from_bytes(font_data, fontdue::FontSettings::default()).unwrap();
 
-        Self { font, font_size }
+        Self {
+            font,
+            font_size,
+            tables,
+        }
     }
     fn set_font_size(&mut self, font_size: f32) {
         self.font_size = font_size;
@@ -44,6 +51,7 @@ impl GlyphExtractorTrait for GlyphExtractor {
             .font
             .horizontal_line_metrics(self.font_size)
             .expect("Cannot get font metrics.");
+        let (typo, typo_line_gap) = self.tables.typo(self.font_size).unzip();
 
         FontHMetrics {
             ascent: h_metrics.ascent.ceil() as i32,
@@ -51,15 +59,24 @@ impl GlyphExtractorTrait for GlyphExtractor {
             line_gap: h_metrics.line_gap.ceil() as i32,
             line_height: h_metrics.new_line_size.ceil() as i32,
             content_height: (h_metrics.ascent - h_metrics.descent).ceil() as i32,
+            typo,
+            typo_line_gap,
+            ideographic_em_box: self.tables.ideographic_em_box(self.font_size),
         }
     }
     fn get_bitmap_and_metrics(&self, ch: char) -> (Vec<u8>, GlyphMetrics) {
-        let (metrics, bitmap) = self.font.rasterize(ch, self.font_size);
+        self.get_bitmap_and_metrics_by_id(self.font.lookup_glyph_index(ch))
+    }
+
+    fn get_bitmap_and_metrics_by_id(&self, glyph_id: u16) -> (Vec<u8>, GlyphMetrics) {
+        let (metrics, bitmap) = self.font.rasterize_indexed(glyph_id, self.font_size);
 
         if metrics.width as f32 > self.font_size {
             let x_scale = self.font_size / metrics.width as f32;
             let y_scale = x_scale;
-            let (new_metrics, bitmap) = self.font.rasterize(ch, self.font_size * x_scale);
+            let (new_metrics, bitmap) = self
+                .font
+                .rasterize_indexed(glyph_id, self.font_size * x_scale);
             let new_metrics: GlyphMetrics = new_metrics.into();
             let mut metrics: GlyphMetrics = metrics.into();
 
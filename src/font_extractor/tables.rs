@@ -0,0 +1,272 @@
+//! Vertical metrics read from the OpenType tables of a font, which are not exposed by every
+//! rasterizer backend.
+
+/// A vertical extent of a font above and below the baseline, in pixels,
+/// whose descent is negative as `FontHMetrics::descent`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerticalExtent {
+    pub ascent: i32,
+    pub descent: i32,
+}
+
+/// The vertical metrics of `OS/2` and `BASE`, in font units.
+#[derive(Debug, Clone, Default)]
+pub(super) struct FontTables {
+    units_per_em: u16,
+    /// `sTypoAscender`, `sTypoDescender` and `sTypoLineGap` of `OS/2`
+    typo: Option<(i16, i16, i16)>,
+    /// the top and the bottom of the ideographic em box of `BASE`
+    ideographic_em_box: Option<(i16, i16)>,
+}
+
+impl FontTables {
+    /// Read the tables of the first font in `font_data`, leaving out any table which is missing
+    /// or malformed.
+    pub fn read(font_data: &[u8]) -> Self {
+        let data = Data(font_data);
+        let Some(tables) = data.table_directory() else {
+            return Self::default();
+        };
+
+        let units_per_em = tables
+            .find(b"head")
+            .and_then(|head| head.u16(18))
+            .filter(|&units_per_em| units_per_em > 0)
+            .unwrap_or(1000);
+
+        let typo = tables
+            .find(b"OS/2")
+            .and_then(|os2| Some((os2.i16(68)?, os2.i16(70)?, os2.i16(72)?)));
+
+        let ideographic_em_box = tables
+            .find(b"BASE")
+            .and_then(|base| base.ideographic_em_box(units_per_em));
+
+        Self {
+            units_per_em,
+            typo,
+            ideographic_em_box,
+        }
+    }
+
+    /// The typographic ascent and descent, and the line gap, at `font_size`.
+    pub fn typo(&self, font_size: f32) -> Option<(VerticalExtent, i32)> {
+        self.typo.map(|(ascent, descent, line_gap)| {
+            (
+                self.extent(ascent, descent, font_size),
+                self.scale(line_gap, font_size).ceil() as i32,
+            )
+        })
+    }
+
+    /// The ideographic em box, at `font_size`.
+    pub fn ideographic_em_box(&self, font_size: f32) -> Option<VerticalExtent> {
+        self.ideographic_em_box
+            .map(|(top, bottom)| self.extent(top, bottom, font_size))
+    }
+
+    fn extent(&self, ascent: i16, descent: i16, font_size: f32) -> VerticalExtent {
+        VerticalExtent {
+            ascent: self.scale(ascent, font_size).ceil() as i32,
+            descent: self.scale(descent, font_size).floor() as i32,
+        }
+    }
+
+    fn scale(&self, value: i16, font_size: f32) -> f32 {
+        value as f32 * font_size / self.units_per_em as f32
+    }
+}
+
+/// A big-endian byte slice of a font, where reading out of bounds gives `None`.
+#[derive(Clone, Copy)]
+struct Data<'a>(&'a [u8]);
+
+impl<'a> Data<'a> {
+    fn bytes<const N: usize>(&self, offset: usize) -> Option<[u8; N]> {
+        self.0.get(offset..offset + N)?.try_into().ok()
+    }
+
+    fn u16(&self, offset: usize) -> Option<u16> {
+        self.bytes(offset).map(u16::from_be_bytes)
+    }
+
+    fn i16(&self, offset: usize) -> Option<i16> {
+        self.bytes(offset).map(i16::from_be_bytes)
+    }
+
+    fn u32(&self, offset: usize) -> Option<u32> {
+        self.bytes(offset).map(u32::from_be_bytes)
+    }
+
+    fn tag(&self, offset: usize) -> Option<[u8; 4]> {
+        self.bytes(offset)
+    }
+
+    /// The data from `offset`, or `None` for a null offset.
+    fn at(&self, offset: usize) -> Option<Self> {
+        (offset != 0)
+            .then(|| self.0.get(offset..))
+            .flatten()
+            .map(Data)
+    }
+
+    /// The data at the 16-bit offset stored at `offset`.
+    fn offset16(&self, offset: usize) -> Option<Self> {
+        self.at(self.u16(offset)? as usize)
+    }
+
+    /// The table directory of the font, or of the first font of a collection.
+    fn table_directory(&self) -> Option<TableDirectory<'a>> {
+        let font = if self.tag(0)? == *b"ttcf" {
+            Data(self.0.get(self.u32(12)? as usize..)?)
+        } else {
+            *self
+        };
+
+        Some(TableDirectory {
+            file: self.0,
+            font,
+            count: font.u16(4)? as usize,
+        })
+    }
+
+    /// The ideographic em box in the horizontal axis of a `BASE` table,
+    /// from the `idtp` and `ideo` baselines.
+    fn ideographic_em_box(&self, units_per_em: u16) -> Option<(i16, i16)> {
+        let axis = self.offset16(4)?;
+        let tags = axis.offset16(0)?;
+        let scripts = axis.offset16(2)?;
+
+        let tag_index =
+            |tag: &[u8; 4]| (0..tags.u16(0)? as usize).find(|&i| tags.tag(2 + i * 4) == Some(*tag));
+        let bottom_index = tag_index(b"ideo")?;
+        let top_index = tag_index(b"idtp");
+
+        // prefer the baselines of Han script, as they may differ between scripts
+        let script_count = scripts.u16(0)? as usize;
+        let script = [b"hani", b"kana", b"hang", b"DFLT"]
+            .into_iter()
+            .find_map(|tag| (0..script_count).find(|&i| scripts.tag(2 + i * 6) == Some(*tag)))
+            .or((script_count > 0).then_some(0))?;
+        let script = scripts.offset16(2 + script * 6 + 4)?;
+        let values = script.offset16(0)?;
+
+        let coordinate = |index: usize| {
+            (index < values.u16(2)? as usize)
+                .then(|| values.offset16(4 + index * 2))
+                .flatten()?
+                .i16(2)
+        };
+        let bottom = coordinate(bottom_index)?;
+        // the em box is one em tall if its top is not given
+        let top = top_index
+            .and_then(coordinate)
+            .unwrap_or_else(|| bottom.saturating_add_unsigned(units_per_em));
+
+        Some((top, bottom))
+    }
+}
+
+struct TableDirectory<'a> {
+    file: &'a [u8],
+    font: Data<'a>,
+    count: usize,
+}
+
+impl<'a> TableDirectory<'a> {
+    /// The table of `tag`, whose offset is from the start of the file.
+    fn find(&self, tag: &[u8; 4]) -> Option<Data<'a>> {
+        (0..self.count)
+            .map(|i| 12 + i * 16)
+            .find(|&record| self.font.tag(record) == Some(*tag))
+            .and_then(|record| {
+                let offset = self.font.u32(record + 8)? as usize;
+                let length = self.font.u32(record + 12)? as usize;
+                self.file.get(offset..offset.checked_add(length)?).map(Data)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A font file with a single table.
+    fn font(tag: &[u8; 4], table: &[u8]) -> Vec<u8> {
+        let mut data = vec![0, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+        data.extend(tag);
+        data.extend([0; 4]);
+        data.extend(28u32.to_be_bytes());
+        data.extend((table.len() as u32).to_be_bytes());
+        data.extend(table);
+        data
+    }
+
+    fn words(values: &[i32]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|&value| (value as i16).to_be_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn test_typo_metrics() {
+        let mut os2 = vec![0; 78];
+        os2[68..74].copy_from_slice(&words(&[880, -120, 100]));
+
+        let tables = FontTables::read(&font(b"OS/2", &os2));
+
+        assert_eq!(
+            tables.typo(100.),
+            Some((
+                VerticalExtent {
+                    ascent: 88,
+                    descent: -12,
+                },
+                10
+            ))
+        );
+        assert_eq!(tables.ideographic_em_box(100.), None);
+    }
+
+    #[test]
+    fn test_ideographic_em_box() {
+        #[rustfmt::skip]
+        let base = [
+            // header: version 1.0, the horizontal axis at 8
+            words(&[1, 0, 8, 0]),
+            // axis: tags at 4, scripts at 14 from the axis
+            words(&[4, 14]),
+            // tags: ideo, romn
+            words(&[2]), b"ideoromn".to_vec(),
+            // scripts: hani at 8 from the list
+            words(&[1]), b"hani".to_vec(), words(&[8]),
+            // script: values at 6
+            words(&[6, 0, 0]),
+            // values: two coordinates at 8 and 12
+            words(&[1, 2, 8, 12]),
+            // coordinates of format 1
+            words(&[1, -120, 1, 0]),
+        ]
+        .concat();
+
+        let tables = FontTables::read(&font(b"BASE", &base));
+
+        assert_eq!(
+            tables.ideographic_em_box(100.),
+            Some(VerticalExtent {
+                ascent: 88,
+                descent: -12,
+            })
+        );
+        assert_eq!(tables.typo(100.), None);
+    }
+
+    #[test]
+    fn test_malformed_tables() {
+        let tables = FontTables::read(&font(b"BASE", &words(&[1, 0, 200])));
+
+        assert_eq!(tables.ideographic_em_box(100.), None);
+        assert_eq!(FontTables::read(&[0, 1]).typo(100.), None);
+    }
+}
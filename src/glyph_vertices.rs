@@ -1,4 +1,9 @@
-use crate::layout::Vertex;
+use std::ops::Range;
+
+use crate::{
+    layout::Vertex,
+    parser::{SegmentId, SpanId},
+};
 
 /// Represents the vertices of a glyph, which consists of three layers: the shadow, the stroke, and the fill.\
 /// It is recommanded to draw the layers in the order of shadow, stroke, and fill.
@@ -26,4 +31,17 @@ pub struct GlyphVertices {
     pub height: u32,
     /// the scale ratio of the glyph
     pub scale_ratio: f32,
+    /// the segment of the source which the glyph comes from
+    pub segment_id: Option<SegmentId>,
+    /// the byte range in the content of the source segment of the characters drawn by the glyph,
+    /// including the doubled tag characters of escapes
+    pub byte_range: Range<usize>,
+    /// the index of the text span which the glyph comes from
+    pub span_index: usize,
+    /// the id of the text span which the glyph comes from
+    pub span_id: Option<SpanId>,
+    /// the index of the text run in its text span
+    pub run_index: usize,
+    /// the time in seconds when the glyph starts to appear in a typewriter reveal
+    pub reveal_time: f32,
 }
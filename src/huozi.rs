@@ -2,11 +2,13 @@ use anyhow::Result;
 use image::{DynamicImage, RgbaImage};
 use log::{info, warn};
 use lru::LruCache;
+use std::collections::HashMap;
 use std::num::NonZeroUsize;
 use std::path::Path;
 
-use crate::constant::{BUFFER, CUTOFF, FONT_SIZE, GRID_SIZE, RADIUS, TEXTURE_SIZE};
-use crate::font_extractor::{GlyphExtractor, GlyphExtractorTrait, GlyphMetrics};
+use crate::config::{ConfigError, HuoziConfig};
+use crate::font_extractor::{FontHMetrics, GlyphExtractor, GlyphExtractorTrait, GlyphMetrics};
+use crate::parser::{TagHandler, TagRegistry, VariableResolver};
 #[cfg(feature = "sdf")]
 use crate::sdf::TinySDF;
 
@@ -15,6 +17,8 @@ pub use crate::layout::ColorSpace;
 #[derive(Debug, Clone, Default)]
 pub struct Glyph {
     pub ch: char,
+    /// index of the loaded font which the glyph is rasterized from
+    pub font_index: usize,
     pub metrics: GlyphMetrics,
     pub page: i32,
     pub index: u32,
@@ -25,13 +29,35 @@ pub struct Glyph {
     pub v_max: f32,
 }
 
+/// The key of a glyph in the cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum GlyphKey {
+    /// a character with the index of the font it is rasterized from
+    Char(usize, char),
+    /// a shaped glyph id with the index of the font it belongs to
+    Id(usize, u16),
+}
+
 pub struct Huozi {
+    config: HuoziConfig,
+    /// all loaded fonts, including the fallback chain and the registered font families
     #[cfg(feature = "sdf")]
-    extractor: GlyphExtractor,
+    extractors: Vec<GlyphExtractor>,
+    /// raw data of `extractors`, used for text shaping
+    #[cfg(feature = "shaping")]
+    font_data: Vec<Vec<u8>>,
+    /// indices of `extractors` in fallback order, the first one is the primary font
+    fallbacks: Vec<usize>,
+    /// indices of `extractors` by registered font family name
+    font_families: HashMap<String, usize>,
+    /// handlers of the tags when parsing text
+    tag_registry: TagRegistry,
+    /// values of the `var` tags when parsing text
+    variable_resolver: Option<Box<dyn VariableResolver>>,
     #[cfg(feature = "sdf")]
     tiny_sdf: TinySDF,
     image: RgbaImage,
-    cache: lru::LruCache<char, Glyph>,
+    cache: lru::LruCache<GlyphKey, Glyph>,
     next_grid_index: u32,
     /// increase this flag when the cache is changed
     image_version: u64,
@@ -43,142 +69,295 @@ unsafe impl Send for Huozi {}
 unsafe impl Sync for Huozi {}
 
 impl Huozi {
+    /// Create a new instance with the default atlas parameters.
     pub fn new(font_data: Vec<u8>) -> Self {
-        let extractor = GlyphExtractor::new(font_data, FONT_SIZE as f32);
+        Self::with_config(font_data, HuoziConfig::default()).expect("the default config is valid")
+    }
+
+    /// Create a new instance with custom atlas parameters, which are checked by
+    /// [`HuoziConfig::validate`].
+    pub fn with_config(font_data: Vec<u8>, config: HuoziConfig) -> Result<Self, ConfigError> {
+        Self::with_fonts(vec![font_data], config)
+    }
 
-        info!("font metrics: {:?}", extractor.font_metrics());
+    /// Create a new instance with a font fallback chain.
+    ///
+    /// For each character, the glyph is rasterized from the first font which contains it.
+    /// All fonts are rasterized at the same em size, so glyphs from different fonts share
+    /// the same baseline on a line.
+    ///
+    /// Returns an error if the atlas parameters are not valid, see [`HuoziConfig::validate`].
+    ///
+    /// # Panics
+    /// Panics if `fonts` is empty.
+    pub fn with_fonts(fonts: Vec<Vec<u8>>, config: HuoziConfig) -> Result<Self, ConfigError> {
+        assert!(!fonts.is_empty(), "at least one font is required");
+        config.validate()?;
+
+        #[cfg(feature = "shaping")]
+        let font_data = fonts.clone();
+
+        let extractors = fonts
+            .into_iter()
+            .map(|font_data| GlyphExtractor::new(font_data, config.font_size as f32))
+            .collect::<Vec<_>>();
+
+        for (i, extractor) in extractors.iter().enumerate() {
+            info!("font #{} metrics: {:?}", i, extractor.font_metrics());
+        }
 
-        let mut image = DynamicImage::new_rgba8(TEXTURE_SIZE, TEXTURE_SIZE).to_rgba8();
+        let mut image =
+            DynamicImage::new_rgba8(config.texture_size, config.texture_size).to_rgba8();
 
         image.fill(0);
 
         #[cfg(feature = "sdf")]
-        let tiny_sdf = TinySDF::new(GRID_SIZE as u32, BUFFER as u32, RADIUS, CUTOFF);
-
-        let cache = LruCache::new(
-            NonZeroUsize::new((TEXTURE_SIZE / GRID_SIZE as u32).pow(2) as usize * 4).unwrap(),
+        let tiny_sdf = TinySDF::new(
+            config.grid_size as u32,
+            config.buffer as u32,
+            config.radius,
+            config.cutoff,
         );
 
-        Self {
+        // a valid config has at least one grid in the texture
+        let cache = LruCache::new(NonZeroUsize::new(config.cache_capacity()).unwrap());
+
+        Ok(Self {
+            config,
+            fallbacks: (0..extractors.len()).collect(),
+            font_families: HashMap::new(),
+            tag_registry: TagRegistry::default(),
+            variable_resolver: None,
             #[cfg(feature = "sdf")]
-            extractor,
+            extractors,
+            #[cfg(feature = "shaping")]
+            font_data,
             #[cfg(feature = "sdf")]
             tiny_sdf,
             image,
             cache,
             next_grid_index: 0,
             image_version: 0,
-        }
+        })
+    }
+
+    /// Register a named font family, which can be selected by `TextStyle::font_family`
+    /// or the `[font=...]` tag. Returns the index of the font family.
+    ///
+    /// Characters missing in the font family fall back to the fallback chain.
+    /// Registering an existing name replaces the previous font for glyphs rasterized afterwards.
+    #[cfg(feature = "sdf")]
+    pub fn register_font<S: Into<String>>(&mut self, name: S, font_data: Vec<u8>) -> usize {
+        #[cfg(feature = "shaping")]
+        self.font_data.push(font_data.clone());
+        let extractor = GlyphExtractor::new(font_data, self.config.font_size as f32);
+        let index = self.extractors.len();
+        self.extractors.push(extractor);
+        self.font_families.insert(name.into(), index);
+        index
+    }
+
+    /// Get the index of a registered font family by its name.
+    pub fn font_family(&self, name: &str) -> Option<usize> {
+        self.font_families.get(name).copied()
+    }
+
+    /// Register the handler of a custom tag for parsing text, such as `[emotion=angry]`.
+    ///
+    /// Registering the name of a built-in tag replaces its handler.
+    pub fn register_tag<S: Into<String>>(&mut self, name: S, handler: impl TagHandler + 'static) {
+        self.tag_registry.register(name, handler);
+    }
+
+    /// The handlers of the tags for parsing text.
+    pub fn tag_registry(&self) -> &TagRegistry {
+        &self.tag_registry
+    }
+
+    /// Set the resolver of the variables in `[var=name]` tags, such as the name of the player.
+    ///
+    /// Without a resolver, every variable is unknown and reported as a diagnostic.
+    pub fn set_variable_resolver(&mut self, resolver: impl VariableResolver + 'static) {
+        self.variable_resolver = Some(Box::new(resolver));
+    }
+
+    /// The resolver of the variables in `[var=name]` tags for parsing text.
+    pub fn variable_resolver(&self) -> Option<&dyn VariableResolver> {
+        self.variable_resolver.as_deref()
     }
 
     #[cfg(feature = "sdf")]
     pub fn get_glyph(&mut self, ch: char) -> &Glyph {
-        if self.cache.contains(&ch) {
-            self.cache.get(&ch).unwrap()
-        } else {
-            if !self.extractor.exist(ch) {
-                warn!("glyph for character `{}` ({:#x}) is not found in the current font or font fallbacks, which may lead to unexpected rendering result.", ch, ch as u16);
-            }
+        self.get_font_glyph(None, ch)
+    }
 
-            let (bitmap, metrics) = self.extractor.get_bitmap_and_metrics(ch);
+    /// Get the index of the first font containing the character, trying the given
+    /// font family index before the fallback chain.
+    #[cfg(feature = "sdf")]
+    pub(crate) fn resolve_font(&self, font_family: Option<usize>, ch: char) -> Option<usize> {
+        font_family
+            .into_iter()
+            .chain(self.fallbacks.iter().copied())
+            .find(|&index| self.extractors[index].exist(ch))
+    }
 
-            // Determine how many grids are needed for this glyph
-            //
-            // Currently we only support horizontal expansion,
-            // so the grid_count is always 1.
-            let grid_count = if metrics.width as f64 > FONT_SIZE {
-                ((metrics.width as f64) / FONT_SIZE + 0.5) as u32
-            } else {
-                1
-            };
+    /// The index of the primary font in the fallback chain.
+    pub(crate) fn primary_font(&self) -> usize {
+        self.fallbacks[0]
+    }
 
-            let (bitmap, width, height) =
-                self.tiny_sdf
-                    .calculate(&bitmap, metrics.width, metrics.height, grid_count);
-
-            // get a zero-valued Glyph and push to cache, which may expire a exising glyph
-            let glyph = Glyph {
-                ch,
-                metrics,
-                page: 0,
-                index: 0,
-                grid_count: 0,
-                u_min: 0.,
-                u_max: 0.,
-                v_min: 0.,
-                v_max: 0.,
-            };
+    /// Raw data of a loaded font, used for text shaping.
+    #[cfg(feature = "shaping")]
+    pub(crate) fn font_data(&self, font_index: usize) -> &[u8] {
+        &self.font_data[font_index]
+    }
 
-            let grid_size = GRID_SIZE as i32;
+    /// Get the glyph of a character, preferring the given font family index
+    /// (see [`Huozi::font_family`]) before the fallback chain.
+    #[cfg(feature = "sdf")]
+    pub fn get_font_glyph(&mut self, font_family: Option<usize>, ch: char) -> &Glyph {
+        let font_index = self.resolve_font(font_family, ch).unwrap_or_else(|| {
+            warn!(
+                "glyph for character `{}` ({:#x}) is not found in the current font or font fallbacks, which may lead to unexpected rendering result.",
+                ch, ch as u32
+            );
+            font_family.unwrap_or(self.primary_font())
+        });
+
+        // keyed by the resolved font, so a fallback glyph is cached once for all families
+        let key = GlyphKey::Char(font_index, ch);
+        if self.cache.contains(&key) {
+            self.cache.get(&key).unwrap()
+        } else {
+            let (bitmap, metrics) = self.extractors[font_index].get_bitmap_and_metrics(ch);
 
-            let line_count = self.image.width() as i32 / grid_size;
+            self.insert_glyph(key, ch, font_index, bitmap, metrics)
+        }
+    }
 
-            let (page, index_in_page, overwrite) =
-                if let Some((_, expired_glyph)) = self.cache.push(ch, glyph) {
-                    (expired_glyph.page, expired_glyph.index, true)
-                } else {
-                    let page = self.next_grid_index as i32 / (line_count * line_count);
-                    let index_in_page = self.next_grid_index as i32 % (line_count * line_count);
+    /// Get the glyph by its id in a loaded font, e.g. from a text shaper.
+    /// `ch` is the first character of the cluster which the glyph is shaped from.
+    #[cfg(feature = "sdf")]
+    pub fn get_glyph_by_id(&mut self, font_index: usize, glyph_id: u16, ch: char) -> &Glyph {
+        let key = GlyphKey::Id(font_index, glyph_id);
+        if self.cache.contains(&key) {
+            self.cache.get(&key).unwrap()
+        } else {
+            let (bitmap, metrics) =
+                self.extractors[font_index].get_bitmap_and_metrics_by_id(glyph_id);
+
+            self.insert_glyph(key, ch, font_index, bitmap, metrics)
+        }
+    }
+
+    /// Generate the SDF of a glyph bitmap and put it into the texture and the cache.
+    #[cfg(feature = "sdf")]
+    fn insert_glyph(
+        &mut self,
+        key: GlyphKey,
+        ch: char,
+        font_index: usize,
+        bitmap: Vec<u8>,
+        metrics: GlyphMetrics,
+    ) -> &Glyph {
+        // Determine how many grids are needed for this glyph
+        //
+        // Currently we only support horizontal expansion,
+        // so the grid_count is always 1.
+        let font_size = self.config.font_size;
+        let grid_count = if metrics.width as f64 > font_size {
+            ((metrics.width as f64) / font_size + 0.5) as u32
+        } else {
+            1
+        };
+
+        let (bitmap, width, height) =
+            self.tiny_sdf
+                .calculate(&bitmap, metrics.width, metrics.height, grid_count);
+
+        // get a zero-valued Glyph and push to cache, which may expire a exising glyph
+        let glyph = Glyph {
+            ch,
+            font_index,
+            metrics,
+            page: 0,
+            index: 0,
+            grid_count: 0,
+            u_min: 0.,
+            u_max: 0.,
+            v_min: 0.,
+            v_max: 0.,
+        };
+
+        let grid_size = self.config.grid_size as i32;
+
+        let line_count = self.image.width() as i32 / grid_size;
+
+        let (page, index_in_page, overwrite) =
+            if let Some((_, expired_glyph)) = self.cache.push(key, glyph) {
+                (expired_glyph.page, expired_glyph.index, true)
+            } else {
+                let page = self.next_grid_index as i32 / (line_count * line_count);
+                let index_in_page = self.next_grid_index as i32 % (line_count * line_count);
 
-                    // advance the next grid index by grid_count
-                    self.next_grid_index += grid_count;
+                // advance the next grid index by grid_count
+                self.next_grid_index += grid_count;
 
-                    (page, index_in_page as u32, false)
-                };
+                (page, index_in_page as u32, false)
+            };
 
-            // the next empty texture block, aligned by grid size
-            let grid_x = grid_size * (index_in_page as i32 % line_count as i32);
-            let grid_y = grid_size * (index_in_page as i32 / line_count as i32);
+        // the next empty texture block, aligned by grid size
+        let grid_x = grid_size * (index_in_page as i32 % line_count);
+        let grid_y = grid_size * (index_in_page as i32 / line_count);
 
-            // clear the block if it's reused from an expired glyph
-            if overwrite {
-                for y in grid_y..grid_y + grid_size {
-                    for x in grid_x..grid_x + grid_size {
-                        let pixel = self.image.get_pixel_mut(x as u32, y as u32);
-                        pixel.0[page as usize] = 0;
-                    }
+        // clear the block if it's reused from an expired glyph
+        if overwrite {
+            for y in grid_y..grid_y + grid_size {
+                for x in grid_x..grid_x + grid_size {
+                    let pixel = self.image.get_pixel_mut(x as u32, y as u32);
+                    pixel.0[page as usize] = 0;
                 }
             }
+        }
 
-            let offset_x =
-                grid_x + ((GRID_SIZE * grid_count as f64) / 2. - width as f64 / 2.).ceil() as i32;
-            let offset_y = grid_y + (GRID_SIZE / 2. - height as f64 / 2.).ceil() as i32;
+        let offset_x = grid_x
+            + ((grid_size as f64 * grid_count as f64) / 2. - width as f64 / 2.).ceil() as i32;
+        let offset_y = grid_y + (grid_size as f64 / 2. - height as f64 / 2.).ceil() as i32;
 
-            let len = bitmap.len() as i32;
+        let len = bitmap.len() as i32;
 
-            for i in 0..len {
-                let x = i % (width as i32) + offset_x;
-                let y = i / (width as i32) + offset_y;
+        for i in 0..len {
+            let x = i % (width as i32) + offset_x;
+            let y = i / (width as i32) + offset_y;
 
-                // Bypass the pixels out of the grid block in case of overflow, though it is unlikely to happen
-                if x < grid_x
-                    || x >= grid_x + grid_size * grid_count as i32
-                    || y <= grid_y
-                    || y >= grid_y + grid_size
-                {
-                    continue;
-                }
+            // Bypass the pixels out of the grid block in case of overflow, though it is unlikely to happen
+            if x < grid_x
+                || x >= grid_x + grid_size * grid_count as i32
+                || y <= grid_y
+                || y >= grid_y + grid_size
+            {
+                continue;
+            }
 
-                let v = bitmap[i as usize];
+            let v = bitmap[i as usize];
 
-                let pixel = self.image.get_pixel_mut(x as u32, y as u32);
-                pixel.0[page as usize] = v;
-            }
+            let pixel = self.image.get_pixel_mut(x as u32, y as u32);
+            pixel.0[page as usize] = v;
+        }
 
-            let texture_width = self.image.width() as f32;
-            let glyph = self.cache.get_mut(&ch).unwrap();
-            glyph.page = page;
-            glyph.index = index_in_page;
-            glyph.grid_count = grid_count;
-            glyph.u_min = grid_x as f32 / texture_width;
-            glyph.v_min = grid_y as f32 / texture_width;
-            glyph.u_max = (grid_x + grid_size * grid_count as i32) as f32 / texture_width;
-            glyph.v_max = (grid_y + grid_size) as f32 / texture_width;
+        let texture_width = self.image.width() as f32;
+        let glyph = self.cache.get_mut(&key).unwrap();
+        glyph.page = page;
+        glyph.index = index_in_page;
+        glyph.grid_count = grid_count;
+        glyph.u_min = grid_x as f32 / texture_width;
+        glyph.v_min = grid_y as f32 / texture_width;
+        glyph.u_max = (grid_x + grid_size * grid_count as i32) as f32 / texture_width;
+        glyph.v_max = (grid_y + grid_size) as f32 / texture_width;
 
-            self.image_version += 1;
+        self.image_version += 1;
 
-            glyph
-        }
+        glyph
     }
 
     #[cfg(feature = "sdf")]
@@ -196,6 +375,23 @@ impl Huozi {
         }
     }
 
+    /// The number of loaded fonts, including the fallback chain and the registered font families.
+    #[cfg(feature = "sdf")]
+    pub fn font_count(&self) -> usize {
+        self.extractors.len()
+    }
+
+    /// The horizontal metrics of a loaded font, in the atlas font size.
+    #[cfg(feature = "sdf")]
+    pub(crate) fn font_metrics(&self, font_index: usize) -> FontHMetrics {
+        self.extractors[font_index].font_metrics()
+    }
+
+    /// The atlas parameters of this instance.
+    pub fn config(&self) -> &HuoziConfig {
+        &self.config
+    }
+
     #[cfg(feature = "sdf")]
     pub fn image_version(&self) -> u64 {
         self.image_version
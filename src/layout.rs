@@ -1,8 +1,17 @@
+mod align;
 mod color_space;
 mod glyph_span;
+mod hit_test;
 mod layout_style;
+mod line;
+mod line_break;
+mod line_info;
 mod punctuation;
+mod reveal;
+mod ruby;
+mod shaping;
 mod vertex;
+mod vertical;
 
 use std::collections::HashMap;
 
@@ -11,32 +20,53 @@ use anyhow::Result;
 
 pub use self::color_space::*;
 pub use self::glyph_span::*;
+pub use self::hit_test::*;
 pub use self::layout_style::*;
+pub use self::line_info::*;
+pub use self::reveal::*;
 pub use self::vertex::*;
 
+use self::vertical::VerticalOrientation;
+
 use crate::{
     Huozi,
-    constant::{ASCENT, FONT_SIZE, GAMMA_COEFFICIENT, GRID_SIZE, VIEWPORT_HEIGHT, VIEWPORT_WIDTH},
+    constant::{GAMMA_COEFFICIENT, VIEWPORT_HEIGHT, VIEWPORT_WIDTH},
     glyph_vertices::GlyphVertices,
-    parser::parse,
 };
 
+/// The laid out text.
+#[derive(Debug, Clone)]
+pub struct LayoutResult {
+    /// the vertices of each glyph, in the order of the text
+    pub glyphs: Vec<GlyphVertices>,
+    /// the glyphs of each segment
+    pub segments: Vec<SegmentGlyphSpan>,
+    /// the lines, or the columns in vertical layout
+    pub lines: Vec<LineInfo>,
+    /// the total width of the laid out text
+    pub width: u32,
+    /// the total height of the laid out text
+    pub height: u32,
+    /// the seconds from the start of a typewriter reveal until it ends, when the glyph after
+    /// the text would be revealed, including the pause of `wait` tags at the end of the text
+    pub reveal_duration: f32,
+}
+
 impl Huozi {
-    /// Parse the text into text spans.
+    /// Parse the text into text spans, with the diagnostics of the markup which is ignored,
+    /// such as `[size=abc]`. Malformed markup fails the parsing with a syntax error.
     pub fn parse_text(
         &self,
         segments: &Vec<Segment>,
         initial_text_style: &TextStyle,
-        style_prefabs: Option<&HashMap<String, TextStyle>>,
-    ) -> Result<Vec<TextSpan>, String> {
-        let elements = segments
-            .iter()
-            .map(|segment| parse(segment))
-            .collect::<Result<Vec<Vec<Element>>, String>>()?
-            .into_iter()
-            .flatten()
-            .collect();
-        to_spans(elements, initial_text_style, style_prefabs)
+        style_prefabs: Option<&HashMap<String, StylePrefab>>,
+    ) -> Result<(Vec<TextSpan>, Vec<Diagnostic>), ParseError> {
+        self.parse_text_with_config(
+            segments,
+            initial_text_style,
+            style_prefabs,
+            &ParserConfig::default(),
+        )
     }
 
     /// Parse the text with custom open and close tag characters.
@@ -44,29 +74,86 @@ impl Huozi {
         &self,
         segments: &Vec<Segment>,
         initial_text_style: &TextStyle,
-        style_prefabs: Option<&HashMap<String, TextStyle>>,
-    ) -> Result<Vec<TextSpan>, String> {
-        let elements = segments
+        style_prefabs: Option<&HashMap<String, StylePrefab>>,
+    ) -> Result<(Vec<TextSpan>, Vec<Diagnostic>), ParseError> {
+        self.parse_text_with_config(
+            segments,
+            initial_text_style,
+            style_prefabs,
+            &ParserConfig::with_delimiters(OPEN, CLOSE),
+        )
+    }
+
+    /// Parse the text with the tag characters and void tags in the parser config, recovering
+    /// from malformed markup if the config is lenient.
+    pub fn parse_text_with_config(
+        &self,
+        segments: &Vec<Segment>,
+        initial_text_style: &TextStyle,
+        style_prefabs: Option<&HashMap<String, StylePrefab>>,
+        parser_config: &ParserConfig,
+    ) -> Result<(Vec<TextSpan>, Vec<Diagnostic>), ParseError> {
+        let mut syntax_diagnostics = vec![];
+        let parsed_segments = segments
             .iter()
-            .map(|segment| parse_with::<OPEN, CLOSE>(segment))
-            .collect::<Result<Vec<Vec<Element>>, String>>()?
-            .into_iter()
-            .flatten()
-            .collect();
-        to_spans(elements, initial_text_style, style_prefabs)
+            .map(|segment| {
+                if parser_config.lenient {
+                    let (elements, diagnostics) = parse_lenient_with_config(segment, parser_config);
+                    syntax_diagnostics.extend(diagnostics);
+                    Ok((segment, elements))
+                } else {
+                    Ok((segment, parse_with_config(segment, parser_config)?))
+                }
+            })
+            .collect::<Result<Vec<_>, ParseError>>()?;
+
+        let (text_spans, diagnostics) = to_spans_with_config(
+            parsed_segments,
+            initial_text_style,
+            style_prefabs,
+            parser_config,
+            self.tag_registry(),
+            self.variable_resolver(),
+        );
+        syntax_diagnostics.extend(diagnostics);
+        Ok((text_spans, syntax_diagnostics))
+    }
+
+    /// Convert elements into text spans, such as the elements from an importer like
+    /// [`import_html`](crate::parser::import_html), each with the segment it is parsed or
+    /// imported from. The diagnostics are located in the content of their segments.
+    pub fn elements_to_spans(
+        &self,
+        parsed_segments: Vec<(&Segment, Vec<Element>)>,
+        initial_text_style: &TextStyle,
+        style_prefabs: Option<&HashMap<String, StylePrefab>>,
+    ) -> (Vec<TextSpan>, Vec<Diagnostic>) {
+        to_spans_with_config(
+            parsed_segments,
+            initial_text_style,
+            style_prefabs,
+            &ParserConfig::default(),
+            self.tag_registry(),
+            self.variable_resolver(),
+        )
     }
 
-    /// Parse the text into text spans, then layout into glyph vertices.
+    /// Parse the text into text spans, then layout into glyph vertices,
+    /// returning the diagnostics of the parsing with the layout.
     pub fn layout_parse(
         &mut self,
         segments: &Vec<Segment>,
         layout_style: &LayoutStyle,
         initial_text_style: &TextStyle,
         color_space: ColorSpace,
-        style_prefabs: Option<&HashMap<String, TextStyle>>,
-    ) -> Result<(Vec<GlyphVertices>, Vec<SegmentGlyphSpan>, u32, u32), String> {
-        let text_spans = self.parse_text(segments, initial_text_style, style_prefabs)?;
-        Ok(self.layout(&layout_style, &text_spans, color_space))
+        style_prefabs: Option<&HashMap<String, StylePrefab>>,
+    ) -> Result<(LayoutResult, Vec<Diagnostic>), ParseError> {
+        let (text_spans, diagnostics) =
+            self.parse_text(segments, initial_text_style, style_prefabs)?;
+        Ok((
+            self.layout(layout_style, &text_spans, color_space),
+            diagnostics,
+        ))
     }
 
     /// Parse the text with custom open and close tag characters, then layout into glyph vertices.
@@ -76,21 +163,69 @@ impl Huozi {
         layout_style: &LayoutStyle,
         initial_text_style: &TextStyle,
         color_space: ColorSpace,
-        style_prefabs: Option<&HashMap<String, TextStyle>>,
-    ) -> Result<(Vec<GlyphVertices>, Vec<SegmentGlyphSpan>, u32, u32), String> {
-        let text_spans =
-            self.parse_text_with::<OPEN, CLOSE>(segments, initial_text_style, style_prefabs)?;
-        Ok(self.layout(&layout_style, &text_spans, color_space))
+        style_prefabs: Option<&HashMap<String, StylePrefab>>,
+    ) -> Result<(LayoutResult, Vec<Diagnostic>), ParseError> {
+        self.layout_parse_with_config(
+            segments,
+            layout_style,
+            initial_text_style,
+            color_space,
+            style_prefabs,
+            &ParserConfig::with_delimiters(OPEN, CLOSE),
+        )
     }
 
-    /// Layout the text into glyph vertices without parsing.
+    /// Parse the text with the parser config, then layout into glyph vertices.
+    pub fn layout_parse_with_config(
+        &mut self,
+        segments: &Vec<Segment>,
+        layout_style: &LayoutStyle,
+        initial_text_style: &TextStyle,
+        color_space: ColorSpace,
+        style_prefabs: Option<&HashMap<String, StylePrefab>>,
+        parser_config: &ParserConfig,
+    ) -> Result<(LayoutResult, Vec<Diagnostic>), ParseError> {
+        let (text_spans, diagnostics) = self.parse_text_with_config(
+            segments,
+            initial_text_style,
+            style_prefabs,
+            parser_config,
+        )?;
+        Ok((
+            self.layout(layout_style, &text_spans, color_space),
+            diagnostics,
+        ))
+    }
+
+    /// Convert elements into text spans as [`Huozi::elements_to_spans`], then layout into
+    /// glyph vertices, returning the diagnostics of the conversion with the layout.
+    pub fn layout_elements(
+        &mut self,
+        parsed_segments: Vec<(&Segment, Vec<Element>)>,
+        layout_style: &LayoutStyle,
+        initial_text_style: &TextStyle,
+        color_space: ColorSpace,
+        style_prefabs: Option<&HashMap<String, StylePrefab>>,
+    ) -> (LayoutResult, Vec<Diagnostic>) {
+        let (text_spans, diagnostics) =
+            self.elements_to_spans(parsed_segments, initial_text_style, style_prefabs);
+        (
+            self.layout(layout_style, &text_spans, color_space),
+            diagnostics,
+        )
+    }
+
+    /// Layout the text into glyph vertices without parsing, so markup is laid out as text.
+    ///
+    /// It does not fail at present, but returns the same error as [`Huozi::layout_parse`],
+    /// so both can be handled alike.
     pub fn layout_plain(
         &mut self,
         segments: &Vec<Segment>,
         layout_style: &LayoutStyle,
         initial_text_style: &TextStyle,
         color_space: ColorSpace,
-    ) -> Result<(Vec<GlyphVertices>, Vec<SegmentGlyphSpan>, u32, u32), String> {
+    ) -> Result<LayoutResult, ParseError> {
         let text_spans = segments
             .iter()
             .map(|segment| TextSpan {
@@ -103,55 +238,109 @@ impl Huozi {
                         start: 0,
                         end: segment.content.len(),
                     },
+                    ruby: None,
+                    source_map: vec![],
+                    reveal_delay: 0.,
+                    marker: None,
                 }],
             })
             .collect::<Vec<_>>();
 
-        Ok(self.layout(&layout_style, &text_spans, color_space))
+        Ok(self.layout(layout_style, &text_spans, color_space))
     }
 
     /// Layout the given text spans into glyph vertices according to the layout style and color space.
-    pub fn layout<'a, T: AsRef<Vec<TextSpan>>>(
+    pub fn layout<T: AsRef<Vec<TextSpan>>>(
         &mut self,
         layout_style: &LayoutStyle,
         text_spans: T,
         color_space: ColorSpace,
-    ) -> (Vec<GlyphVertices>, Vec<SegmentGlyphSpan>, u32, u32) {
+    ) -> LayoutResult {
+        let base_font_size = self.config().font_size;
+        let grid_size = self.config().grid_size;
+
+        let is_vertical = layout_style.direction == LayoutDirection::Vertical;
+
+        // `x` is measured along the text flow and `y` across the lines (columns in vertical layout),
+        // they are mapped onto the screen when the vertices are calculated.
         let mut total_width: f64 = 0.;
         let mut total_height: f64 = 0.;
 
-        let first_run = text_spans
-            .as_ref()
-            .first()
-            .and_then(|span| span.runs.first());
-
         let mut current_x = 0.;
         let mut current_y = 0.;
         let mut is_new_line = true;
+        // the pen is measured in the font units of the current run
+        let mut current_scale_ratio = 1.;
+        // the font size and line height of the current run, for an empty line after the text
+        let mut current_line_box = (0., 0.);
+        let mut overflowed = false;
 
         let mut current_col: u32 = 0;
         let mut current_row: u32 = 0;
         let mut previous_char_on_line: Option<char> = None;
         let mut line_has_hanging_punctuation = false;
 
-        let max_width = layout_style.box_width;
-        let max_height = layout_style.box_height;
+        let (max_width, max_height) = match layout_style.direction {
+            LayoutDirection::Horizontal => (layout_style.box_width, layout_style.box_height),
+            LayoutDirection::Vertical => (layout_style.box_height, layout_style.box_width),
+        };
+
+        // glyphs are placed on the baseline of their fonts, by the ascent and descent
+        // in the atlas font size
+        let font_extents = (0..self.font_count())
+            .map(|font_index| {
+                line::font_extent(&self.font_metrics(font_index), layout_style.line_metrics)
+            })
+            .collect::<Vec<_>>();
 
         let mut glyph_vertices_vec = vec![];
         let mut segment_glyph_spans = vec![];
+        // placements and lines of glyphs, used to align lines after wrapping
+        let mut placements = vec![];
+        let mut lines = vec![];
+        let mut line_start: usize = 0;
+        let mut rubies: Vec<ruby::RubyBase> = vec![];
         let mut current_segment_id: Option<SegmentId> = None;
         let mut current_segment_range_start: usize = 0;
+        // the time to reveal the next glyph, advanced by each glyph at the speed of its run
+        let mut reveal_clock = 0.;
 
-        'out: for span in text_spans.as_ref() {
+        'out: for (span_index, span) in text_spans.as_ref().iter().enumerate() {
             let text_runs = &span.runs;
 
             // preallocate memory for vertices and indices
             glyph_vertices_vec.reserve(text_runs.iter().map(|s| s.text.len()).sum());
 
-            for run in text_runs {
+            for (run_index, run) in text_runs.iter().enumerate() {
                 let style = &run.style;
                 let text = &run.text;
+
+                let scale_ratio = style.font_size / base_font_size;
+                current_x *= current_scale_ratio / scale_ratio;
+                current_y *= current_scale_ratio / scale_ratio;
+                current_scale_ratio = scale_ratio;
+                current_line_box = (style.font_size, style.font_size * style.line_height);
+                reveal_clock += run.reveal_delay;
+
                 let segment_id = &run.source_range.segment_id;
+                let font_family = style.font_family.as_deref().and_then(|name| {
+                    let font_family = self.font_family(name);
+                    if font_family.is_none() {
+                        log::warn!("font family `{}` is not registered, ignored.", name);
+                    }
+                    font_family
+                });
+                let ruby_index = run.ruby.as_ref().map(|ruby| {
+                    rubies.push(ruby::RubyBase::new(
+                        ruby,
+                        style,
+                        text,
+                        glyph_vertices_vec.len(),
+                    ));
+                    rubies.len() - 1
+                });
+                let ruby_group =
+                    ruby_index.filter(|&index| rubies[index].ruby.mode == RubyMode::Group);
 
                 if segment_id != &current_segment_id {
                     // save previous segment span
@@ -177,7 +366,8 @@ impl Huozi {
                 // 2.0 should be enough
                 let fill_buffer = 2.;
                 // 0.6 is a magic number, to enable anti-aliasing
-                let gamma = GAMMA_COEFFICIENT * 0.6 / 2. / (style.font_size / FONT_SIZE) as f32;
+                let gamma =
+                    GAMMA_COEFFICIENT * 0.6 / 2. / (style.font_size / base_font_size) as f32;
                 let fill_color = get_color_value(&style.fill_color, &color_space);
 
                 let StrokeStyle {
@@ -195,27 +385,36 @@ impl Huozi {
                 } = style.shadow.clone().unwrap_or_default();
                 let shadow_color = get_color_value(&shadow_color, &color_space);
 
-                // total size of this run in FONT_SIZE, so it must be scaled to font size later.
+                // total size of this run in base_font_size, so it must be scaled to font size later.
                 let mut total_width_of_run: f64 = 0.;
                 let mut _total_height_of_run: f64 = 0.;
 
-                for ch in text.chars() {
-                    let glyph = self.get_glyph(ch);
+                for unit in self.layout_units(text, font_family, layout_style) {
+                    let ch = unit.ch;
+                    let glyph = match unit.glyph_id {
+                        // a soft hyphen is drawn as a hyphen if the line breaks after it
+                        _ if ch == line_break::SOFT_HYPHEN => self.get_font_glyph(font_family, '-'),
+                        Some((font_index, glyph_id)) => {
+                            self.get_glyph_by_id(font_index, glyph_id, ch)
+                        }
+                        None => self.get_font_glyph(font_family, ch),
+                    };
                     let metrics = &glyph.metrics;
 
                     if is_new_line {
-                        current_x = style.indent * FONT_SIZE;
+                        current_x = style.indent * base_font_size;
                         is_new_line = false;
                     }
 
                     // handles line break
-                    if glyph.ch == '\n' || glyph.ch == '\r' {
+                    if ch == '\n' || ch == '\r' {
                         // update actual width
                         total_width_of_run = total_width_of_run.max(current_x);
                         // reset x
                         current_x = 0.;
                         // use original font size (when grid size is 64), it will be scaled in offset_y later.
-                        current_y += FONT_SIZE * style.line_height;
+                        let line_top = current_y * style.font_size / base_font_size;
+                        current_y += base_font_size * style.line_height;
                         // mark new line
                         is_new_line = true;
 
@@ -224,73 +423,175 @@ impl Huozi {
                         previous_char_on_line = None;
                         line_has_hanging_punctuation = false;
 
+                        lines.push(line::Line::new(
+                            line_start..glyph_vertices_vec.len(),
+                            LineBreak::Hard,
+                            line_top,
+                            &placements,
+                            (style.font_size, style.font_size * style.line_height),
+                        ));
+                        line_start = glyph_vertices_vec.len();
+
                         // if text overflows the box, ignore the rest characters
-                        if max_height
-                            .is_some_and(|height| current_y / FONT_SIZE * style.font_size >= height)
-                        {
-                            total_width =
-                                total_width.max(total_width_of_run / FONT_SIZE * style.font_size);
+                        if max_height.is_some_and(|height| {
+                            current_y / base_font_size * style.font_size >= height
+                        }) {
+                            total_width = total_width
+                                .max(total_width_of_run / base_font_size * style.font_size);
                             total_height = max_height.unwrap();
+                            overflowed = true;
                             break 'out;
                         }
 
                         // update actual height to current_y with additional a line
-                        _total_height_of_run = current_y + FONT_SIZE * style.line_height;
+                        _total_height_of_run = current_y + base_font_size * style.line_height;
 
                         continue;
                     }
 
-                    let mut h_advance = metrics.h_advance as f64;
+                    let mut h_advance = if ch == line_break::SOFT_HYPHEN {
+                        0.
+                    } else {
+                        unit.h_advance.unwrap_or(metrics.h_advance as f64)
+                    };
+                    let orientation = unit.orientation;
+                    // fonts without vertical metrics report zero, so fall back to 1em.
+                    let v_advance = if metrics.v_advance > 0. {
+                        metrics.v_advance as f64
+                    } else {
+                        base_font_size
+                    } + unit.v_advance_delta;
+                    let advance = |h_advance: f64| match orientation {
+                        Some(VerticalOrientation::Upright) => v_advance,
+                        _ => h_advance,
+                    };
 
-                    let punctuation_compression = if layout_style.direction
-                        == LayoutDirection::Horizontal
-                        && layout_style.punctuation.compression
-                    {
-                        punctuation::compression_between(previous_char_on_line, glyph.ch)
-                            * FONT_SIZE
+                    let punctuation_compression = if layout_style.punctuation.compression {
+                        punctuation::compression_between(previous_char_on_line, ch) * base_font_size
                     } else {
                         0.0
                     };
                     let compressed_x = current_x - punctuation_compression;
                     let overflow = max_width
-                        .and_then(|width| {
-                            let max_width_in_font_units = width * FONT_SIZE / style.font_size;
-                            Some(compressed_x + h_advance - max_width_in_font_units)
+                        .map(|width| {
+                            let max_width_in_font_units = width * base_font_size / style.font_size;
+                            compressed_x + advance(h_advance) - max_width_in_font_units
                         })
                         .unwrap_or(0.0);
-                    let can_hang = layout_style.direction == LayoutDirection::Horizontal
-                        && layout_style.punctuation.hanging
+                    let can_hang = layout_style.punctuation.hanging
                         && !line_has_hanging_punctuation
-                        && punctuation::is_hangable(glyph.ch)
+                        && punctuation::is_hangable(ch)
                         && overflow > 0.0
-                        && overflow <= FONT_SIZE * layout_style.punctuation.hanging_tolerance;
+                        && overflow <= base_font_size * layout_style.punctuation.hanging_tolerance;
+                    // spaces always stay at the end of the line, but do not hang
+                    let space_overflows = overflow > 0.0 && line_break::is_space(ch);
+                    let stays_on_line = can_hang || space_overflows;
+
+                    let actual_scale_ratio = style.font_size / base_font_size;
+                    let line_end = placements.last().filter(|_| line_start < placements.len());
+                    let can_break = line_end.is_none_or(|previous: &line::Placement| {
+                        let in_same_ruby =
+                            previous.ruby_group.is_some() && previous.ruby_group == ruby_group;
+                        !in_same_ruby
+                            && !unit.joins_previous
+                            && line_break::can_break_between(previous.ch, ch, layout_style)
+                    });
+                    // squeeze punctuation marks on the line to pull in the character
+                    let pulled_in = if overflow > 0.0
+                        && !stays_on_line
+                        && !can_break
+                        && layout_style.kinsoku.pull
+                    {
+                        line_break::pull_in(
+                            &placements[line_start..],
+                            ch,
+                            overflow * actual_scale_ratio,
+                            layout_style.punctuation.compression,
+                        )
+                    } else {
+                        None
+                    };
 
                     // check text overflow
-                    if overflow > 0.0 && !can_hang {
+                    if let Some(shifts) = pulled_in {
+                        for (index, shift) in (line_start..).zip(shifts) {
+                            line::shift_glyphs(
+                                &mut glyph_vertices_vec[index..=index],
+                                &mut placements[index..=index],
+                                shift,
+                                0.,
+                                &layout_style.direction,
+                            );
+                        }
+                        current_x = compressed_x - overflow;
+                    } else if overflow > 0.0 && !stays_on_line {
+                        // push characters which may not end the line to the next line with this one
+                        let break_index = line_start
+                            + line_break::find_break(
+                                &placements[line_start..],
+                                ch,
+                                ruby_group,
+                                unit.joins_previous,
+                                layout_style,
+                            );
+
                         // update actual width to max width
-                        total_width_of_run = max_width.unwrap() * FONT_SIZE / style.font_size;
+                        total_width_of_run = max_width.unwrap() * base_font_size / style.font_size;
                         // reset x
                         current_x = 0.;
+                        let line_top = current_y * actual_scale_ratio;
                         // use original font size (when grid size is 64), it will be scaled in offset_y later.
-                        current_y += FONT_SIZE * style.line_height;
+                        current_y += base_font_size * style.line_height;
 
                         current_col = 0;
                         current_row += 1;
-                        previous_char_on_line = None;
                         line_has_hanging_punctuation = false;
 
+                        lines.push(line::Line::new(
+                            line_start..break_index,
+                            LineBreak::Wrap,
+                            line_top,
+                            &placements,
+                            (style.font_size, style.font_size * style.line_height),
+                        ));
+                        line_start = break_index;
+
                         // if text overflows the box, ignore the rest characters
-                        if max_height
-                            .is_some_and(|height| current_y / FONT_SIZE * style.font_size >= height)
-                        {
-                            total_width =
-                                total_width.max(total_width_of_run / FONT_SIZE * style.font_size);
+                        if max_height.is_some_and(|height| {
+                            current_y / base_font_size * style.font_size >= height
+                        }) {
+                            glyph_vertices_vec.truncate(break_index);
+                            placements.truncate(break_index);
+                            total_width = total_width
+                                .max(total_width_of_run / base_font_size * style.font_size);
                             total_height = max_height.unwrap();
+                            overflowed = true;
                             break 'out;
                         }
 
                         // update actual height to current_y with additional a line
-                        _total_height_of_run = current_y + FONT_SIZE * style.line_height;
+                        _total_height_of_run = current_y + base_font_size * style.line_height;
+
+                        if break_index < placements.len() {
+                            let pushed_out_start = placements[break_index].start;
+                            line::shift_glyphs(
+                                &mut glyph_vertices_vec[break_index..],
+                                &mut placements[break_index..],
+                                -pushed_out_start,
+                                base_font_size * style.line_height * actual_scale_ratio,
+                                &layout_style.direction,
+                            );
+                            for (col, glyph_vertices) in
+                                glyph_vertices_vec[break_index..].iter_mut().enumerate()
+                            {
+                                glyph_vertices.col = col as u32;
+                                glyph_vertices.row = current_row;
+                            }
+
+                            current_col = (placements.len() - break_index) as u32;
+                            current_x = placements.last().unwrap().end / actual_scale_ratio
+                                - punctuation_compression;
+                        }
                     } else {
                         current_x = compressed_x;
                         line_has_hanging_punctuation |= can_hang;
@@ -304,70 +605,97 @@ impl Huozi {
 
                     let mut grid_scale_ratio_w = 1.;
                     let grid_scale_ratio_h = 1.;
-                    let actual_scale_ratio = style.font_size / FONT_SIZE;
 
                     // scale character letting width fulfills font size.
                     // don't know why em/two-em dash have to do so.
-                    if glyph.ch == '—' || glyph.ch == '―' {
-                        grid_scale_ratio_w = FONT_SIZE / actual_width as f64;
-                        h_advance = FONT_SIZE;
-                    } else if glyph.ch == '⸺' {
-                        grid_scale_ratio_w = FONT_SIZE * 2. / actual_width as f64;
-                        h_advance = FONT_SIZE * 2.;
-                    } else if glyph.ch == '–' {
-                        grid_scale_ratio_w = FONT_SIZE / 2. / actual_width as f64;
-                        h_advance = FONT_SIZE / 2.;
-                    } else if glyph.ch == '⸻' {
-                        grid_scale_ratio_w = FONT_SIZE * 3. / actual_width as f64;
-                        h_advance = FONT_SIZE * 3.;
+                    if ch == '—' || ch == '―' {
+                        grid_scale_ratio_w = base_font_size / actual_width;
+                        h_advance = base_font_size;
+                    } else if ch == '⸺' {
+                        grid_scale_ratio_w = base_font_size * 2. / actual_width;
+                        h_advance = base_font_size * 2.;
+                    } else if ch == '–' {
+                        grid_scale_ratio_w = base_font_size / 2. / actual_width;
+                        h_advance = base_font_size / 2.;
+                    } else if ch == '⸻' {
+                        grid_scale_ratio_w = base_font_size * 3. / actual_width;
+                        h_advance = base_font_size * 3.;
                     }
 
+                    let line_height = base_font_size * style.line_height;
+                    let (font_ascent, font_descent) = font_extents[glyph.font_index];
+                    let ascent = line::baseline(font_ascent, font_descent, line_height);
+
                     // scale by font size, 48 is the texture font size when the grid size is 64.
-                    let offset_x = current_x * actual_scale_ratio
-                        - (GRID_SIZE * glyph.grid_count as f64 / 2. / x_scale
+                    let offset_x = (current_x + unit.x_offset) * actual_scale_ratio
+                        - (grid_size * glyph.grid_count as f64 / 2. / x_scale
                             - actual_width / 2.
                             - metrics.x_min as f64)
                             * actual_scale_ratio
                             * grid_scale_ratio_w;
-                    let offset_y = (current_y) * actual_scale_ratio
-                        - (GRID_SIZE / 2. / y_scale - actual_height / 2. - ASCENT
+                    let offset_y = (current_y - unit.y_offset) * actual_scale_ratio
+                        - (grid_size / 2. / y_scale - actual_height / 2. - ascent
                             + metrics.y_max as f64)
                             * actual_scale_ratio
                             * grid_scale_ratio_h;
 
-                    let actual_grid_size_w = GRID_SIZE
+                    let actual_grid_size_w = grid_size
                         * glyph.grid_count as f64
                         * actual_scale_ratio
                         * grid_scale_ratio_w
                         / x_scale;
                     let actual_grid_size_h =
-                        GRID_SIZE * actual_scale_ratio * grid_scale_ratio_h / y_scale;
+                        grid_size * actual_scale_ratio * grid_scale_ratio_h / y_scale;
 
                     // calculate four vertices without multiplying with transform matrix
 
-                    let tx = offset_x / VIEWPORT_WIDTH;
-                    let ty = offset_y / VIEWPORT_HEIGHT;
-
-                    let w1 = 0.;
-                    let w0 = actual_grid_size_w / VIEWPORT_WIDTH;
-                    let h1 = 0.;
-                    let h0 = actual_grid_size_h / VIEWPORT_HEIGHT;
+                    let pen_x = current_x * actual_scale_ratio;
+                    let pen_y = current_y * actual_scale_ratio;
+
+                    // the glyph grid relative to the pen position, as if in a horizontal line
+                    let w1 = offset_x - pen_x;
+                    let w0 = w1 + actual_grid_size_w;
+                    let h1 = offset_y - pen_y;
+                    let h0 = h1 + actual_grid_size_h;
+
+                    // map a point of the glyph grid onto the screen, columns of vertical layout
+                    // go leftwards from zero and are moved into the box at the end of layout.
+                    let place = |x: f64, y: f64| {
+                        let (x, y) = match orientation {
+                            None => (pen_x + x, pen_y + y),
+                            Some(VerticalOrientation::Upright) => {
+                                // center the glyph in the column, with the top of its em box at the pen
+                                let (mut dx, mut dy) =
+                                    ((line_height - h_advance) / 2., font_ascent - ascent);
+                                // vertical alternates are drawn in place by the font
+                                if vertical::is_corner_punctuation(ch) && !unit.vertical_alternate {
+                                    dx += h_advance / 2.;
+                                    dy -= v_advance / 2.;
+                                }
+                                (
+                                    -pen_y - line_height * actual_scale_ratio
+                                        + dx * actual_scale_ratio
+                                        + x,
+                                    pen_x + dy * actual_scale_ratio + y,
+                                )
+                            }
+                            // rotate 90° clockwise, the top of the line faces the right of the column
+                            Some(VerticalOrientation::Rotated) => (-pen_y - y, pen_x + x),
+                        };
+                        (x / VIEWPORT_WIDTH, y / VIEWPORT_HEIGHT)
+                    };
 
                     // left top
-                    let p0x = w1 + tx;
-                    let p0y = h1 + ty;
+                    let (p0x, p0y) = place(w1, h1);
 
                     // left bottom
-                    let p1x = w1 + tx;
-                    let p1y = h0 + ty;
+                    let (p1x, p1y) = place(w1, h0);
 
                     // right top
-                    let p2x = w0 + tx;
-                    let p2y = h0 + ty;
+                    let (p2x, p2y) = place(w0, h0);
 
                     // right bottom
-                    let p3x = w0 + tx;
-                    let p3y = h1 + ty;
+                    let (p3x, p3y) = place(w0, h1);
 
                     let mut vertices_fill = Vec::with_capacity(4);
                     let mut vertices_stroke = Vec::with_capacity(4);
@@ -428,7 +756,7 @@ impl Huozi {
                         let buffer = base_buffer
                             - GAMMA_COEFFICIENT * stroke_width
                                 / 2.
-                                / (style.font_size / FONT_SIZE) as f32
+                                / (style.font_size / base_font_size) as f32
                                 * x_scale as f32
                                 / grid_scale_ratio_w as f32;
 
@@ -494,12 +822,12 @@ impl Huozi {
                         let buffer = base_buffer
                             - GAMMA_COEFFICIENT * shadow_width
                                 / 2.
-                                / (style.font_size / FONT_SIZE) as f32
+                                / (style.font_size / base_font_size) as f32
                                 * x_scale as f32
                                 / grid_scale_ratio_w as f32;
                         let gamma = GAMMA_COEFFICIENT * shadow_blur
                             / 2.
-                            / (style.font_size / FONT_SIZE * 2.) as f32
+                            / (style.font_size / base_font_size * 2.) as f32
                             * x_scale as f32
                             / grid_scale_ratio_w as f32;
 
@@ -557,25 +885,58 @@ impl Huozi {
                         row: current_row,
                         x: current_x.round() as u32,
                         y: current_y.round() as u32,
-                        width: h_advance.round() as u32,
-                        height: (FONT_SIZE * style.line_height).round() as u32,
+                        width: advance(h_advance).round() as u32,
+                        height: line_height.round() as u32,
                         scale_ratio: actual_scale_ratio as f32,
+                        segment_id: segment_id.clone(),
+                        byte_range: run.source_offset(unit.byte_range.start)
+                            ..run.source_offset(unit.byte_range.end),
+                        span_index,
+                        span_id: span.span_id.clone(),
+                        run_index,
+                        reveal_time: reveal_clock as f32,
                     };
+                    if !style.reveal_instant {
+                        reveal_clock += layout_style.reveal_interval / style.reveal_speed;
+                    }
 
                     glyph_vertices_vec.push(glyph_vertices);
+                    placements.push(line::Placement {
+                        ch,
+                        start: current_x * actual_scale_ratio,
+                        end: (current_x + advance(h_advance)) * actual_scale_ratio,
+                        block: pen_y,
+                        em: style.font_size,
+                        line_height: line_height * actual_scale_ratio,
+                        // columns of vertical layout share the central baseline
+                        baseline: if is_vertical {
+                            line_height / 2.
+                        } else {
+                            ascent
+                        } * actual_scale_ratio,
+                        font_index: glyph.font_index,
+                        hanging: can_hang,
+                        ruby_group,
+                        joins_previous: unit.joins_previous,
+                    });
+                    if let Some(index) = ruby_index {
+                        rubies[index].glyph_range.end = glyph_vertices_vec.len();
+                    }
 
-                    current_x += h_advance;
+                    current_x += advance(h_advance);
                     current_col += 1;
-                    previous_char_on_line = Some(glyph.ch);
+                    previous_char_on_line = Some(ch);
                 }
 
                 // in case of the last line without line break
                 total_width_of_run = total_width_of_run.max(current_x);
-                _total_height_of_run = current_y + FONT_SIZE * style.line_height;
+                _total_height_of_run = current_y + base_font_size * style.line_height;
 
                 // update total size
-                total_width = total_width.max(total_width_of_run / FONT_SIZE * style.font_size);
-                total_height = total_height.max(_total_height_of_run / FONT_SIZE * style.font_size);
+                total_width =
+                    total_width.max(total_width_of_run / base_font_size * style.font_size);
+                total_height =
+                    total_height.max(_total_height_of_run / base_font_size * style.font_size);
             }
         }
 
@@ -587,11 +948,325 @@ impl Huozi {
             });
         }
 
-        (
-            glyph_vertices_vec,
-            segment_glyph_spans,
-            total_width.round() as u32,
-            total_height.round() as u32,
-        )
+        // the last line, unless the text ends with a line break or overflows the box
+        if line_start < glyph_vertices_vec.len() {
+            lines.push(line::Line::new(
+                line_start..glyph_vertices_vec.len(),
+                LineBreak::End,
+                placements[line_start].block,
+                &placements,
+                (0., 0.),
+            ));
+        }
+
+        // an empty line follows the line break at the end of text
+        if !overflowed
+            && line_start == glyph_vertices_vec.len()
+            && lines
+                .last()
+                .is_some_and(|line: &line::Line| line.line_break == LineBreak::Hard)
+        {
+            lines.push(line::Line::new(
+                line_start..line_start,
+                LineBreak::End,
+                current_y * current_scale_ratio,
+                &placements,
+                current_line_box,
+            ));
+        }
+
+        // lines are stacked by their tallest content, with space for ruby annotations before
+        // them, and glyphs on a line are moved onto the shared baseline
+        let mut line_bottom = 0.;
+        for line in lines.iter_mut() {
+            let glyph_range = line.glyph_range.clone();
+            let ruby_space = rubies
+                .iter()
+                .filter(|ruby_base| {
+                    ruby_base.glyph_range.start < glyph_range.end
+                        && glyph_range.start < ruby_base.glyph_range.end
+                })
+                .map(ruby::RubyBase::annotation_size)
+                .fold(0., f64::max);
+
+            let (above, below) = if glyph_range.is_empty() {
+                // empty lines take the metrics of the primary font
+                let scale_ratio = line.em / base_font_size;
+                let above = line::baseline(
+                    font_extents[0].0 * scale_ratio,
+                    font_extents[0].1 * scale_ratio,
+                    line.height,
+                );
+                (above, line.height - above)
+            } else {
+                placements[glyph_range.clone()].iter().fold(
+                    (0., 0.),
+                    |(above, below), placement| {
+                        (
+                            f64::max(above, placement.baseline),
+                            f64::max(below, placement.line_height - placement.baseline),
+                        )
+                    },
+                )
+            };
+
+            line.top = line_bottom + ruby_space;
+            line.height = above + below;
+            line.baseline = above;
+            line_bottom = line.top + line.height;
+
+            for index in glyph_range {
+                let placement = &placements[index];
+                let offset = line.top + line.baseline - placement.baseline - placement.block;
+                if offset != 0. {
+                    line::shift_glyphs(
+                        &mut glyph_vertices_vec[index..=index],
+                        &mut placements[index..=index],
+                        0.,
+                        offset,
+                        &layout_style.direction,
+                    );
+                }
+            }
+        }
+        if !overflowed {
+            total_height = line_bottom;
+        }
+        if let Some(height) = max_height.filter(|&height| total_height > height || overflowed) {
+            // drop lines pushed out of the box
+            let line_count = lines.iter().take_while(|line| line.top < height).count();
+            let glyph_count = lines
+                .get(line_count)
+                .map_or(glyph_vertices_vec.len(), |line| line.glyph_range.start);
+            lines.truncate(line_count);
+            glyph_vertices_vec.truncate(glyph_count);
+            placements.truncate(glyph_count);
+            total_height = height;
+        }
+        for ruby_base in rubies.iter_mut() {
+            let glyph_range = &mut ruby_base.glyph_range;
+            glyph_range.end = glyph_range.end.min(glyph_vertices_vec.len());
+        }
+
+        // glyphs pushed out to a line overflowing the box have been removed
+        for segment_glyph_span in segment_glyph_spans.iter_mut() {
+            let glyph_range = &mut segment_glyph_span.glyph_range;
+            glyph_range.end = glyph_range.end.min(glyph_vertices_vec.len());
+            glyph_range.start = glyph_range.start.min(glyph_range.end);
+        }
+
+        // soft hyphens are only visible at the end of wrapped lines
+        for (index, placement) in placements.iter().enumerate() {
+            let at_wrapped_line_end = || {
+                lines
+                    .iter()
+                    .any(|line| !line.ends_paragraph() && line.glyph_range.end == index + 1)
+            };
+            if placement.ch == line_break::SOFT_HYPHEN && !at_wrapped_line_end() {
+                let glyph_vertices = &mut glyph_vertices_vec[index];
+                glyph_vertices.fill.clear();
+                glyph_vertices.stroke.clear();
+                glyph_vertices.shadow.clear();
+                glyph_vertices.indices.clear();
+            }
+        }
+
+        let line_widths = lines
+            .iter()
+            .map(|line| line.width(&placements))
+            .collect::<Vec<_>>();
+
+        let align_offsets =
+            align::align_offsets(layout_style.align, &lines, &placements, max_width);
+        for (index, offset) in align_offsets.into_iter().enumerate() {
+            if offset != 0. {
+                line::shift_glyphs(
+                    &mut glyph_vertices_vec[index..=index],
+                    &mut placements[index..=index],
+                    offset,
+                    0.,
+                    &layout_style.direction,
+                );
+            }
+        }
+        if layout_style.align != TextAlign::Start {
+            // aligned lines spread over the box
+            total_width = total_width.max(max_width.unwrap_or(0.));
+        }
+
+        if is_vertical {
+            // columns are laid out from right to left, starting at the right edge of the box.
+            let box_width = max_height.unwrap_or(total_height);
+            let offset_x = (box_width / VIEWPORT_WIDTH) as f32;
+            for glyph_vertices in glyph_vertices_vec.iter_mut() {
+                for vertex in glyph_vertices
+                    .fill
+                    .iter_mut()
+                    .chain(glyph_vertices.stroke.iter_mut())
+                    .chain(glyph_vertices.shadow.iter_mut())
+                {
+                    vertex.position[0] += offset_x;
+                }
+
+                // turn the bounding box measured along the column into the one on the screen
+                let GlyphVertices {
+                    x,
+                    y,
+                    width,
+                    height,
+                    scale_ratio,
+                    ..
+                } = *glyph_vertices;
+                glyph_vertices.x = (box_width / scale_ratio as f64 - (y + height) as f64)
+                    .round()
+                    .max(0.) as u32;
+                glyph_vertices.y = x;
+                glyph_vertices.width = height;
+                glyph_vertices.height = width;
+            }
+
+            std::mem::swap(&mut total_width, &mut total_height);
+        }
+
+        let line_infos = lines
+            .iter()
+            .zip(line_widths)
+            .map(|(line, width)| {
+                let placements = &placements[line.glyph_range.clone()];
+                // empty lines take the metrics of the primary font
+                let font_scales = placements
+                    .iter()
+                    .map(|placement| (placement.font_index, placement.em))
+                    .chain(placements.is_empty().then_some((0, line.em)));
+                let (font_ascent, font_descent) =
+                    font_scales.fold((0., 0.), |(ascent, descent), (font_index, em)| {
+                        let (font_ascent, font_descent) = font_extents[font_index];
+                        let scale_ratio = em / base_font_size;
+                        (
+                            f64::max(ascent, font_ascent * scale_ratio),
+                            f64::max(descent, -font_descent * scale_ratio),
+                        )
+                    });
+                let baseline = if is_vertical {
+                    max_height.unwrap_or(total_width) - line.top - line.height / 2.
+                } else {
+                    line.top + line.baseline
+                };
+
+                LineInfo {
+                    glyph_range: line.glyph_range.clone(),
+                    baseline: baseline as f32,
+                    ascent: font_ascent as f32,
+                    descent: font_descent as f32,
+                    width: width as f32,
+                    line_break: line.line_break,
+                    has_hanging_punctuation: placements.iter().any(|placement| placement.hanging),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // annotations are laid out after all base glyphs, so indices of the base glyphs are kept
+        for ruby_base in rubies.iter() {
+            let annotation_style = ruby_base.annotation_style();
+            for (annotation, glyph_range) in ruby_base.annotations() {
+                if glyph_range.is_empty() {
+                    continue;
+                }
+                // a base text longer than a line is only annotated on its first line
+                let base = &placements[glyph_range.clone()];
+                let base_end = base
+                    .iter()
+                    .take_while(|placement| placement.block == base[0].block)
+                    .map(|placement| placement.end)
+                    .fold(base[0].end, f64::max);
+                let base_glyph = &glyph_vertices_vec[glyph_range.start];
+                let (row, col) = (base_glyph.row, base_glyph.col);
+                // annotations come from the source of their base text
+                let byte_range = base_glyph.byte_range.start
+                    ..glyph_vertices_vec[glyph_range.end - 1].byte_range.end;
+                let segment_id = base_glyph.segment_id.clone();
+                let span_id = base_glyph.span_id.clone();
+                let (span_index, run_index) = (base_glyph.span_index, base_glyph.run_index);
+                let reveal_time = base_glyph.reveal_time;
+
+                let annotation_layout_style = LayoutStyle {
+                    direction: layout_style.direction.clone(),
+                    line_metrics: layout_style.line_metrics,
+                    ..Default::default()
+                };
+                let LayoutResult {
+                    glyphs: mut annotation_glyphs,
+                    width: annotation_width,
+                    height: annotation_height,
+                    ..
+                } = self.layout(
+                    &annotation_layout_style,
+                    vec![TextSpan {
+                        span_id: None,
+                        runs: vec![TextRun {
+                            text: annotation,
+                            style: annotation_style.clone(),
+                            ..Default::default()
+                        }],
+                    }],
+                    color_space,
+                );
+
+                // center the annotation over the base, in the space before the line
+                let (annotation_length, line_top) = if is_vertical {
+                    (annotation_height as f64, base[0].block)
+                } else {
+                    (
+                        annotation_width as f64,
+                        base[0].block - ruby_base.annotation_size(),
+                    )
+                };
+                let inline = base[0].start + (base_end - base[0].start - annotation_length) / 2.;
+                let (dx, dy) = if is_vertical {
+                    // columns are laid out from the right edge of the box, see above
+                    (max_height.unwrap_or(total_width) - line_top, inline)
+                } else {
+                    (inline, line_top)
+                };
+                let (offset_x, offset_y) =
+                    ((dx / VIEWPORT_WIDTH) as f32, (dy / VIEWPORT_HEIGHT) as f32);
+                for glyph_vertices in annotation_glyphs.iter_mut() {
+                    for vertex in glyph_vertices
+                        .fill
+                        .iter_mut()
+                        .chain(glyph_vertices.stroke.iter_mut())
+                        .chain(glyph_vertices.shadow.iter_mut())
+                    {
+                        vertex.position[0] += offset_x;
+                        vertex.position[1] += offset_y;
+                    }
+
+                    let scale_ratio = glyph_vertices.scale_ratio as f64;
+                    glyph_vertices.x =
+                        (glyph_vertices.x as f64 + dx / scale_ratio).round().max(0.) as u32;
+                    glyph_vertices.y =
+                        (glyph_vertices.y as f64 + dy / scale_ratio).round().max(0.) as u32;
+                    glyph_vertices.row = row;
+                    glyph_vertices.col = col;
+                    glyph_vertices.segment_id = segment_id.clone();
+                    glyph_vertices.byte_range = byte_range.clone();
+                    glyph_vertices.span_index = span_index;
+                    glyph_vertices.span_id = span_id.clone();
+                    glyph_vertices.run_index = run_index;
+                    glyph_vertices.reveal_time = reveal_time;
+                }
+
+                glyph_vertices_vec.append(&mut annotation_glyphs);
+            }
+        }
+
+        LayoutResult {
+            glyphs: glyph_vertices_vec,
+            segments: segment_glyph_spans,
+            lines: line_infos,
+            width: total_width.round() as u32,
+            height: total_height.round() as u32,
+            reveal_duration: reveal_clock as f32,
+        }
     }
 }
@@ -0,0 +1,108 @@
+use super::{
+    TextAlign,
+    line::{Line, Placement},
+    line_break::{is_cjk, is_latin},
+    punctuation,
+};
+
+/// The maximum amount, in em, a Latin space or a CJK-Latin boundary is expanded
+/// before the space between CJK characters is.
+const MAX_WORD_SPACE_EXPANSION: f64 = 0.5;
+
+/// Returns the offset along the text flow, in pixels, of each glyph to align its line
+/// in the available size, or in the widest line if there is no size limit.
+pub(super) fn align_offsets(
+    align: TextAlign,
+    lines: &[Line],
+    placements: &[Placement],
+    available: Option<f64>,
+) -> Vec<f64> {
+    let mut offsets = vec![0.; placements.len()];
+    if align == TextAlign::Start {
+        return offsets;
+    }
+
+    let available = available.unwrap_or_else(|| {
+        lines
+            .iter()
+            .map(|line| line.width(placements))
+            .fold(0., f64::max)
+    });
+
+    for line in lines {
+        let slack = available - line.width(placements);
+        if slack <= 0. {
+            continue;
+        }
+
+        match align {
+            TextAlign::Start => {}
+            TextAlign::Center => offsets[line.glyph_range.clone()].fill(slack / 2.),
+            TextAlign::End => offsets[line.glyph_range.clone()].fill(slack),
+            TextAlign::Justify if line.ends_paragraph() => {}
+            TextAlign::Justify | TextAlign::JustifyAll => {
+                justify(line, placements, slack, &mut offsets)
+            }
+        }
+    }
+
+    offsets
+}
+
+/// Spread the slack of a line across the gaps between glyphs, following the priorities of
+/// clreq and JLREQ: Latin spaces and CJK-Latin boundaries are expanded first up to a limit,
+/// then the rest goes to the gaps between CJK characters.
+fn justify(line: &Line, placements: &[Placement], slack: f64, offsets: &mut [f64]) {
+    let mut word_gaps = vec![];
+    let mut cjk_gaps = vec![];
+
+    for i in line.glyph_range.start + 1..line.glyph_range.end {
+        let (previous, current) = (&placements[i - 1], &placements[i]);
+        // a hanging punctuation mark keeps attached to the previous character
+        if current.hanging {
+            continue;
+        }
+
+        if previous.ch == ' '
+            || (is_cjk(previous.ch) && is_latin(current.ch))
+            || (is_latin(previous.ch) && is_cjk(current.ch))
+        {
+            word_gaps.push(i);
+        } else if is_cjk(previous.ch)
+            && is_cjk(current.ch)
+            && !punctuation::is_adjustable(previous.ch)
+            && !punctuation::is_adjustable(current.ch)
+        {
+            cjk_gaps.push(i);
+        }
+    }
+
+    let mut expansions = vec![0.; placements.len()];
+    let mut remaining = slack;
+
+    if !word_gaps.is_empty() {
+        let share = slack / word_gaps.len() as f64;
+        for &i in &word_gaps {
+            let expansion = if cjk_gaps.is_empty() {
+                share
+            } else {
+                share.min(placements[i].em * MAX_WORD_SPACE_EXPANSION)
+            };
+            expansions[i] = expansion;
+            remaining -= expansion;
+        }
+    }
+
+    if !cjk_gaps.is_empty() && remaining > 0. {
+        let share = remaining / cjk_gaps.len() as f64;
+        for &i in &cjk_gaps {
+            expansions[i] = share;
+        }
+    }
+
+    let mut offset = 0.;
+    for i in line.glyph_range.clone() {
+        offset += expansions[i];
+        offsets[i] = offset;
+    }
+}
@@ -1,5 +1,6 @@
 use csscolorparser::Color;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ColorSpace {
     Linear,
     SRGB,
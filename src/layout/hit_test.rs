@@ -0,0 +1,253 @@
+use std::ops::Range;
+
+use super::{LayoutDirection, LineBreak, LineInfo};
+use crate::{
+    glyph_vertices::GlyphVertices,
+    parser::{SegmentId, SourceRange},
+};
+
+/// A rectangle on the screen, measured in pixels.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// The glyph under a point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hit {
+    /// the index of the glyph in the glyph vertices
+    pub glyph_index: usize,
+    /// the source of the characters drawn by the glyph
+    pub source_range: SourceRange,
+    /// whether the point is in the latter half of the glyph along the text flow
+    pub trailing: bool,
+}
+
+impl Hit {
+    /// The source offset of the caret nearest to the point.
+    pub fn caret_offset(&self) -> usize {
+        if self.trailing {
+            self.source_range.end
+        } else {
+            self.source_range.start
+        }
+    }
+}
+
+/// Queries on the output of `Huozi::layout`, to map points on the screen to the source text
+/// and back, e.g. for clickable words or text selection.
+///
+/// Only glyphs of the base text are hit, while ruby annotations are skipped.
+pub struct HitTester<'a> {
+    glyphs: &'a [GlyphVertices],
+    lines: &'a [LineInfo],
+    is_vertical: bool,
+}
+
+impl<'a> HitTester<'a> {
+    /// Create a hit tester on the glyphs and lines from a layout in `direction`.
+    pub fn new(
+        glyphs: &'a [GlyphVertices],
+        lines: &'a [LineInfo],
+        direction: &LayoutDirection,
+    ) -> Self {
+        Self {
+            glyphs,
+            lines,
+            is_vertical: *direction == LayoutDirection::Vertical,
+        }
+    }
+
+    /// The bounding box of a glyph, which is its advance across its line box.
+    pub fn glyph_rect(&self, glyph_index: usize) -> Rect {
+        let glyph = &self.glyphs[glyph_index];
+        Rect {
+            x: glyph.x as f32 * glyph.scale_ratio,
+            y: glyph.y as f32 * glyph.scale_ratio,
+            width: glyph.width as f32 * glyph.scale_ratio,
+            height: glyph.height as f32 * glyph.scale_ratio,
+        }
+    }
+
+    /// The source of the characters drawn by a glyph.
+    pub fn source_range(&self, glyph_index: usize) -> SourceRange {
+        let byte_range = &self.glyphs[glyph_index].byte_range;
+        SourceRange {
+            segment_id: self.segment_id(glyph_index).cloned(),
+            start: byte_range.start,
+            end: byte_range.end,
+        }
+    }
+
+    /// The glyph nearest to the point `(x, y)`, on the nearest line to it.
+    pub fn hit_test(&self, x: f32, y: f32) -> Option<Hit> {
+        let (along, across) = self.flow_axes(x, y);
+
+        let (line, _) = self
+            .lines
+            .iter()
+            .filter(|line| !line.glyph_range.is_empty())
+            .map(|line| (line, self.line_extent(line)))
+            .min_by(|(_, a), (_, b)| distance(across, a).total_cmp(&distance(across, b)))?;
+        let glyph_index = line.glyph_range.clone().min_by(|&a, &b| {
+            distance(along, &self.glyph_extent(a))
+                .total_cmp(&distance(along, &self.glyph_extent(b)))
+        })?;
+
+        let extent = self.glyph_extent(glyph_index);
+        Some(Hit {
+            glyph_index,
+            source_range: self.source_range(glyph_index),
+            trailing: along > (extent.start + extent.end) / 2.,
+        })
+    }
+
+    /// The caret before the character at the source `offset` of a segment, or after the
+    /// character ending at `offset` if no glyph starts there, as a line across the text flow.
+    /// The caret on an empty line, such as the one after the break at the end of `"a\n"`,
+    /// is at the start of the box.
+    pub fn caret_rect(&self, segment_id: Option<&SegmentId>, offset: usize) -> Option<Rect> {
+        let glyphs_in_lines = || {
+            self.lines.iter().flat_map(move |line| {
+                line.glyph_range
+                    .clone()
+                    .filter(move |&index| self.segment_id(index) == segment_id)
+                    .map(move |index| (line, index))
+            })
+        };
+
+        let (line, along) = glyphs_in_lines()
+            .find(|&(_, index)| self.glyphs[index].byte_range.contains(&offset))
+            .map(|(line, index)| (line, self.glyph_extent(index).start))
+            .or_else(|| {
+                glyphs_in_lines()
+                    .find(|&(_, index)| self.glyphs[index].byte_range.end == offset)
+                    .map(|(line, index)| (line, self.glyph_extent(index).end))
+            })
+            .or_else(|| {
+                self.empty_lines()
+                    .find(|&(line_segment_id, line_offset, _)| {
+                        line_segment_id == segment_id && line_offset == offset
+                    })
+                    .map(|(_, _, line)| (line, 0.))
+            })?;
+
+        Some(self.rect(along..along, self.line_extent(line)))
+    }
+
+    /// The rectangles covering the glyphs from a source range, one per line.
+    pub fn selection_rects(&self, source_range: &SourceRange) -> Vec<Rect> {
+        let segment_id = source_range.segment_id.as_ref();
+
+        self.lines
+            .iter()
+            .filter_map(|line| {
+                let along = line
+                    .glyph_range
+                    .clone()
+                    .filter(|&index| {
+                        let byte_range = &self.glyphs[index].byte_range;
+                        self.segment_id(index) == segment_id
+                            && byte_range.start < source_range.end
+                            && source_range.start < byte_range.end
+                    })
+                    .map(|index| self.glyph_extent(index))
+                    .reduce(|a, b| a.start.min(b.start)..a.end.max(b.end))?;
+
+                Some(self.rect(along, self.line_extent(line)))
+            })
+            .collect()
+    }
+
+    fn segment_id(&self, glyph_index: usize) -> Option<&'a SegmentId> {
+        self.glyphs[glyph_index].segment_id.as_ref()
+    }
+
+    /// The coordinates of a point along the text flow and across the lines.
+    fn flow_axes(&self, x: f32, y: f32) -> (f32, f32) {
+        if self.is_vertical { (y, x) } else { (x, y) }
+    }
+
+    /// The rectangle of the extents along the text flow and across the lines.
+    fn rect(&self, along: Range<f32>, across: Range<f32>) -> Rect {
+        let (x, y) = self.flow_axes(along.start, across.start);
+        let (width, height) = self.flow_axes(along.end - along.start, across.end - across.start);
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// The extents of the bounding box of a glyph along the text flow and across the lines.
+    fn glyph_extents(&self, glyph_index: usize) -> (Range<f32>, Range<f32>) {
+        let Rect {
+            x,
+            y,
+            width,
+            height,
+        } = self.glyph_rect(glyph_index);
+        let (along, across) = self.flow_axes(x, y);
+        let (length, thickness) = self.flow_axes(width, height);
+        (along..along + length, across..across + thickness)
+    }
+
+    /// The extent of a glyph along the text flow.
+    fn glyph_extent(&self, glyph_index: usize) -> Range<f32> {
+        self.glyph_extents(glyph_index).0
+    }
+
+    /// The extent of a line across the text flow, which covers the line boxes of its glyphs,
+    /// or the ascent and descent around the baseline for an empty line.
+    fn line_extent(&self, line: &LineInfo) -> Range<f32> {
+        line.glyph_range
+            .clone()
+            .map(|index| self.glyph_extents(index).1)
+            .reduce(|a, b| a.start.min(b.start)..a.end.max(b.end))
+            .unwrap_or_else(|| {
+                if self.is_vertical {
+                    // the baseline is central in a column
+                    let half = (line.ascent + line.descent) / 2.;
+                    line.baseline - half..line.baseline + half
+                } else {
+                    line.baseline - line.ascent..line.baseline + line.descent
+                }
+            })
+    }
+
+    /// The empty lines with the segment and the source offset they start at, which follows the
+    /// line break ending the previous line.
+    fn empty_lines(&self) -> impl Iterator<Item = (Option<&'a SegmentId>, usize, &'a LineInfo)> {
+        let first_segment_id = self.glyphs.first().and_then(|_| self.segment_id(0));
+        let mut next = (first_segment_id, 0);
+        self.lines.iter().filter_map(move |line| {
+            let start = match line.glyph_range.clone().last() {
+                Some(index) => {
+                    next = (self.segment_id(index), self.glyphs[index].byte_range.end);
+                    None
+                }
+                None => Some(next),
+            };
+            // a hard line break is a single `\n` or `\r` in the source
+            if line.line_break == LineBreak::Hard {
+                next.1 += 1;
+            }
+            start.map(|(segment_id, offset)| (segment_id, offset, line))
+        })
+    }
+}
+
+/// The distance from a value to a range, which is zero in the range.
+fn distance(value: f32, range: &Range<f32>) -> f32 {
+    if value < range.start {
+        range.start - value
+    } else if value > range.end {
+        value - range.end
+    } else {
+        0.
+    }
+}
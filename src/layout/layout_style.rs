@@ -8,6 +8,23 @@ pub enum LayoutDirection {
     Vertical,
 }
 
+/// How lines are aligned along the text flow in the box.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TextAlign {
+    /// Align lines to the start of the box.
+    #[default]
+    Start,
+    /// Center lines in the box.
+    Center,
+    /// Align lines to the end of the box.
+    End,
+    /// Stretch lines to fill the box, except for the last line of each paragraph.
+    Justify,
+    /// Stretch all lines to fill the box.
+    JustifyAll,
+}
+
 /// Optional adjustments for full-width CJK punctuation.
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase", default)]
@@ -30,21 +47,97 @@ impl Default for PunctuationStyle {
     }
 }
 
+/// How strictly the line-breaking rules of CJK text (kinsoku shori) are applied,
+/// similar to the `line-break` property of CSS.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineBreakStrictness {
+    /// Break between any characters, without any prohibition.
+    Anywhere,
+    /// Keep only closing brackets and pause or stop punctuation from starting a line.
+    Loose,
+    /// Also keep middle dots, iteration marks, hyphens and ellipses from starting a line.
+    #[default]
+    Normal,
+    /// Also keep small kana and prolonged sound marks from starting a line.
+    Strict,
+}
+
+/// Where lines may break in words, similar to the `word-break` property of CSS.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WordBreak {
+    /// Keep words, numbers and URLs together, unless a word is longer than the line.
+    #[default]
+    Normal,
+    /// Break between any characters, as CJK text.
+    BreakAll,
+}
+
+/// Line-breaking rules which keep some characters from starting or ending a line.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct KinsokuStyle {
+    /// the preset of prohibited characters.
+    pub strictness: LineBreakStrictness,
+    /// characters which may not start a line, replacing the ones of `strictness` if set,
+    /// e.g. to follow the rules of another locale.
+    pub line_start_prohibited: Option<String>,
+    /// characters which may not end a line, replacing the ones of `strictness` if set.
+    pub line_end_prohibited: Option<String>,
+    /// Squeeze the blank of full-width punctuation marks on a line to pull in a character
+    /// which may not start the next line, before pushing characters out to the next line.
+    pub pull: bool,
+}
+
+/// Which vertical metrics of the fonts place glyphs in their line boxes.
+///
+/// The content area between the ascent and the descent is centered in the line box,
+/// whose height is given by the line height of the text style rather than the line gap.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LineMetrics {
+    /// The ascent and descent of the `hhea` table, as most renderers do.
+    #[default]
+    Hhea,
+    /// The typographic ascent and descent of the `OS/2` table,
+    /// or `Hhea` if the font does not have the table.
+    Typo,
+    /// The ideographic em box of the `BASE` table, which centers CJK glyphs in the line box,
+    /// or `Typo` if the font does not have the table.
+    IdeographicEmBox,
+}
+
 /// This is the setting of the full text in a `box`, which is also known as `text window`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase", default)]
 pub struct LayoutStyle {
     /// the writing direction of the text in the box,
-    /// only `Horizontal` (right-to-left) or `Vertical` (top-to-bottom) is valid.
+    /// either `Horizontal` (left-to-right) or `Vertical` (top-to-bottom, columns from right to left).
     pub direction: LayoutDirection,
     /// The maximum width of the box, or `None` for no width limit.
     pub box_width: Option<f64>,
     /// The maximum height of the box, or `None` for no height limit.
     pub box_height: Option<f64>,
+    /// the alignment of lines, in the box or in the longest line if the box has no size limit.
+    pub align: TextAlign,
     /// the size of the glyph grid which each character be fit to, usually equals to `font_size`.
     pub glyph_grid_size: f64,
     /// optional adjustments for full-width CJK punctuation.
     pub punctuation: PunctuationStyle,
+    /// where lines may break in words when a line is wrapped.
+    pub word_break: WordBreak,
+    /// line-breaking rules applied when a line is wrapped and the punctuation does not hang.
+    pub kinsoku: KinsokuStyle,
+    /// OpenType features applied when the `shaping` feature is enabled,
+    /// e.g. `halt`, `-liga` or `kern=0`. Vertical text is shaped from top to bottom,
+    /// so `vert` applies by default there, and `vhal` halves full-width punctuation.
+    pub font_features: Vec<String>,
+    /// which vertical metrics of the fonts place glyphs in their line boxes.
+    pub line_metrics: LineMetrics,
+    /// the seconds between revealing two characters in a typewriter reveal at speed 1,
+    /// for `GlyphVertices::reveal_time`.
+    pub reveal_interval: f64,
 }
 
 impl Default for LayoutStyle {
@@ -53,8 +146,14 @@ impl Default for LayoutStyle {
             direction: Default::default(),
             box_width: None,
             box_height: None,
+            align: Default::default(),
             glyph_grid_size: 24.,
             punctuation: Default::default(),
+            word_break: Default::default(),
+            kinsoku: Default::default(),
+            font_features: vec![],
+            line_metrics: Default::default(),
+            reveal_interval: 0.05,
         }
     }
 }
@@ -0,0 +1,160 @@
+use std::ops::Range;
+
+use super::{LayoutDirection, LineBreak, LineMetrics, line_break};
+use crate::{
+    constant::{VIEWPORT_HEIGHT, VIEWPORT_WIDTH},
+    font_extractor::{FontHMetrics, VerticalExtent},
+    glyph_vertices::GlyphVertices,
+};
+
+/// A glyph placed on a line, measured along the text flow in pixels.
+pub(super) struct Placement {
+    pub ch: char,
+    /// the start of the glyph from the start of the line
+    pub start: f64,
+    /// the end of the glyph from the start of the line
+    pub end: f64,
+    /// the start of the line across the lines
+    pub block: f64,
+    /// the font size of the glyph
+    pub em: f64,
+    /// the line height of the glyph
+    pub line_height: f64,
+    /// the distance from the top of the line box of the glyph to its baseline
+    pub baseline: f64,
+    pub font_index: usize,
+    /// whether the glyph is a punctuation mark hanging outside of the line
+    pub hanging: bool,
+    /// the group ruby whose base text contains the glyph, which is not broken across lines
+    pub ruby_group: Option<usize>,
+    /// whether the glyph follows another glyph of the same cluster, which is not broken
+    pub joins_previous: bool,
+}
+
+/// A line of glyphs after wrapping.
+pub(super) struct Line {
+    pub glyph_range: Range<usize>,
+    pub line_break: LineBreak,
+    /// the start of the line across the lines
+    pub top: f64,
+    /// the largest font size on the line
+    pub em: f64,
+    /// the height of the line box, which fits the tallest content on the line
+    pub height: f64,
+    /// the distance from the top of the line box to the baseline shared by the glyphs
+    pub baseline: f64,
+}
+
+impl Line {
+    /// A line of the placed glyphs in `glyph_range`, or of the given font size and line height
+    /// if it is empty.
+    pub fn new(
+        glyph_range: Range<usize>,
+        line_break: LineBreak,
+        top: f64,
+        placements: &[Placement],
+        (em, height): (f64, f64),
+    ) -> Self {
+        let glyphs = &placements[glyph_range.clone()];
+        let (em, height) = if glyphs.is_empty() {
+            (em, height)
+        } else {
+            glyphs.iter().fold((0., 0.), |(em, height), placement| {
+                (
+                    f64::max(em, placement.em),
+                    f64::max(height, placement.line_height),
+                )
+            })
+        };
+
+        Self {
+            glyph_range,
+            line_break,
+            top,
+            em,
+            height,
+            baseline: 0.,
+        }
+    }
+
+    /// Whether the line is ended by a line break or the end of text, rather than wrapping.
+    pub fn ends_paragraph(&self) -> bool {
+        self.line_break != LineBreak::Wrap
+    }
+
+    /// The width of the line, excluding hanging punctuation and trailing spaces.
+    pub fn width(&self, placements: &[Placement]) -> f64 {
+        placements[self.glyph_range.clone()]
+            .iter()
+            .rev()
+            .skip_while(|placement| placement.hanging || line_break::is_space(placement.ch))
+            .map(|placement| placement.end)
+            .fold(0., f64::max)
+    }
+}
+
+/// The ascent and descent of a font chosen by `line_metrics`, whose descent is negative.
+pub(super) fn font_extent(metrics: &FontHMetrics, line_metrics: LineMetrics) -> (f64, f64) {
+    let hhea = VerticalExtent {
+        ascent: metrics.ascent,
+        descent: metrics.descent,
+    };
+    let extent = match line_metrics {
+        LineMetrics::Hhea => hhea,
+        LineMetrics::Typo => metrics.typo.unwrap_or(hhea),
+        LineMetrics::IdeographicEmBox => {
+            metrics.ideographic_em_box.or(metrics.typo).unwrap_or(hhea)
+        }
+    };
+
+    (extent.ascent as f64, extent.descent as f64)
+}
+
+/// The distance from the top of a line box to the baseline, as the leading is split evenly
+/// above and below the content area of the font, whose descent is negative.
+pub(super) fn baseline(ascent: f64, descent: f64, line_height: f64) -> f64 {
+    ascent + (line_height - (ascent - descent)) / 2.
+}
+
+/// Move placed glyphs along the text flow and across the lines, in pixels.
+///
+/// This must be done before columns of vertical layout are moved into the box.
+pub(super) fn shift_glyphs(
+    glyphs: &mut [GlyphVertices],
+    placements: &mut [Placement],
+    inline: f64,
+    block: f64,
+    direction: &LayoutDirection,
+) {
+    let (dx, dy) = match direction {
+        LayoutDirection::Horizontal => (inline, block),
+        // columns go leftwards, see `Huozi::layout`
+        LayoutDirection::Vertical => (-block, inline),
+    };
+    let (dx, dy) = ((dx / VIEWPORT_WIDTH) as f32, (dy / VIEWPORT_HEIGHT) as f32);
+
+    for (glyph_vertices, placement) in glyphs.iter_mut().zip(placements) {
+        for vertex in glyph_vertices
+            .fill
+            .iter_mut()
+            .chain(glyph_vertices.stroke.iter_mut())
+            .chain(glyph_vertices.shadow.iter_mut())
+        {
+            vertex.position[0] += dx;
+            vertex.position[1] += dy;
+        }
+
+        // `x` and `y` are measured along the text flow and across the lines in font units
+        let scale_ratio = glyph_vertices.scale_ratio as f64;
+        glyph_vertices.x = (glyph_vertices.x as f64 + inline / scale_ratio)
+            .round()
+            .max(0.) as u32;
+        glyph_vertices.y = (glyph_vertices.y as f64 + block / scale_ratio)
+            .round()
+            .max(0.) as u32;
+
+        placement.start += inline;
+        placement.end += inline;
+        placement.block += block;
+    }
+}
@@ -0,0 +1,171 @@
+use super::{LayoutStyle, WordBreak, line::Placement, punctuation};
+
+/// The soft hyphen, which is only visible as a hyphen when a line is broken after it.
+pub(super) const SOFT_HYPHEN: char = '\u{AD}';
+/// The zero width space, which marks a place where a line may break.
+pub(super) const ZERO_WIDTH_SPACE: char = '\u{200B}';
+
+/// Returns whether a line may break between two characters, by the break opportunities
+/// between words and the line-breaking rules of CJK text.
+pub(super) fn can_break_between(previous: char, next: char, layout_style: &LayoutStyle) -> bool {
+    let word_break = match layout_style.word_break {
+        WordBreak::Normal => is_break_opportunity(previous, next),
+        WordBreak::BreakAll => !is_space(next),
+    };
+
+    word_break && !is_kinsoku_prohibited(previous, next, layout_style)
+}
+
+/// Returns whether there is a break opportunity between two characters, a simplified
+/// version of [UAX #14](https://www.unicode.org/reports/tr14/).
+///
+/// Words, numbers and URLs are kept together, while CJK text breaks between any characters.
+fn is_break_opportunity(previous: char, next: char) -> bool {
+    match (previous, next) {
+        // spaces stay at the end of the line
+        (_, next) if is_space(next) => false,
+        (previous, _) if is_space(previous) || previous == SOFT_HYPHEN => true,
+        // word joiner and no-break space
+        ('\u{2060}' | '\u{A0}' | '\u{202F}', _) | (_, '\u{2060}' | '\u{A0}' | '\u{202F}') => false,
+        // after a hyphen or a dash inside a word, but not before a number, e.g. `-1`
+        ('-' | '‐' | '–', next) => next.is_alphabetic() && !is_cjk(next),
+        ('—' | '―', _) | (_, '—' | '―') => true,
+        (previous, next) => is_cjk(previous) || is_cjk(next),
+    }
+}
+
+/// Returns whether a character is a space which may stay at the end of a line.
+pub(super) fn is_space(ch: char) -> bool {
+    matches!(ch, ' ' | '\t' | ZERO_WIDTH_SPACE | '\u{3000}')
+}
+
+fn is_kinsoku_prohibited(previous: char, next: char, layout_style: &LayoutStyle) -> bool {
+    let kinsoku = &layout_style.kinsoku;
+    let strictness = kinsoku.strictness;
+
+    let line_start_prohibited = match &kinsoku.line_start_prohibited {
+        Some(chars) => chars.contains(next),
+        None => punctuation::is_line_start_prohibited(next, strictness),
+    };
+    let line_end_prohibited = match &kinsoku.line_end_prohibited {
+        Some(chars) => chars.contains(previous),
+        None => punctuation::is_line_end_prohibited(previous, strictness),
+    };
+
+    let inseparable = previous == next && punctuation::is_inseparable(next, strictness);
+
+    line_start_prohibited || line_end_prohibited || inseparable
+}
+
+/// Returns whether a character is set as CJK text.
+pub(super) fn is_cjk(ch: char) -> bool {
+    matches!(
+        ch,
+        // CJK symbols and punctuation, kana.
+        '\u{3000}'..='\u{30FF}'
+            // CJK unified ideographs extension A, CJK unified ideographs.
+            | '\u{3400}'..='\u{4DBF}'
+            | '\u{4E00}'..='\u{9FFF}'
+            // Hangul syllables.
+            | '\u{AC00}'..='\u{D7AF}'
+            // CJK compatibility ideographs.
+            | '\u{F900}'..='\u{FAFF}'
+            // Full-width forms.
+            | '\u{FF00}'..='\u{FFEF}'
+            // Supplementary ideographic planes.
+            | '\u{20000}'..='\u{3FFFF}'
+    )
+}
+
+/// Returns whether a character is a letter or a digit of a script other than CJK.
+pub(super) fn is_latin(ch: char) -> bool {
+    ch.is_alphanumeric() && !is_cjk(ch)
+}
+
+/// Returns the index in the placed glyphs of a line where the next line starts,
+/// when the character `next` overflows the line.
+///
+/// Characters are pushed out to the next line until the line can be broken, but a hanging
+/// punctuation mark is never moved, and at least one glyph is kept on the line. If there is
+/// no such place, the line is broken right before `next`.
+///
+/// `next_ruby_group` is the group ruby whose base text contains `next`, as the base text of a
+/// group ruby is not broken, and `next_joins_previous` is whether `next` follows another glyph
+/// of the same cluster, as a cluster is not broken either.
+pub(super) fn find_break(
+    line: &[Placement],
+    next: char,
+    next_ruby_group: Option<usize>,
+    next_joins_previous: bool,
+    layout_style: &LayoutStyle,
+) -> usize {
+    let (mut next, mut next_ruby_group, mut next_joins_previous) =
+        (next, next_ruby_group, next_joins_previous);
+
+    for index in (1..=line.len()).rev() {
+        let previous = &line[index - 1];
+        let in_same_ruby = previous.ruby_group.is_some() && previous.ruby_group == next_ruby_group;
+        if !in_same_ruby
+            && !next_joins_previous
+            && can_break_between(previous.ch, next, layout_style)
+        {
+            return index;
+        }
+        if previous.hanging {
+            break;
+        }
+        (next, next_ruby_group, next_joins_previous) =
+            (previous.ch, previous.ruby_group, previous.joins_previous);
+    }
+
+    line.len()
+}
+
+/// Returns how far, in pixels, each placed glyph of a line moves backwards to pull in the
+/// character `next` which overflows the line by `overflow` pixels, by squeezing the blank
+/// of full-width punctuation marks, or `None` if there is not enough blank to squeeze.
+pub(super) fn pull_in(
+    line: &[Placement],
+    next: char,
+    overflow: f64,
+    compression: bool,
+) -> Option<Vec<f64>> {
+    // with compression enabled, adjacent punctuation marks have been squeezed already
+    let is_adjustable = |index: usize| {
+        line.get(index)
+            .map(|placement| placement.ch)
+            .or(Some(next).filter(|_| index == line.len()))
+            .is_some_and(punctuation::is_adjustable)
+    };
+    let squeezable = (0..line.len())
+        .filter(|&index| {
+            is_adjustable(index)
+                && !(compression
+                    && ((index > 0 && is_adjustable(index - 1)) || is_adjustable(index + 1)))
+        })
+        .collect::<Vec<_>>();
+
+    if squeezable.is_empty() {
+        return None;
+    }
+
+    let share = overflow / squeezable.len() as f64;
+    if squeezable.iter().any(|&index| share > line[index].em / 2.) {
+        return None;
+    }
+
+    let mut shifts = vec![0.; line.len()];
+    for index in squeezable {
+        // the blank of an opening bracket is before it, otherwise after it
+        let from = if punctuation::is_opening(line[index].ch) {
+            index
+        } else {
+            index + 1
+        };
+        for shift in &mut shifts[from..] {
+            *shift -= share;
+        }
+    }
+
+    Some(shifts)
+}
@@ -0,0 +1,28 @@
+use std::ops::Range;
+
+/// How a line is ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineBreak {
+    /// ended by a line break in the text
+    Hard,
+    /// wrapped as the next character overflows the box
+    Wrap,
+    /// the last line of the text
+    End,
+}
+
+/// Metrics of a laid out line (a column in vertical layout), measured in pixels.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineInfo {
+    pub glyph_range: Range<usize>, // [start, end)
+    /// the y value of the baseline, or the x value of the central baseline of the column in vertical layout
+    pub baseline: f32,
+    /// the largest ascent of the fonts on the line, above the baseline
+    pub ascent: f32,
+    /// the largest descent of the fonts on the line, below the baseline
+    pub descent: f32,
+    /// the advance of the glyphs, excluding hanging punctuation and trailing spaces
+    pub width: f32,
+    pub line_break: LineBreak,
+    pub has_hanging_punctuation: bool,
+}
@@ -1,9 +1,9 @@
-/// Returns whether a character participates in full-width punctuation
-/// adjustment.
-pub(super) fn is_adjustable(ch: char) -> bool {
+use super::LineBreakStrictness;
+
+/// Returns whether a character is a full-width opening bracket or quotation mark.
+pub(super) fn is_opening(ch: char) -> bool {
     matches!(
         ch,
-        // Opening brackets and quotation marks.
         '（' | '〔'
             | '［'
             | '｛'
@@ -17,9 +17,14 @@ pub(super) fn is_adjustable(ch: char) -> bool {
             | '〚'
             | '‘'
             | '“'
-            // Closing brackets and quotation marks.
-            | '）'
-            | '〕'
+    )
+}
+
+/// Returns whether a character is a full-width closing bracket or quotation mark.
+pub(super) fn is_closing(ch: char) -> bool {
+    matches!(
+        ch,
+        '）' | '〕'
             | '］'
             | '｝'
             | '〉'
@@ -32,17 +37,20 @@ pub(super) fn is_adjustable(ch: char) -> bool {
             | '〛'
             | '’'
             | '”'
-            // Full-width pause and stop punctuation.
-            | '、'
-            | '，'
-            | '。'
-            | '：'
-            | '；'
-            | '？'
-            | '！'
     )
 }
 
+/// Returns whether a character is a full-width pause or stop punctuation mark.
+pub(super) fn is_pause(ch: char) -> bool {
+    matches!(ch, '、' | '，' | '。' | '：' | '；' | '？' | '！')
+}
+
+/// Returns whether a character participates in full-width punctuation
+/// adjustment.
+pub(super) fn is_adjustable(ch: char) -> bool {
+    is_opening(ch) || is_closing(ch) || is_pause(ch)
+}
+
 /// Returns the amount, in em, removed between two adjacent punctuation marks.
 pub(super) fn compression_between(previous: Option<char>, current: char) -> f64 {
     if previous.is_some_and(is_adjustable) && is_adjustable(current) {
@@ -52,7 +60,63 @@ pub(super) fn compression_between(previous: Option<char>, current: char) -> f64
     }
 }
 
-/// Returns whether a punctuation mark may hang at the end of a horizontal line.
+/// Returns whether a punctuation mark may hang at the end of a line.
 pub(super) fn is_hangable(ch: char) -> bool {
     matches!(ch, '、' | '，' | '。' | '：' | '；' | '？' | '！')
 }
+
+/// Returns whether a line may not start with the character, following JIS X 4051 and clreq.
+pub(super) fn is_line_start_prohibited(ch: char, strictness: LineBreakStrictness) -> bool {
+    let loose = is_closing(ch)
+        || is_pause(ch)
+        || matches!(
+            ch,
+            // Closing brackets and quotation marks not adjusted.
+            '〟' | '｠' | '»' | ')' | ']' | '}'
+                // Pause and stop punctuation not adjusted.
+                | '．' | ',' | '.' | ':' | ';' | '?' | '!' | '‼' | '⁇' | '⁈' | '⁉'
+        );
+    let normal = matches!(
+        ch,
+        // Middle dots.
+        '・' | '･' | '‧'
+            // Iteration marks.
+            | 'ヽ' | 'ヾ' | 'ゝ' | 'ゞ' | '々' | '〻'
+            // Inseparable characters.
+            | '‥' | '…'
+            // Hyphens.
+            | '‐' | '゠' | '–' | '〜' | '～'
+    );
+    let strict = matches!(
+        ch,
+        // Small kana.
+        'ぁ' | 'ぃ' | 'ぅ' | 'ぇ' | 'ぉ' | 'っ' | 'ゃ' | 'ゅ' | 'ょ' | 'ゎ' | 'ゕ' | 'ゖ'
+            | 'ァ' | 'ィ' | 'ゥ' | 'ェ' | 'ォ' | 'ッ' | 'ャ' | 'ュ' | 'ョ' | 'ヮ' | 'ヵ' | 'ヶ'
+            | 'ㇰ'..='ㇿ'
+            | 'ｧ'..='ｯ'
+            // Prolonged sound marks.
+            | 'ー' | 'ｰ'
+    );
+
+    match strictness {
+        LineBreakStrictness::Anywhere => false,
+        LineBreakStrictness::Loose => loose,
+        LineBreakStrictness::Normal => loose || normal,
+        LineBreakStrictness::Strict => loose || normal || strict,
+    }
+}
+
+/// Returns whether a line may not end with the character, following JIS X 4051 and clreq.
+pub(super) fn is_line_end_prohibited(ch: char, strictness: LineBreakStrictness) -> bool {
+    strictness != LineBreakStrictness::Anywhere
+        && (is_opening(ch) || matches!(ch, '〝' | '｟' | '«' | '(' | '[' | '{'))
+}
+
+/// Returns whether a line may not break between two same characters, e.g. a two-em dash
+/// written as two em dashes.
+pub(super) fn is_inseparable(ch: char, strictness: LineBreakStrictness) -> bool {
+    matches!(
+        strictness,
+        LineBreakStrictness::Normal | LineBreakStrictness::Strict
+    ) && matches!(ch, '…' | '‥' | '—' | '―')
+}
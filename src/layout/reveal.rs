@@ -0,0 +1,47 @@
+use crate::glyph_vertices::GlyphVertices;
+
+/// The progress of a typewriter reveal at a moment.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RevealProgress {
+    /// the number of glyphs from the start which have started to appear
+    pub visible_count: usize,
+    /// the opacity of the last visible glyph, and of the glyphs revealed at the same time,
+    /// from 0 to 1
+    pub frontier_alpha: f32,
+}
+
+/// The progress of a typewriter reveal after `elapsed` seconds, where each glyph fades in
+/// for `fade_duration` seconds from its `reveal_time`.
+///
+/// Glyphs of the text are revealed in order, so the first `visible_count` glyphs are drawn.
+/// Ruby annotations follow all glyphs of the text, so they are better drawn by their own
+/// `reveal_time` with `reveal_alpha`.
+pub fn reveal_progress(
+    glyphs: &[GlyphVertices],
+    elapsed: f32,
+    fade_duration: f32,
+) -> RevealProgress {
+    let visible_count = glyphs
+        .iter()
+        .take_while(|glyph| glyph.reveal_time <= elapsed)
+        .count();
+    let frontier_alpha = match visible_count.checked_sub(1) {
+        Some(frontier) => reveal_alpha(&glyphs[frontier], elapsed, fade_duration),
+        None => 0.,
+    };
+
+    RevealProgress {
+        visible_count,
+        frontier_alpha,
+    }
+}
+
+/// The opacity of a glyph after `elapsed` seconds, which fades in for `fade_duration` seconds
+/// from its `reveal_time`.
+pub fn reveal_alpha(glyph: &GlyphVertices, elapsed: f32, fade_duration: f32) -> f32 {
+    if fade_duration <= 0. {
+        return if elapsed >= glyph.reveal_time { 1. } else { 0. };
+    }
+
+    ((elapsed - glyph.reveal_time) / fade_duration).clamp(0., 1.)
+}
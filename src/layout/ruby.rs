@@ -0,0 +1,68 @@
+use std::ops::Range;
+
+use crate::parser::{Ruby, RubyMode, TextStyle};
+
+/// The font size of ruby annotations relative to their base text.
+pub(super) const RUBY_FONT_SIZE_RATIO: f64 = 0.5;
+
+/// The base text of a ruby placed in the layout.
+pub(super) struct RubyBase {
+    pub ruby: Ruby,
+    /// the style of the base text
+    pub style: TextStyle,
+    pub glyph_range: Range<usize>,
+}
+
+impl RubyBase {
+    /// Mono ruby whose annotations do not match the characters of the base text falls back to
+    /// group ruby of the joined annotations.
+    pub fn new(ruby: &Ruby, style: &TextStyle, base_text: &str, glyph_start: usize) -> Self {
+        let ruby =
+            if ruby.mode == RubyMode::Mono && ruby.annotations.len() != base_text.chars().count() {
+                Ruby {
+                    annotations: vec![ruby.annotations.concat()],
+                    mode: RubyMode::Group,
+                }
+            } else {
+                ruby.clone()
+            };
+
+        Self {
+            ruby,
+            style: style.clone(),
+            glyph_range: glyph_start..glyph_start,
+        }
+    }
+
+    /// The style of the annotation text, which is set solid in a smaller size.
+    pub fn annotation_style(&self) -> TextStyle {
+        TextStyle {
+            font_size: self.style.font_size * RUBY_FONT_SIZE_RATIO,
+            line_height: 1.,
+            indent: 0.,
+            ..self.style.clone()
+        }
+    }
+
+    /// The space taken by the annotation across the line, in pixels.
+    pub fn annotation_size(&self) -> f64 {
+        self.style.font_size * RUBY_FONT_SIZE_RATIO
+    }
+
+    /// The annotations with the glyphs they are centered over.
+    pub fn annotations(&self) -> Vec<(String, Range<usize>)> {
+        let Range { start, end } = self.glyph_range;
+
+        match self.ruby.mode {
+            // the base text may be shaped into fewer glyphs than characters, e.g. ligatures
+            RubyMode::Mono if self.ruby.annotations.len() == end - start => self
+                .ruby
+                .annotations
+                .iter()
+                .enumerate()
+                .map(|(index, annotation)| (annotation.clone(), start + index..start + index + 1))
+                .collect(),
+            _ => vec![(self.ruby.annotations.concat(), start..end)],
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use std::ops::Range;
+
+use crate::Huozi;
+
+use super::{
+    LayoutDirection, LayoutStyle,
+    vertical::{self, VerticalOrientation},
+};
+
+/// A unit positioned by the layout, which is either a plain character or a shaped glyph.
+pub(super) struct LayoutUnit {
+    /// the character, or the first character of the cluster for a shaped glyph
+    pub ch: char,
+    /// the byte range of the character or the cluster in the text of the run
+    pub byte_range: Range<usize>,
+    /// the font index and glyph id of a shaped glyph
+    pub glyph_id: Option<(usize, u16)>,
+    /// the shaped advance, in atlas font size
+    pub h_advance: Option<f64>,
+    /// the shaped offset along x, in atlas font size
+    pub x_offset: f64,
+    /// the shaped offset along y (upwards), in atlas font size
+    pub y_offset: f64,
+    /// the change of the vertical advance by font features such as `vhal`, in atlas font size
+    pub v_advance_delta: f64,
+    /// how the glyph is set in a vertical line, or `None` in a horizontal line
+    pub orientation: Option<VerticalOrientation>,
+    /// whether the glyph is a vertical alternate from a feature such as `vert`,
+    /// which is set upright and drawn in its place in the column by the font
+    pub vertical_alternate: bool,
+    /// whether the glyph follows another glyph of the same cluster,
+    /// so that a line is never broken before it
+    pub joins_previous: bool,
+}
+
+impl LayoutUnit {
+    fn from_char((index, ch): (usize, char), is_vertical: bool) -> Self {
+        Self {
+            ch,
+            byte_range: index..index + ch.len_utf8(),
+            glyph_id: None,
+            h_advance: None,
+            x_offset: 0.,
+            y_offset: 0.,
+            v_advance_delta: 0.,
+            orientation: is_vertical.then(|| vertical::vertical_orientation(ch)),
+            vertical_alternate: false,
+            joins_previous: false,
+        }
+    }
+}
+
+impl Huozi {
+    /// Split the text of a run into layout units, one per character.
+    #[cfg(not(feature = "shaping"))]
+    pub(super) fn layout_units(
+        &self,
+        text: &str,
+        _font_family: Option<usize>,
+        layout_style: &LayoutStyle,
+    ) -> Vec<LayoutUnit> {
+        let is_vertical = layout_style.direction == LayoutDirection::Vertical;
+        text.char_indices()
+            .map(|char_index| LayoutUnit::from_char(char_index, is_vertical))
+            .collect()
+    }
+
+    /// Shape the text of a run into layout units, one per glyph.
+    ///
+    /// The text is itemized by line breaks and by the font containing each character,
+    /// then each item is shaped with its own font. In a vertical line, CJK text is shaped
+    /// from top to bottom, so that vertical features such as `vert` and `vhal` apply,
+    /// while scripts set sideways are shaped as in a horizontal line.
+    #[cfg(feature = "shaping")]
+    pub(super) fn layout_units(
+        &self,
+        text: &str,
+        font_family: Option<usize>,
+        layout_style: &LayoutStyle,
+    ) -> Vec<LayoutUnit> {
+        use std::str::FromStr;
+
+        let features = layout_style
+            .font_features
+            .iter()
+            .filter_map(|feature| {
+                rustybuzz::Feature::from_str(feature)
+                    .inspect_err(|_| log::warn!("invalid font feature `{}`, ignored.", feature))
+                    .ok()
+            })
+            .collect::<Vec<_>>();
+        let is_vertical = layout_style.direction == LayoutDirection::Vertical;
+
+        let mut units = Vec::with_capacity(text.len());
+        let mut item_start = 0;
+        // the font and whether the item is shaped from top to bottom
+        let mut item = None;
+
+        for (i, ch) in text.char_indices() {
+            if ch == '\n' || ch == '\r' {
+                if let Some((font_index, vertical)) = item.take() {
+                    let range = item_start..i;
+                    self.shape_item(text, range, font_index, vertical, &features, &mut units);
+                }
+                units.push(LayoutUnit::from_char((i, ch), is_vertical));
+                item_start = i + ch.len_utf8();
+                continue;
+            }
+
+            let font_index = self
+                .resolve_font(font_family, ch)
+                .unwrap_or(font_family.unwrap_or(self.primary_font()));
+            let next_item = (font_index, is_vertical && !vertical::is_sideways_script(ch));
+
+            if item != Some(next_item) {
+                if let Some((font_index, vertical)) = item {
+                    let range = item_start..i;
+                    self.shape_item(text, range, font_index, vertical, &features, &mut units);
+                }
+                item_start = i;
+                item = Some(next_item);
+            }
+        }
+
+        if let Some((font_index, vertical)) = item {
+            let range = item_start..text.len();
+            self.shape_item(text, range, font_index, vertical, &features, &mut units);
+        }
+
+        if is_vertical {
+            for unit in units.iter_mut().filter(|unit| unit.orientation.is_none()) {
+                unit.orientation = Some(vertical::vertical_orientation(unit.ch));
+            }
+        }
+
+        units
+    }
+
+    #[cfg(feature = "shaping")]
+    fn shape_item(
+        &self,
+        text: &str,
+        range: std::ops::Range<usize>,
+        font_index: usize,
+        vertical: bool,
+        features: &[rustybuzz::Feature],
+        units: &mut Vec<LayoutUnit>,
+    ) {
+        let item = &text[range.clone()];
+
+        let Some(face) = rustybuzz::Face::from_slice(self.font_data(font_index), 0) else {
+            log::warn!(
+                "cannot load font #{} for shaping, fallback to characters.",
+                font_index
+            );
+            units.extend(
+                item.char_indices()
+                    .map(|(i, ch)| LayoutUnit::from_char((range.start + i, ch), false)),
+            );
+            return;
+        };
+
+        let mut buffer = rustybuzz::UnicodeBuffer::new();
+        buffer.push_str(item);
+        buffer.guess_segment_properties();
+        if vertical {
+            buffer.set_direction(rustybuzz::Direction::TopToBottom);
+        }
+
+        let glyph_buffer = rustybuzz::shape(&face, features, buffer);
+        let scale = self.config().font_size / face.units_per_em() as f64;
+
+        let glyph_infos = glyph_buffer.glyph_infos();
+        // glyphs may be in the visual order, e.g. reversed in right-to-left text,
+        // so clusters are ordered by their starts in the text
+        let mut cluster_starts = glyph_infos
+            .iter()
+            .map(|info| info.cluster as usize)
+            .collect::<Vec<_>>();
+        cluster_starts.sort_unstable();
+        cluster_starts.dedup();
+
+        for (i, (info, position)) in glyph_infos
+            .iter()
+            .zip(glyph_buffer.glyph_positions())
+            .enumerate()
+        {
+            let cluster = info.cluster as usize;
+            let ch = item[cluster..].chars().next().unwrap();
+            // the cluster ends where the next one in the text starts
+            let cluster_end = cluster_starts
+                .get(cluster_starts.partition_point(|&start| start <= cluster))
+                .copied()
+                .unwrap_or(item.len());
+            let joins_previous = i > 0 && glyph_infos[i - 1].cluster == info.cluster;
+
+            let mut unit = LayoutUnit {
+                ch,
+                byte_range: range.start + cluster..range.start + cluster_end,
+                glyph_id: Some((font_index, info.glyph_id as u16)),
+                h_advance: Some(position.x_advance as f64 * scale),
+                x_offset: position.x_offset as f64 * scale,
+                y_offset: position.y_offset as f64 * scale,
+                v_advance_delta: 0.,
+                orientation: None,
+                vertical_alternate: false,
+                joins_previous,
+            };
+
+            if vertical {
+                // positions from top to bottom are measured from the vertical origin of the
+                // glyph, so only the adjustments of the features are kept, as the glyph is
+                // centered in the column by the layout
+                let glyph = rustybuzz::ttf_parser::GlyphId(info.glyph_id as u16);
+                let (v_advance, origin_x, origin_y) = default_vertical_position(&face, glyph);
+                unit.h_advance = None;
+                unit.x_offset = (position.x_offset + origin_x) as f64 * scale;
+                unit.y_offset = (position.y_offset + origin_y) as f64 * scale;
+                unit.v_advance_delta = (-position.y_advance - v_advance) as f64 * scale;
+                unit.vertical_alternate = item[cluster..cluster_end]
+                    .chars()
+                    .all(|ch| face.glyph_index(ch) != Some(glyph));
+                unit.orientation = Some(if unit.vertical_alternate {
+                    VerticalOrientation::Upright
+                } else {
+                    vertical::vertical_orientation(ch)
+                });
+                // rotated glyphs are set as in a horizontal line
+                if unit.orientation == Some(VerticalOrientation::Rotated) {
+                    (unit.x_offset, unit.y_offset, unit.v_advance_delta) = (0., 0., 0.);
+                }
+            }
+
+            units.push(unit);
+        }
+    }
+}
+
+/// The vertical advance of a glyph and its vertical origin from its horizontal origin,
+/// in font units, which rustybuzz gives to glyphs shaped from top to bottom before applying
+/// font features.
+#[cfg(feature = "shaping")]
+fn default_vertical_position(
+    face: &rustybuzz::Face,
+    glyph: rustybuzz::ttf_parser::GlyphId,
+) -> (i32, i32, i32) {
+    let has_vmtx = face.tables().vmtx.is_some();
+    let v_advance = if has_vmtx {
+        face.glyph_ver_advance(glyph).unwrap_or(0) as i32
+    } else {
+        face.ascender() as i32 - face.descender() as i32
+    };
+    let h_advance = face.glyph_hor_advance(glyph).unwrap_or(0) as i32;
+
+    let origin_y = match (face.glyph_y_origin(glyph), face.glyph_bounding_box(glyph)) {
+        (Some(y), _) => y as i32,
+        (None, Some(bbox)) if has_vmtx => {
+            bbox.y_max as i32 + face.glyph_ver_side_bearing(glyph).unwrap_or(0) as i32
+        }
+        // centered in the vertical advance
+        (None, Some(bbox)) => {
+            bbox.y_max as i32 + ((v_advance - (bbox.y_max - bbox.y_min) as i32) >> 1)
+        }
+        (None, None) => face.ascender() as i32,
+    };
+
+    (v_advance, h_advance / 2, origin_y)
+}
@@ -0,0 +1,66 @@
+/// How a character is set in a vertical line, a simplified version of
+/// [UAX #50](https://www.unicode.org/reports/tr50/).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum VerticalOrientation {
+    /// Set upright, advancing by the vertical advance of the glyph.
+    Upright,
+    /// Rotated 90° clockwise, advancing by the horizontal advance of the glyph.
+    Rotated,
+}
+
+/// Returns whether a character belongs to a script set sideways in a vertical line, such as
+/// Latin, Greek and Cyrillic, whose text is shaped as in a horizontal line before rotating.
+pub(super) fn is_sideways_script(ch: char) -> bool {
+    matches!(ch, '\u{0000}'..='\u{10FF}')
+}
+
+/// Returns the orientation of a character in a vertical line.
+pub(super) fn vertical_orientation(ch: char) -> VerticalOrientation {
+    let rotated = is_sideways_script(ch)
+        || matches!(
+            ch,
+            // General punctuation, e.g. dashes, quotation marks and ellipses.
+            '\u{2000}'..='\u{206F}'
+            // Arrows.
+            | '\u{2190}'..='\u{21FF}'
+            // Two-em and three-em dashes.
+            | '⸺'
+            | '⸻'
+            // CJK brackets.
+            | '〈'..='】'
+            | '〔'..='〛'
+            // Wave dash.
+            | '〜'
+            // Full-width brackets, colons, dashes and tildes.
+            | '（'
+            | '）'
+            | '－'
+            | '：'
+            | '；'
+            | '＜'
+            | '＞'
+            | '［'
+            | '］'
+            | '＿'
+            | '｛'
+            | '｜'
+            | '｝'
+            | '～'
+            | '｟'
+            | '｠'
+            | '｢'
+            | '｣'
+        );
+
+    if rotated {
+        VerticalOrientation::Rotated
+    } else {
+        VerticalOrientation::Upright
+    }
+}
+
+/// Returns whether a punctuation mark is set at the top-right corner of the em box
+/// in a vertical line, instead of the bottom-left corner in a horizontal one.
+pub(super) fn is_corner_punctuation(ch: char) -> bool {
+    matches!(ch, '、' | '。' | '，' | '．' | '､' | '｡')
+}
@@ -1,5 +1,6 @@
 #[cfg(feature = "charsets")]
 pub mod charsets;
+mod config;
 pub mod constant;
 pub mod font_extractor;
 #[cfg(feature = "layout")]
@@ -11,4 +12,5 @@ pub mod parser;
 #[cfg(feature = "sdf")]
 pub mod sdf;
 
+pub use crate::config::*;
 pub use crate::huozi::*;
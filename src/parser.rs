@@ -1,22 +1,42 @@
+mod diagnostic;
 mod elements_to_spans;
+mod import;
+mod inline_marker;
 mod parse_elements;
+mod parser_config;
+mod ruby;
 mod segment;
+mod serialize_markup;
 mod source_range;
+mod style_prefab;
+mod tag_registry;
 mod text_run;
 mod text_span;
 mod text_style;
+mod variable_resolver;
 
+pub use diagnostic::*;
 pub(crate) use elements_to_spans::*;
+pub use import::*;
+pub use inline_marker::*;
 pub use parse_elements::*;
+pub use parser_config::*;
+pub use ruby::*;
 pub use segment::*;
+pub use serialize_markup::*;
 pub use source_range::*;
+pub use style_prefab::*;
+pub use tag_registry::*;
 pub use text_run::*;
 pub use text_span::*;
 pub use text_style::*;
+pub use variable_resolver::*;
 
 #[cfg(test)]
 mod tests {
-    use crate::parser::{parse_elements::*, Segment};
+    use crate::parser::{
+        parse_elements::*, DiagnosticCode, ParserConfig, Segment, SegmentId, Severity, SourceRange,
+    };
 
     #[test]
     fn plain_text() {
@@ -117,7 +137,9 @@ mod tests {
                         segment_id: None,
                     }],
                     tag: "real".to_string(),
-                    value: None
+                    value: None,
+                    attributes: vec![],
+                    segment_id: None,
                 }
             ]
         );
@@ -144,7 +166,9 @@ mod tests {
                         segment_id: None,
                     }],
                     tag: "real".to_string(),
-                    value: None
+                    value: None,
+                    attributes: vec![],
+                    segment_id: None,
                 }
             ]
         );
@@ -174,7 +198,9 @@ mod tests {
                         segment_id: None,
                     }],
                     tag: "bold".to_string(),
-                    value: None
+                    value: None,
+                    attributes: vec![],
+                    segment_id: None,
                 },
                 Element::Text {
                     start: 60,
@@ -200,7 +226,9 @@ mod tests {
                     segment_id: None,
                 }],
                 tag: "foo".to_string(),
-                value: None
+                value: None,
+                attributes: vec![],
+                segment_id: None,
             }]
         );
     }
@@ -219,7 +247,9 @@ mod tests {
                     segment_id: None,
                 }],
                 tag: "".to_string(),
-                value: None
+                value: None,
+                attributes: vec![],
+                segment_id: None,
             }]
         );
     }
@@ -238,7 +268,9 @@ mod tests {
                     segment_id: None,
                 }],
                 tag: "foo".to_string(),
-                value: Some("bar".to_string())
+                value: Some("bar".to_string()),
+                attributes: vec![],
+                segment_id: None,
             }]
         );
     }
@@ -257,7 +289,9 @@ mod tests {
                     segment_id: None,
                 }],
                 tag: "foo".to_string(),
-                value: Some("bar ".to_string())
+                value: Some("bar ".to_string()),
+                attributes: vec![],
+                segment_id: None,
             }]
         );
     }
@@ -276,9 +310,31 @@ mod tests {
                     segment_id: None,
                 }],
                 tag: "foo".to_string(),
-                value: Some("bar ".to_string())
+                value: Some("bar ".to_string()),
+                attributes: vec![],
+                segment_id: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn quoted_value_with_doubled_quotes() {
+        assert_eq!(
+            parse(&Segment::dummy(r#"[foo="say ""hi""" bar='it''s' baz=""/]"#)).unwrap(),
+            vec![Element::Void {
+                start: 0,
+                end: 38,
+                tag: "foo".to_string(),
+                value: Some(r#"say "hi""#.to_string()),
+                attributes: vec![
+                    ("bar".to_string(), "it's".to_string()),
+                    ("baz".to_string(), "".to_string()),
+                ],
+                segment_id: None,
             }]
         );
+        // a quote which is not doubled still ends the value
+        assert!(parse(&Segment::dummy(r#"[foo="a"b"]text[/foo]"#)).is_err());
     }
 
     #[test]
@@ -295,7 +351,9 @@ mod tests {
                     segment_id: None,
                 }],
                 tag: "foo".to_string(),
-                value: Some("bar".to_string())
+                value: Some("bar".to_string()),
+                attributes: vec![],
+                segment_id: None,
             }]
         );
     }
@@ -321,7 +379,9 @@ mod tests {
                         segment_id: None,
                     }],
                     tag: "foo".to_string(),
-                    value: Some("bar".to_string())
+                    value: Some("bar".to_string()),
+                    attributes: vec![],
+                    segment_id: None,
                 }
             ]
         );
@@ -339,10 +399,14 @@ mod tests {
                     end: 22,
                     inner: vec![],
                     tag: "xx".to_string(),
-                    value: Some("123".to_string())
+                    value: Some("123".to_string()),
+                    attributes: vec![],
+                    segment_id: None,
                 }],
                 tag: "foo".to_string(),
-                value: Some("bar".to_string())
+                value: Some("bar".to_string()),
+                attributes: vec![],
+                segment_id: None,
             }]
         );
     }
@@ -377,7 +441,9 @@ mod tests {
                             end: 26,
                             inner: vec![],
                             tag: "xx".to_string(),
-                            value: Some("123".to_string())
+                            value: Some("123".to_string()),
+                            attributes: vec![],
+                            segment_id: None,
                         },
                         Element::Text {
                             start: 26,
@@ -387,14 +453,18 @@ mod tests {
                         }
                     ],
                     tag: "foo".to_string(),
-                    value: Some("bar".to_string())
+                    value: Some("bar".to_string()),
+                    attributes: vec![],
+                    segment_id: None,
                 },
                 Element::Block {
                     start: 33,
                     end: 42,
                     inner: vec![],
                     tag: "yy".to_string(),
-                    value: None
+                    value: None,
+                    attributes: vec![],
+                    segment_id: None,
                 }
             ]
         );
@@ -414,11 +484,202 @@ mod tests {
                     segment_id: None,
                 }],
                 tag: "foo".to_string(),
-                value: Some("bar ".to_string())
+                value: Some("bar ".to_string()),
+                attributes: vec![],
+                segment_id: None,
             }]
         );
     }
 
+    #[test]
+    fn void_wait_tag() {
+        assert_eq!(
+            parse(&Segment::dummy("a[wait=0.5]b")).unwrap(),
+            vec![
+                Element::Text {
+                    start: 0,
+                    end: 1,
+                    content: "a".to_string(),
+                    segment_id: None,
+                },
+                Element::Void {
+                    start: 1,
+                    end: 11,
+                    tag: "wait".to_string(),
+                    value: Some("0.5".to_string()),
+                    attributes: vec![],
+                    segment_id: None,
+                },
+                Element::Text {
+                    start: 11,
+                    end: 12,
+                    content: "b".to_string(),
+                    segment_id: None,
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn tag_with_attributes() {
+        assert_eq!(
+            parse(&Segment::dummy(
+                "[style color=red size = 40 stroke=\"#fff 2\"]a[/style]"
+            ))
+            .unwrap(),
+            vec![Element::Block {
+                start: 0,
+                end: 52,
+                inner: vec![Element::Text {
+                    start: 43,
+                    end: 44,
+                    content: "a".to_string(),
+                    segment_id: None,
+                }],
+                tag: "style".to_string(),
+                value: None,
+                attributes: vec![
+                    ("color".to_string(), "red".to_string()),
+                    ("size".to_string(), "40".to_string()),
+                    ("stroke".to_string(), "#fff 2".to_string()),
+                ],
+                segment_id: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn tag_value_with_attributes() {
+        assert_eq!(
+            parse(&Segment::dummy("[icon=coin size=20/]")).unwrap(),
+            vec![Element::Void {
+                start: 0,
+                end: 20,
+                tag: "icon".to_string(),
+                value: Some("coin".to_string()),
+                attributes: vec![("size".to_string(), "20".to_string())],
+                segment_id: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn attribute_without_value() {
+        assert!(parse(&Segment::dummy("[style color]a[/style]")).is_err());
+    }
+
+    #[test]
+    fn self_closing_void_tag() {
+        assert_eq!(
+            parse(&Segment::dummy("[br/][icon = \"a/b\" /]")).unwrap(),
+            vec![
+                Element::Void {
+                    start: 0,
+                    end: 5,
+                    tag: "br".to_string(),
+                    value: None,
+                    attributes: vec![],
+                    segment_id: None,
+                },
+                Element::Void {
+                    start: 5,
+                    end: 21,
+                    tag: "icon".to_string(),
+                    value: Some("a/b".to_string()),
+                    attributes: vec![],
+                    segment_id: None,
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn configured_void_tags() {
+        let segment = Segment::dummy("[icon=coin]x[[br]]");
+        assert!(parse(&segment).is_err());
+        assert_eq!(
+            parse_with_config(
+                &segment,
+                &ParserConfig {
+                    void_tags: vec!["icon".to_string(), "br".to_string()],
+                    ..Default::default()
+                }
+            )
+            .unwrap(),
+            vec![
+                Element::Void {
+                    start: 0,
+                    end: 11,
+                    tag: "icon".to_string(),
+                    value: Some("coin".to_string()),
+                    attributes: vec![],
+                    segment_id: None,
+                },
+                Element::Text {
+                    start: 11,
+                    end: 18,
+                    content: "x[br]".to_string(),
+                    segment_id: None,
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn void_tag_has_no_end_tag() {
+        assert!(parse(&Segment::dummy("[br/]text[/br]")).is_err());
+    }
+
+    #[test]
+    fn escape_character() {
+        let config = ParserConfig {
+            escape: Some('\\'),
+            ..Default::default()
+        };
+        assert_eq!(
+            parse_with_config(&Segment::dummy(r"\[a\]\\[b]c[/b]"), &config).unwrap(),
+            vec![
+                Element::Text {
+                    start: 0,
+                    end: 7,
+                    content: r"[a]\".to_string(),
+                    segment_id: None,
+                },
+                Element::Block {
+                    start: 7,
+                    end: 15,
+                    inner: vec![Element::Text {
+                        start: 10,
+                        end: 11,
+                        content: "c".to_string(),
+                        segment_id: None,
+                    }],
+                    tag: "b".to_string(),
+                    value: None,
+                    attributes: vec![],
+                    segment_id: None,
+                }
+            ]
+        );
+        // doubled tag characters and lone escape characters are not escapes
+        assert!(parse_with_config(&Segment::dummy("[[a]]"), &config).is_err());
+        assert!(parse_with_config(&Segment::dummy(r"a\n"), &config).is_err());
+    }
+
+    #[test]
+    fn custom_slash() {
+        let config = ParserConfig {
+            slash: '|',
+            ..Default::default()
+        };
+        assert_eq!(
+            parse_with_config(&Segment::dummy("[a]x/y[|a][br|]"), &config)
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
     #[test]
     fn empty() {
         assert_eq!(parse(&Segment::dummy("")).unwrap(), vec![]);
@@ -428,4 +689,154 @@ mod tests {
     fn failed() {
         assert!(parse(&Segment::dummy("foo[bar]")).is_err());
     }
+
+    #[test]
+    fn syntax_error_diagnostic() {
+        let segment = Segment {
+            id: Some(SegmentId::Lite(3)),
+            content: "x\n一[b]y[/b".into(),
+        };
+        let error = parse(&segment).unwrap_err();
+        let diagnostic = error.diagnostic();
+
+        assert_eq!(diagnostic.severity, Severity::Error);
+        assert_eq!(diagnostic.code, DiagnosticCode::Syntax);
+        assert_eq!(
+            diagnostic.source_range,
+            SourceRange {
+                segment_id: Some(SegmentId::Lite(3)),
+                start: 12,
+                end: 12,
+            }
+        );
+        // the column counts characters rather than bytes
+        assert_eq!((diagnostic.line, diagnostic.column), (2, 9));
+        assert_eq!(diagnostic.expected, ["`]`"]);
+        assert_eq!(
+            error.to_string(),
+            "2:9: error: unexpected end of text in end tag, expected `]`"
+        );
+
+        let error = parse(&Segment::dummy("a[b]x[/c]")).unwrap_err();
+        assert_eq!(
+            error.diagnostic().message,
+            "the end tag does not match the tag"
+        );
+        assert_eq!(error.diagnostic().source_range.start, 1);
+    }
+
+    #[test]
+    fn lenient_unclosed_tags() {
+        let (elements, diagnostics) = parse_lenient(&Segment::dummy("[i][b]x[/i]y[color=red]z"));
+        assert_eq!(
+            elements,
+            vec![
+                Element::Block {
+                    start: 0,
+                    end: 11,
+                    inner: vec![Element::Block {
+                        start: 3,
+                        end: 7,
+                        inner: vec![Element::Text {
+                            start: 6,
+                            end: 7,
+                            content: "x".to_string(),
+                            segment_id: None,
+                        }],
+                        tag: "b".to_string(),
+                        value: None,
+                        attributes: vec![],
+                        segment_id: None,
+                    }],
+                    tag: "i".to_string(),
+                    value: None,
+                    attributes: vec![],
+                    segment_id: None,
+                },
+                Element::Text {
+                    start: 11,
+                    end: 12,
+                    content: "y".to_string(),
+                    segment_id: None,
+                },
+                Element::Block {
+                    start: 12,
+                    end: 24,
+                    inner: vec![Element::Text {
+                        start: 23,
+                        end: 24,
+                        content: "z".to_string(),
+                        segment_id: None,
+                    }],
+                    tag: "color".to_string(),
+                    value: Some("red".to_string()),
+                    attributes: vec![],
+                    segment_id: None,
+                },
+            ]
+        );
+
+        let problems = diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let range = &diagnostic.source_range;
+                (diagnostic.code, range.start, range.end)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            problems,
+            [
+                (DiagnosticCode::UnclosedTag, 3, 6),
+                (DiagnosticCode::UnclosedTag, 12, 23)
+            ]
+        );
+        assert_eq!(diagnostics[1].expected, ["`[/color]`"]);
+    }
+
+    #[test]
+    fn lenient_stray_tag_characters() {
+        let (elements, diagnostics) = parse_lenient(&Segment::dummy("a[/b]c]d"));
+        let texts = elements
+            .iter()
+            .map(|element| match element {
+                Element::Text {
+                    start,
+                    end,
+                    content,
+                    ..
+                } => (content.as_str(), *start, *end),
+                _ => panic!("unexpected element {:?}", element),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            texts,
+            [
+                ("a", 0, 1),
+                ("[/b]", 1, 5),
+                ("c", 5, 6),
+                ("]", 6, 7),
+                ("d", 7, 8)
+            ]
+        );
+
+        let codes = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.severity, diagnostic.code))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            codes,
+            [
+                (Severity::Error, DiagnosticCode::UnmatchedEndTag),
+                (Severity::Error, DiagnosticCode::Syntax)
+            ]
+        );
+    }
+
+    #[test]
+    fn lenient_well_formed() {
+        let input = Segment::dummy("a[[b]][color=red]c[wait=1]d[/color]");
+        let (elements, diagnostics) = parse_lenient(&input);
+        assert_eq!(elements, parse(&input).unwrap());
+        assert!(diagnostics.is_empty());
+    }
 }
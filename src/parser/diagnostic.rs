@@ -0,0 +1,121 @@
+use std::fmt;
+
+use crate::parser::SourceRange;
+
+/// How severe a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// the markup is malformed, which fails the parsing unless it is lenient
+    Error,
+    /// the text is parsed, but part of the markup is ignored
+    Warning,
+}
+
+/// What a diagnostic is about, for tools to match on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticCode {
+    /// the markup is malformed, e.g. a tag head without the close character
+    Syntax,
+    /// a tag without its end tag
+    UnclosedTag,
+    /// an end tag without its tag
+    UnmatchedEndTag,
+    /// a tag has no handler, and is not a style prefab
+    UnknownTag,
+    /// an attribute of a tag has no handler
+    UnknownAttribute,
+    /// the value of a tag or an attribute is missing or rejected by its handler
+    InvalidValue,
+    /// a tag where it has no effect, e.g. a tag inside a ruby
+    IgnoredTag,
+    /// a variable in a `var` tag which the resolver does not know
+    UnknownVariable,
+    /// a style prefab which extends an unknown prefab, or extends itself
+    InvalidPrefab,
+    /// a construct of an imported dialect which has no equivalent, e.g. bold text
+    Unsupported,
+}
+
+/// A problem in the markup of a segment, such as `[size=abc]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: DiagnosticCode,
+    /// the segment and the byte range in it, which is the tag with the problem
+    pub source_range: SourceRange,
+    /// the line of the start of `source_range`, starting from 1
+    pub line: usize,
+    /// the column of the start of `source_range` in characters, starting from 1
+    pub column: usize,
+    pub message: String,
+    /// the tokens which would be valid at the problem, for a syntax error
+    pub expected: Vec<String>,
+}
+
+impl Diagnostic {
+    /// Create a diagnostic in the content of a segment, locating the line and column
+    /// of the source range.
+    pub(crate) fn new(
+        severity: Severity,
+        code: DiagnosticCode,
+        source_range: SourceRange,
+        content: &str,
+        message: String,
+    ) -> Self {
+        let before = &content[..source_range.start.min(content.len())];
+        let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+
+        Self {
+            severity,
+            code,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            source_range,
+            message,
+            expected: vec![],
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.line, self.column, severity, self.message
+        )?;
+        if !self.expected.is_empty() {
+            write!(f, ", expected {}", self.expected.join(" or "))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A failure to parse text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// the markup of a segment is malformed
+    Syntax(Diagnostic),
+}
+
+impl ParseError {
+    /// The diagnostic of the failure.
+    pub fn diagnostic(&self) -> &Diagnostic {
+        match self {
+            ParseError::Syntax(diagnostic) => diagnostic,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.diagnostic().fmt(f)
+    }
+}
+
+impl std::error::Error for ParseError {}
@@ -1,185 +1,469 @@
-use std::str::FromStr;
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
-use anyhow::Result;
-
 use crate::parser::*;
 
+type ElementIter = Rc<RefCell<std::vec::IntoIter<Element>>>;
+
+/// Convert elements parsed with default square bracket tags `[]` to text spans,
+/// with the handlers of the built-in tags. The diagnostics are discarded.
+#[cfg(test)]
 pub(crate) fn to_spans(
     elements: Vec<Element>,
     current_style: &TextStyle,
-    style_prefabs: Option<&HashMap<String, TextStyle>>,
+    style_prefabs: Option<&HashMap<String, StylePrefab>>,
 ) -> Result<Vec<TextSpan>, String> {
+    let (spans, _) = to_spans_with_config(
+        vec![(&Segment::dummy(""), elements)],
+        current_style,
+        style_prefabs,
+        &ParserConfig::default(),
+        &TagRegistry::default(),
+        None,
+    );
+    Ok(spans)
+}
+
+/// Convert the elements of each segment parsed with the config to text spans, with the tag
+/// handlers in the registry and the values of `var` tags from the resolver. The markup which is ignored is reported as diagnostics,
+/// located in the content of its segment.
+pub(crate) fn to_spans_with_config(
+    parsed_segments: Vec<(&Segment<'_>, Vec<Element>)>,
+    current_style: &TextStyle,
+    style_prefabs: Option<&HashMap<String, StylePrefab>>,
+    config: &ParserConfig,
+    registry: &TagRegistry,
+    variables: Option<&dyn VariableResolver>,
+) -> (Vec<TextSpan>, Vec<Diagnostic>) {
     let mut spans = vec![];
     let mut current_runs = vec![];
+    let mut diagnostics = vec![];
 
     // (elements iterator, current style, is_span)
-    let mut stack: Vec<(Rc<RefCell<std::vec::IntoIter<Element>>>, TextStyle, bool)> = vec![];
+    let mut stack: Vec<(ElementIter, TextStyle, bool)> = vec![];
     let mut current_style = current_style.clone();
-    let mut elements = Rc::new(RefCell::new(elements.into_iter()));
-
-    loop {
-        let elements_remaining = elements.borrow_mut().len();
-        if elements_remaining == 0 {
-            if !stack.is_empty() {
-                let (next_elements, next_style, is_span) = stack.pop().unwrap();
-                elements = next_elements;
-                current_style = next_style;
-
-                if is_span && !current_runs.is_empty() {
-                    let runs = current_runs.drain(..).collect();
-                    let span = TextSpan {
-                        runs,
-                        span_id: Some(SpanId::Lite(0)),
-                    };
-                    spans.push(span);
-                }
+    // the pause from `wait` tags before the next run
+    let mut reveal_delay = 0.;
+    // the end of the last segment, where a pause at the end of the text is kept
+    let mut text_end = None;
+
+    // the tags of a segment are closed in the segment, while the runs of consecutive segments
+    // are joined into the same span
+    for (segment, segment_elements) in parsed_segments {
+        let mut reporter = Reporter {
+            content: &segment.content,
+            diagnostics: &mut diagnostics,
+        };
+        let mut elements = Rc::new(RefCell::new(segment_elements.into_iter()));
+        text_end = Some(SourceRange {
+            segment_id: segment.id.clone(),
+            start: segment.content.len(),
+            end: segment.content.len(),
+        });
+
+        loop {
+            let elements_remaining = elements.borrow_mut().len();
+            if elements_remaining == 0 {
+                if !stack.is_empty() {
+                    let (next_elements, next_style, is_span) = stack.pop().unwrap();
+                    elements = next_elements;
+                    current_style = next_style;
+
+                    if is_span && !current_runs.is_empty() {
+                        let runs = std::mem::take(&mut current_runs);
+                        let span = TextSpan {
+                            runs,
+                            span_id: Some(SpanId::Lite(0)),
+                        };
+                        spans.push(span);
+                    }
 
-                continue;
-            } else {
-                break;
+                    continue;
+                } else {
+                    break;
+                }
             }
-        }
 
-        let element = elements.borrow_mut().next().unwrap();
+            let element = elements.borrow_mut().next().unwrap();
+
+            match element {
+                Element::Text {
+                    start,
+                    end,
+                    content,
+                    segment_id,
+                } => {
+                    let mut source_map = vec![];
+                    push_source_map(&mut source_map, &content, 0, start, end, config);
+
+                    current_runs.push(TextRun {
+                        text: content,
+                        style: current_style.clone(),
+                        source_range: SourceRange {
+                            segment_id,
+                            start,
+                            end,
+                        },
+                        ruby: None,
+                        source_map,
+                        reveal_delay: std::mem::take(&mut reveal_delay),
+                        marker: None,
+                    });
+                }
+                Element::Void {
+                    start,
+                    end,
+                    tag,
+                    value,
+                    attributes,
+                    segment_id,
+                } if tag.as_str() == "var" => {
+                    let source_range = SourceRange {
+                        segment_id,
+                        start,
+                        end,
+                    };
+                    let name = value.unwrap_or_default();
+                    let Some(text) = variables.and_then(|variables| variables.resolve(&name))
+                    else {
+                        reporter.warn(
+                            DiagnosticCode::UnknownVariable,
+                            &source_range,
+                            format!("unknown variable `{}`, ignored", name),
+                        );
+                        continue;
+                    };
 
-        match element {
-            Element::Text {
-                start,
-                end,
-                content,
-                segment_id,
-            } => {
-                current_runs.push(TextRun {
-                    text: content,
-                    style: current_style.clone(),
-                    source_range: SourceRange {
+                    // the attributes style the value only, such as `[var=player_name color=red]`
+                    let mut style = current_style.clone();
+                    let mut markers = vec![];
+                    apply_attributes(
+                        registry,
+                        &attributes,
+                        &mut TagContext {
+                            style: &mut style,
+                            reveal_delay: &mut reveal_delay,
+                            markers: &mut markers,
+                        },
+                        &source_range,
+                        &mut reporter,
+                    );
+                    push_markers(
+                        &mut current_runs,
+                        markers,
+                        &style,
+                        &source_range,
+                        &mut reveal_delay,
+                    );
+
+                    // the value is not in the source, so its first character maps to the whole
+                    // tag and the rest to the end of the tag
+                    let Some(first) = text.chars().next() else {
+                        continue;
+                    };
+                    current_runs.push(TextRun {
+                        source_map: vec![(first.len_utf8(), end)],
+                        text,
+                        style,
+                        source_range,
+                        ruby: None,
+                        reveal_delay: std::mem::take(&mut reveal_delay),
+                        marker: None,
+                    });
+                }
+                Element::Void {
+                    start,
+                    end,
+                    tag,
+                    value,
+                    attributes,
+                    segment_id,
+                } => {
+                    let source_range = SourceRange {
                         segment_id,
                         start,
                         end,
-                    },
-                });
-            }
-            Element::Block {
-                start: _,
-                end: _,
-                inner,
-                tag,
-                value,
-            } => {
-                if tag.as_str() != "span" && value.is_some() {
-                    stack.push((elements.clone(), current_style.clone(), false));
-                    elements = Rc::new(RefCell::new(inner.into_iter()));
-
-                    let value = value.as_ref().unwrap();
-                    match tag.as_str() {
-                        "size" => {
-                            current_style.font_size = parse_str(value, &current_style.font_size);
-                        }
-                        "color" | "fillColor" => {
-                            current_style.fill_color = parse_str(value, &current_style.fill_color);
-                        }
-                        "lineHeight" => {
-                            current_style.line_height =
-                                parse_str(value, &current_style.line_height);
-                        }
-                        "indent" => {
-                            current_style.indent = parse_str(value, &current_style.indent);
-                        }
-                        "stroke" => {
-                            current_style.stroke =
-                                parse_str_optional(value, current_style.stroke.as_ref());
-                        }
-                        "strokeColor" => {
-                            if current_style.stroke.is_none() {
-                                current_style.stroke = Some(StrokeStyle::default());
-                            }
-                            let stroke = current_style.stroke.as_mut().unwrap();
-                            stroke.stroke_color = parse_str(value, &stroke.stroke_color);
-                        }
-                        "strokeWidth" => {
-                            if current_style.stroke.is_none() {
-                                current_style.stroke = Some(StrokeStyle::default());
-                            }
-                            let stroke = current_style.stroke.as_mut().unwrap();
-                            stroke.stroke_width = parse_str(value, &stroke.stroke_width);
-                        }
-                        "shadow" => {
-                            current_style.shadow =
-                                parse_str_optional(value, current_style.shadow.as_ref());
-                        }
-                        "shadowOffsetX" => {
-                            if current_style.shadow.is_none() {
-                                current_style.shadow = Some(ShadowStyle::default());
-                            }
-                            let shadow = current_style.shadow.as_mut().unwrap();
-                            shadow.shadow_offset_x = parse_str(value, &shadow.shadow_offset_x);
-                        }
-                        "shadowOffsetY" => {
-                            if current_style.shadow.is_none() {
-                                current_style.shadow = Some(ShadowStyle::default());
-                            }
-                            let shadow = current_style.shadow.as_mut().unwrap();
-                            shadow.shadow_offset_y = parse_str(value, &shadow.shadow_offset_y);
-                        }
-                        "shadowWidth" => {
-                            if current_style.shadow.is_none() {
-                                current_style.shadow = Some(ShadowStyle::default());
-                            }
-                            let shadow = current_style.shadow.as_mut().unwrap();
-                            shadow.shadow_width = parse_str(value, &shadow.shadow_width);
-                        }
-                        "shadowBlur" => {
-                            if current_style.shadow.is_none() {
-                                current_style.shadow = Some(ShadowStyle::default());
+                    };
+                    // a void tag has no text to style, so changes to the style are discarded
+                    let mut style = current_style.clone();
+                    let mut markers = vec![];
+                    let mut context = TagContext {
+                        style: &mut style,
+                        reveal_delay: &mut reveal_delay,
+                        markers: &mut markers,
+                    };
+
+                    match apply_tag(registry, &tag, value.as_deref(), &mut context) {
+                        Some(result) => {
+                            if let Err(reason) = result {
+                                reporter.invalid_value(&source_range, &tag, &reason);
                             }
-                            let shadow = current_style.shadow.as_mut().unwrap();
-                            shadow.shadow_blur = parse_str(value, &shadow.shadow_blur);
+                            apply_attributes(
+                                registry,
+                                &attributes,
+                                &mut context,
+                                &source_range,
+                                &mut reporter,
+                            );
                         }
-                        "shadowColor" => {
-                            if current_style.shadow.is_none() {
-                                current_style.shadow = Some(ShadowStyle::default());
-                            }
-                            let shadow = current_style.shadow.as_mut().unwrap();
-                            shadow.shadow_color = parse_str(value, &shadow.shadow_color);
+                        None if tag.is_empty() => {
+                            reporter.warn(
+                                DiagnosticCode::IgnoredTag,
+                                &source_range,
+                                "void tag without name, ignored".to_string(),
+                            );
                         }
-                        _ => {
-                            log::warn!("unrecognized style tag `{}`, ignored.", tag);
+                        None => {
+                            markers.push(InlineMarker {
+                                tag,
+                                value,
+                                attributes,
+                            });
                         }
+                    }
+
+                    push_markers(
+                        &mut current_runs,
+                        markers,
+                        &current_style,
+                        &source_range,
+                        &mut reveal_delay,
+                    );
+                }
+                Element::Block {
+                    start,
+                    end,
+                    inner,
+                    tag,
+                    value,
+                    attributes,
+                    segment_id,
+                } if tag.as_str() == "ruby" => {
+                    let tag_range = SourceRange {
+                        segment_id,
+                        start,
+                        end,
                     };
-                } else {
-                    if let Some(style_prefabs) = style_prefabs {
-                        if let Some(style_prefab) = style_prefabs.get(&tag) {
-                            stack.push((elements.clone(), current_style.clone(), false));
-                            elements = Rc::new(RefCell::new(inner.into_iter()));
 
-                            current_style = style_prefab.clone();
-                            continue;
-                        }
+                    // the base text of a ruby is laid out as a whole, so inner tags are flattened
+                    let mut texts = vec![];
+                    if flatten_texts(inner, &mut texts) {
+                        reporter.warn(
+                            DiagnosticCode::IgnoredTag,
+                            &tag_range,
+                            "tags inside `ruby` tag are ignored".to_string(),
+                        );
                     }
 
-                    if tag.as_str() != "span" && !tag.is_empty() {
-                        log::warn!("unrecognized prefab tag `{}`, treated as normal span", tag);
+                    let (Some(first), Some(last)) = (texts.first(), texts.last()) else {
+                        continue;
+                    };
+                    let source_range = SourceRange {
+                        segment_id: first.1.clone(),
+                        start: first.2,
+                        end: last.3,
+                    };
+                    let mut text = String::new();
+                    let mut source_map = vec![];
+                    for (content, _, start, end) in texts.iter() {
+                        push_source_map(&mut source_map, content, text.len(), *start, *end, config);
+                        text.push_str(content);
                     }
 
-                    if !current_runs.is_empty() {
-                        let runs = current_runs.drain(..).collect();
-                        let span = TextSpan {
-                            runs,
-                            span_id: Some(SpanId::Lite(0)),
+                    let ruby = value.as_deref().map(|value| {
+                        let Ok(ruby) = value.parse::<Ruby>();
+                        ruby
+                    });
+                    if ruby.is_none() {
+                        reporter.warn(
+                            DiagnosticCode::InvalidValue,
+                            &tag_range,
+                            "`ruby` tag without annotation, treated as normal text".to_string(),
+                        );
+                    }
+
+                    let mut style = current_style.clone();
+                    let mut markers = vec![];
+                    apply_attributes(
+                        registry,
+                        &attributes,
+                        &mut TagContext {
+                            style: &mut style,
+                            reveal_delay: &mut reveal_delay,
+                            markers: &mut markers,
+                        },
+                        &tag_range,
+                        &mut reporter,
+                    );
+                    push_markers(
+                        &mut current_runs,
+                        markers,
+                        &style,
+                        &source_range,
+                        &mut reveal_delay,
+                    );
+
+                    current_runs.push(TextRun {
+                        text,
+                        style,
+                        source_range,
+                        ruby,
+                        source_map,
+                        reveal_delay: std::mem::take(&mut reveal_delay),
+                        marker: None,
+                    });
+                }
+                Element::Block {
+                    start,
+                    end,
+                    inner,
+                    tag,
+                    value,
+                    attributes,
+                    segment_id,
+                } => {
+                    let source_range = SourceRange {
+                        segment_id,
+                        start,
+                        end,
+                    };
+                    let mut markers = vec![];
+
+                    let style_prefabs = style_prefabs.filter(|style_prefabs| {
+                        value.is_none() && style_prefabs.contains_key(&tag)
+                    });
+                    // a bare tag is a prefab if there is one, even if a handler has the same name
+                    let is_bare_prefab = style_prefabs.is_some() && attributes.is_empty();
+                    // a bare tag whose handler requires a value, e.g. `[size]`, is a normal span
+                    let is_bare_span = value.is_none() && attributes.is_empty() && {
+                        let mut context = TagContext {
+                            style: &mut current_style.clone(),
+                            reveal_delay: &mut 0.,
+                            markers: &mut vec![],
                         };
-                        spans.push(span);
+                        matches!(apply_tag(registry, &tag, None, &mut context), Some(Err(_)))
+                    };
+
+                    if tag.as_str() != "span"
+                        && !is_bare_prefab
+                        && !is_bare_span
+                        && registry.get(&tag).is_some()
+                    {
+                        stack.push((elements.clone(), current_style.clone(), false));
+                        elements = Rc::new(RefCell::new(inner.into_iter()));
+
+                        let mut context = TagContext {
+                            style: &mut current_style,
+                            reveal_delay: &mut reveal_delay,
+                            markers: &mut markers,
+                        };
+                        if let Some(Err(reason)) =
+                            apply_tag(registry, &tag, value.as_deref(), &mut context)
+                        {
+                            reporter.invalid_value(&source_range, &tag, &reason);
+                        }
+                    } else if let Some(style_prefabs) = style_prefabs {
+                        let style_prefab = resolve_style_prefab(style_prefabs, &tag);
+                        let is_span =
+                            matches!(&style_prefab, Ok(style_prefab) if style_prefab.new_span);
+                        if is_span && !current_runs.is_empty() {
+                            let runs = std::mem::take(&mut current_runs);
+                            let span = TextSpan {
+                                runs,
+                                span_id: Some(SpanId::Lite(0)),
+                            };
+                            spans.push(span);
+                        }
+
+                        stack.push((elements.clone(), current_style.clone(), is_span));
+                        elements = Rc::new(RefCell::new(inner.into_iter()));
+
+                        // the prefab only replaces the fields it sets
+                        match style_prefab {
+                            Ok(style_prefab) => style_prefab.apply(&mut current_style),
+                            Err(error) => reporter.warn(
+                                DiagnosticCode::InvalidPrefab,
+                                &source_range,
+                                format!("{}, ignored", error),
+                            ),
+                        }
+                    } else if value.is_some() && tag.as_str() != "span" {
+                        reporter.warn(
+                            DiagnosticCode::UnknownTag,
+                            &source_range,
+                            format!("unrecognized style tag `{}`, ignored", tag),
+                        );
+
+                        stack.push((elements.clone(), current_style.clone(), false));
+                        elements = Rc::new(RefCell::new(inner.into_iter()));
+                    } else {
+                        if tag.as_str() != "span" && !tag.is_empty() && !is_bare_span {
+                            reporter.warn(
+                                DiagnosticCode::UnknownTag,
+                                &source_range,
+                                format!(
+                                    "unrecognized prefab tag `{}`, treated as normal span",
+                                    tag
+                                ),
+                            );
+                        }
+
+                        if !current_runs.is_empty() {
+                            let runs = std::mem::take(&mut current_runs);
+                            let span = TextSpan {
+                                runs,
+                                span_id: Some(SpanId::Lite(0)),
+                            };
+                            spans.push(span);
+                        }
+
+                        stack.push((elements.clone(), current_style.clone(), true));
+                        elements = Rc::new(RefCell::new(inner.into_iter()));
                     }
 
-                    stack.push((elements.clone(), current_style.clone(), true));
-                    elements = Rc::new(RefCell::new(inner.into_iter()));
+                    // attributes are applied over the style set by the tag itself
+                    apply_attributes(
+                        registry,
+                        &attributes,
+                        &mut TagContext {
+                            style: &mut current_style,
+                            reveal_delay: &mut reveal_delay,
+                            markers: &mut markers,
+                        },
+                        &source_range,
+                        &mut reporter,
+                    );
+                    push_markers(
+                        &mut current_runs,
+                        markers,
+                        &current_style,
+                        &source_range,
+                        &mut reveal_delay,
+                    );
                 }
             }
         }
     }
 
+    // a pause at the end of the text follows the last run in an empty run,
+    // so that it counts in the duration of the reveal
+    if let Some(source_range) = text_end.filter(|_| reveal_delay > 0.) {
+        let run = TextRun {
+            text: String::new(),
+            style: current_style.clone(),
+            source_range,
+            ruby: None,
+            source_map: vec![],
+            reveal_delay,
+            marker: None,
+        };
+        match spans.last_mut() {
+            Some(span) if current_runs.is_empty() => span.runs.push(run),
+            _ => current_runs.push(run),
+        }
+    }
+
     if !current_runs.is_empty() {
-        let runs = current_runs.drain(..).collect();
+        let runs = std::mem::take(&mut current_runs);
         let span = TextSpan {
             runs,
             span_id: Some(SpanId::Lite(0)),
@@ -187,36 +471,170 @@ pub(crate) fn to_spans(
         spans.push(span);
     }
 
-    Ok(spans)
+    (spans, diagnostics)
+}
+
+/// Collects the diagnostics in the content of a segment.
+struct Reporter<'a> {
+    content: &'a str,
+    diagnostics: &'a mut Vec<Diagnostic>,
 }
 
-fn parse_str<T: FromStr + Clone>(str: &str, fallback: &T) -> T {
-    str.parse::<T>().unwrap_or_else(|_| {
-        log::warn!(
-            "cannot parse string value `{}` to type `{}`.",
-            str,
-            std::any::type_name::<T>()
+impl Reporter<'_> {
+    fn warn(&mut self, code: DiagnosticCode, source_range: &SourceRange, message: String) {
+        self.diagnostics.push(Diagnostic::new(
+            Severity::Warning,
+            code,
+            source_range.clone(),
+            self.content,
+            message,
+        ));
+    }
+
+    fn invalid_value(&mut self, source_range: &SourceRange, tag: &str, reason: &str) {
+        self.warn(
+            DiagnosticCode::InvalidValue,
+            source_range,
+            format!("invalid value of tag `{}`: {}, ignored", tag, reason),
         );
-        (*fallback).clone()
-    })
+    }
 }
 
-fn parse_str_optional<T: FromStr + Clone>(str: &str, fallback: Option<&T>) -> Option<T> {
-    str.parse::<T>()
-        .and_then(|v| Ok(Some(v)))
-        .unwrap_or_else(|_| {
-            log::warn!(
-                "cannot parse string value `{}` to type `{}`.",
-                str,
-                std::any::type_name::<T>()
-            );
-            fallback.cloned()
-        })
+/// Apply a tag by its handler in the registry, returning the result of the handler,
+/// or `None` if the tag has no handler.
+/// A rejected value is ignored, leaving the context unchanged.
+fn apply_tag(
+    registry: &TagRegistry,
+    tag: &str,
+    value: Option<&str>,
+    context: &mut TagContext,
+) -> Option<Result<(), String>> {
+    let handler = registry.get(tag)?;
+
+    let mut next_style = context.style.clone();
+    let mut next_reveal_delay = *context.reveal_delay;
+    let mut next_markers = vec![];
+    let result = handler.apply(
+        &mut TagContext {
+            style: &mut next_style,
+            reveal_delay: &mut next_reveal_delay,
+            markers: &mut next_markers,
+        },
+        value,
+    );
+    if result.is_ok() {
+        *context.style = next_style;
+        *context.reveal_delay = next_reveal_delay;
+        context.markers.append(&mut next_markers);
+    }
+
+    Some(result)
+}
+
+/// Apply the attributes of a tag by the handlers of the tags with the same names.
+fn apply_attributes(
+    registry: &TagRegistry,
+    attributes: &[(String, String)],
+    context: &mut TagContext,
+    source_range: &SourceRange,
+    reporter: &mut Reporter,
+) {
+    for (key, value) in attributes {
+        match apply_tag(registry, key, Some(value), context) {
+            Some(Ok(())) => {}
+            Some(Err(reason)) => reporter.invalid_value(source_range, key, &reason),
+            None => reporter.warn(
+                DiagnosticCode::UnknownAttribute,
+                source_range,
+                format!("unrecognized attribute `{}`, ignored", key),
+            ),
+        }
+    }
+}
+
+/// Add the markers at a tag to the runs, each as a run with empty text.
+fn push_markers(
+    runs: &mut Vec<TextRun>,
+    markers: Vec<InlineMarker>,
+    style: &TextStyle,
+    source_range: &SourceRange,
+    reveal_delay: &mut f64,
+) {
+    for marker in markers {
+        runs.push(TextRun {
+            text: String::new(),
+            style: style.clone(),
+            source_range: source_range.clone(),
+            ruby: None,
+            source_map: vec![],
+            reveal_delay: std::mem::take(reveal_delay),
+            marker: Some(marker),
+        });
+    }
+}
+
+/// Collect the texts of the elements with their segment ids and source positions,
+/// returning whether any tag is met.
+fn flatten_texts(
+    elements: Vec<Element>,
+    texts: &mut Vec<(String, Option<SegmentId>, usize, usize)>,
+) -> bool {
+    let mut has_tags = false;
+    for element in elements {
+        match element {
+            Element::Text {
+                start,
+                end,
+                content,
+                segment_id,
+            } => texts.push((content, segment_id, start, end)),
+            Element::Block { inner, .. } => {
+                has_tags = true;
+                flatten_texts(inner, texts);
+            }
+            Element::Void { .. } => {
+                has_tags = true;
+            }
+        }
+    }
+
+    has_tags
+}
+
+/// Add the anchors of `TextRun::source_map` for an unescaped text from `source_start` to
+/// `source_end`, which is joined to the text of a run at `text_start`.
+fn push_source_map(
+    source_map: &mut Vec<(usize, usize)>,
+    text: &str,
+    text_start: usize,
+    source_start: usize,
+    source_end: usize,
+    config: &ParserConfig,
+) {
+    if text_start != 0 {
+        source_map.push((text_start, source_start));
+    }
+
+    // a text as long as its source has no escapes, even if it has tag characters,
+    // e.g. a stray end tag treated as text by lenient parsing
+    if source_end - source_start == text.len() {
+        return;
+    }
+
+    // a tag character in the text is escaped in the source
+    let mut escaped = 0;
+    for (i, ch) in text.char_indices() {
+        if let Some(escape_len) = config.escape_len(ch) {
+            let next = i + ch.len_utf8();
+            escaped += escape_len;
+            source_map.push((text_start + next, source_start + next + escaped));
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::elements_to_spans::to_spans;
+    use super::elements_to_spans::{to_spans, to_spans_with_config};
     use crate::parser::*;
 
     /// Helper function to create a default TextStyle
@@ -331,6 +749,151 @@ mod tests {
         assert_eq!(result[2].runs[0].text, " after");
     }
 
+    #[test]
+    fn test_style_prefabs() {
+        let red = "#f00".parse::<csscolorparser::Color>().unwrap();
+        let style_prefabs = std::collections::HashMap::from([
+            (
+                "name".to_string(),
+                StylePrefab {
+                    fill_color: Some(red.clone()),
+                    ..Default::default()
+                },
+            ),
+            (
+                "title".to_string(),
+                StylePrefab {
+                    extends: Some("name".to_string()),
+                    indent: Some(2.),
+                    new_span: true,
+                    ..Default::default()
+                },
+            ),
+            (
+                "loop".to_string(),
+                StylePrefab {
+                    extends: Some("loop".to_string()),
+                    ..Default::default()
+                },
+            ),
+        ]);
+
+        let segment =
+            Segment::dummy("[size=40]a[name]b[/name][title]c[/title][loop]d[/loop][/size]");
+        let elements = parse(&segment).expect("Failed to parse");
+        let (result, diagnostics) = to_spans_with_config(
+            vec![(&segment, elements)],
+            &default_style(),
+            Some(&style_prefabs),
+            &ParserConfig::default(),
+            &TagRegistry::default(),
+            None,
+        );
+
+        // only the prefab asking for it starts a new span
+        let texts = result
+            .iter()
+            .map(|span| {
+                span.runs
+                    .iter()
+                    .map(|run| run.text.as_str())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(texts, [vec!["a", "b"], vec!["c"], vec!["d"]]);
+
+        // the prefabs keep the fields they do not set
+        let name = &result[0].runs[1].style;
+        assert_eq!(
+            (name.font_size, name.fill_color.clone()),
+            (40., red.clone())
+        );
+        let title = &result[1].runs[0].style;
+        assert_eq!((title.font_size, title.indent), (40., 2.));
+        assert_eq!(title.fill_color, red);
+        assert_eq!(
+            result[2].runs[0].style.fill_color,
+            default_style().fill_color
+        );
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, DiagnosticCode::InvalidPrefab);
+        assert_eq!(
+            diagnostics[0].message,
+            "style prefabs extend in a cycle: `loop` -> `loop`, ignored"
+        );
+    }
+
+    #[test]
+    fn test_style_prefabs_named_as_builtin_tags() {
+        let red = "#f00".parse::<csscolorparser::Color>().unwrap();
+        let style_prefabs = std::collections::HashMap::from([(
+            "size".to_string(),
+            StylePrefab {
+                font_size: Some(50.),
+                fill_color: Some(red.clone()),
+                ..Default::default()
+            },
+        )]);
+
+        let segment = Segment::dummy("[size]a[/size][size=40]b[/size][size color=#00f]c[/size]");
+        let elements = parse(&segment).expect("Failed to parse");
+        let (result, diagnostics) = to_spans_with_config(
+            vec![(&segment, elements)],
+            &default_style(),
+            Some(&style_prefabs),
+            &ParserConfig::default(),
+            &TagRegistry::default(),
+            None,
+        );
+
+        let runs = result
+            .iter()
+            .flat_map(|span| span.runs.iter())
+            .collect::<Vec<_>>();
+        assert_eq!(runs.len(), 3);
+
+        // a bare tag is the prefab, which is not shadowed by the built-in tag
+        assert_eq!(
+            (runs[0].style.font_size, runs[0].style.fill_color.clone()),
+            (50., red)
+        );
+        // a tag with a value is the built-in tag
+        assert_eq!(runs[1].style.font_size, 40.);
+        assert_eq!(runs[1].style.fill_color, default_style().fill_color);
+        // so is a tag with attributes, which rejects the missing value
+        assert_eq!(runs[2].style.font_size, default_style().font_size);
+        assert_eq!(runs[2].style.fill_color, "#00f".parse().unwrap());
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, DiagnosticCode::InvalidValue);
+    }
+
+    #[test]
+    fn test_bare_builtin_tags_without_value() {
+        let segment = Segment::dummy("a[size]b[/size][instant]c[/instant]");
+        let elements = parse(&segment).expect("Failed to parse");
+        let (result, diagnostics) = to_spans_with_config(
+            vec![(&segment, elements)],
+            &default_style(),
+            None,
+            &ParserConfig::default(),
+            &TagRegistry::default(),
+            None,
+        );
+
+        // a tag which requires a value is a normal span without it, as a bare unknown tag
+        let texts = result
+            .iter()
+            .map(|span| span.runs.iter().map(|run| run.text.as_str()).collect())
+            .collect::<Vec<Vec<_>>>();
+        assert_eq!(texts, [vec!["a"], vec!["b"], vec!["c"]]);
+        assert_eq!(result[1].runs[0].style.font_size, default_style().font_size);
+        // while a tag which takes no value is applied
+        assert!(result[2].runs[0].style.reveal_instant);
+        assert!(diagnostics.is_empty());
+    }
+
     #[test]
     fn test_nested_spans() {
         let input = "[span]Outer [span]Middle [span]Inner[/span] middle[/span] outer[/span]";
@@ -419,8 +982,7 @@ mod tests {
 
     #[test]
     fn test_multiple_style_attributes() {
-        let input =
-        "[span][color=#ff0000][size=48][lineHeight=2.0]Styled[/lineHeight][/size][/color][/span]";
+        let input = "[span][color=#ff0000][size=48][lineHeight=2.0]Styled[/lineHeight][/size][/color][/span]";
         let elements = parse(&Segment::dummy(input)).expect("Failed to parse");
 
         let result =
@@ -437,6 +999,39 @@ mod tests {
         assert!(color.contains("ff0000") || color.contains("FF0000"));
     }
 
+    #[test]
+    fn test_tag_attributes() {
+        let input = concat!(
+            "[span]",
+            "[style color=#ff0000 size=40 stroke=\"#fff 2\"]styled[/style]",
+            "[color=#00ff00 size=24 color=#0000ff]last wins[/color]",
+            "plain",
+            "[/span]"
+        );
+        let elements = parse(&Segment::dummy(input)).expect("Failed to parse");
+
+        let result =
+            to_spans(elements, &default_style(), None).expect("Failed to parse text recursive");
+
+        let runs = &result[0].runs;
+        assert_eq!(runs.len(), 3);
+
+        assert_eq!(runs[0].text, "styled");
+        assert_eq!(runs[0].style.font_size, 40.0);
+        assert!(runs[0].style.fill_color.to_css_hex().contains("ff0000"));
+        let stroke = runs[0].style.stroke.as_ref().unwrap();
+        assert_eq!(stroke.stroke_width, 2.0);
+        assert!(stroke.stroke_color.to_css_hex().contains("ffffff"));
+
+        // attributes are applied in order, after the value of the tag itself
+        assert_eq!(runs[1].style.font_size, 24.0);
+        assert!(runs[1].style.fill_color.to_css_hex().contains("0000ff"));
+
+        assert_eq!(runs[2].text, "plain");
+        assert_eq!(runs[2].style.font_size, 32.0);
+        assert!(runs[2].style.stroke.is_none());
+    }
+
     #[test]
     fn test_shadow_style_attributes() {
         // Test individual shadow attributes
@@ -467,8 +1062,7 @@ mod tests {
 
     #[test]
     fn test_stroke_attributes() {
-        let input =
-        "[span][strokeColor=#0000ff][strokeWidth=2.5]Stroked[/strokeWidth][/strokeColor][/span]";
+        let input = "[span][strokeColor=#0000ff][strokeWidth=2.5]Stroked[/strokeWidth][/strokeColor][/span]";
         let elements = parse(&Segment::dummy(input)).expect("Failed to parse");
 
         let result =
@@ -521,6 +1115,221 @@ mod tests {
         assert_eq!(sr.end, 37);
     }
 
+    #[test]
+    fn test_ruby_tag() {
+        let input =
+            "[span]读[ruby=hàn|zì]汉[size=48]字[/size][/ruby][ruby=かんじ]漢字[/ruby][/span]";
+        let elements = parse(&Segment::dummy(input)).expect("Failed to parse");
+
+        let result =
+            to_spans(elements, &default_style(), None).expect("Failed to parse text recursive");
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].runs.len(), 3);
+        assert_eq!(result[0].runs[0].ruby, None);
+
+        // inner tags are flattened into the base text
+        let mono = &result[0].runs[1];
+        assert_eq!(mono.text, "汉字");
+        assert_eq!(mono.style.font_size, 32.0);
+        assert_eq!(mono.source_range.start, 24);
+        assert_eq!(mono.source_range.end, 39);
+        // the joined texts keep their own source offsets
+        assert_eq!(mono.source_offset(0), 24);
+        assert_eq!(mono.source_offset(3), 36);
+        assert_eq!(mono.source_offset(6), 39);
+        let ruby = mono.ruby.as_ref().expect("Should have ruby");
+        assert_eq!(ruby.annotations, ["hàn", "zì"]);
+        assert_eq!(ruby.mode, RubyMode::Mono);
+
+        let group = &result[0].runs[2];
+        assert_eq!(group.text, "漢字");
+        let ruby = group.ruby.as_ref().expect("Should have ruby");
+        assert_eq!(ruby.annotations, ["かんじ"]);
+        assert_eq!(ruby.mode, RubyMode::Group);
+    }
+
+    #[test]
+    fn test_escaped_source_offsets() {
+        let input = "[span]a[[b]]c[/span]";
+        let elements = parse(&Segment::dummy(input)).expect("Failed to parse");
+
+        let result =
+            to_spans(elements, &default_style(), None).expect("Failed to parse text recursive");
+
+        let run = &result[0].runs[0];
+        assert_eq!(run.text, "a[b]c");
+        assert_eq!(run.source_map, [(2, 9), (4, 12)]);
+        // the escaped characters span both tag characters in the source
+        let offsets = (0..=run.text.len())
+            .map(|offset| run.source_offset(offset))
+            .collect::<Vec<_>>();
+        assert_eq!(offsets, [6, 7, 9, 10, 12, 13]);
+    }
+
+    #[test]
+    fn test_escape_character_source_offsets() {
+        let config = ParserConfig {
+            open: '【',
+            close: '】',
+            escape: Some('\\'),
+            ..Default::default()
+        };
+        let segment = Segment::dummy(r"【span】a\【b\】c【/span】");
+        let elements = parse_with_config(&segment, &config).expect("Failed to parse");
+
+        let (result, _) = to_spans_with_config(
+            vec![(&segment, elements)],
+            &default_style(),
+            None,
+            &config,
+            &TagRegistry::default(),
+            None,
+        );
+
+        let run = &result[0].runs[0];
+        assert_eq!(run.text, "a【b】c");
+        // the escape character is one byte before each three-byte tag character
+        assert_eq!(run.source_map, [(4, 15), (8, 20)]);
+        assert_eq!(run.source_offset(9), 21);
+    }
+
+    #[test]
+    fn test_custom_tag_handlers() {
+        let mut registry = TagRegistry::default();
+        registry.register(
+            "emotion",
+            |context: &mut TagContext, value: Option<&str>| match value {
+                Some("angry") => {
+                    context.style.font_size = 40.;
+                    context.emit_marker(InlineMarker {
+                        tag: "shake".to_string(),
+                        ..Default::default()
+                    });
+                    Ok(())
+                }
+                _ => Err("unknown emotion".to_string()),
+            },
+        );
+        registry.register("name", |context: &mut TagContext, _: Option<&str>| {
+            context.style.indent = 1.;
+            Ok(())
+        });
+        // the built-in tags can be replaced
+        registry.register("size", |_: &mut TagContext, _: Option<&str>| {
+            Err("fixed size".to_string())
+        });
+
+        let input = concat!(
+            "[span]",
+            "[emotion=angry]a[/emotion]",
+            "[emotion=sad]b[/emotion]",
+            "[name]c[/name]",
+            "[size=48]d[/size]",
+            "[/span]"
+        );
+        let segment = Segment::dummy(input);
+        let elements = parse(&segment).expect("Failed to parse");
+        let (result, _) = to_spans_with_config(
+            vec![(&segment, elements)],
+            &default_style(),
+            None,
+            &ParserConfig::default(),
+            &registry,
+            None,
+        );
+
+        let runs = &result[0].runs;
+        let texts = runs.iter().map(|run| run.text.as_str()).collect::<Vec<_>>();
+        assert_eq!(texts, ["", "a", "b", "c", "d"]);
+
+        // the marker is emitted at the tag, with the style of the tag
+        assert_eq!(
+            runs[0].marker.as_ref().map(|marker| marker.tag.as_str()),
+            Some("shake")
+        );
+        assert_eq!(runs[0].style.font_size, 40.);
+        assert_eq!(
+            (runs[0].source_range.start, runs[0].source_range.end),
+            (6, 32)
+        );
+        assert_eq!(runs[1].style.font_size, 40.);
+
+        // rejected values leave the style unchanged
+        assert_eq!(runs[2].style.font_size, 32.);
+        assert_eq!(runs[3].style.indent, 1.);
+        assert_eq!(runs[4].style.font_size, 32.);
+    }
+
+    #[test]
+    fn test_reveal_timing_tags() {
+        let input = "[span]a[wait=0.5]b[speed=2]c[/speed][instant]de[/instant][wait=1][/span]";
+        let elements = parse(&Segment::dummy(input)).expect("Failed to parse");
+
+        let result =
+            to_spans(elements, &default_style(), None).expect("Failed to parse text recursive");
+
+        let runs = &result[0].runs;
+        let timings = runs
+            .iter()
+            .map(|run| {
+                (
+                    run.text.as_str(),
+                    run.reveal_delay,
+                    run.style.reveal_speed,
+                    run.style.reveal_instant,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            timings,
+            [
+                ("a", 0., 1., false),
+                ("b", 0.5, 1., false),
+                ("c", 0., 2., false),
+                ("de", 0., 1., true),
+                // the pause at the end is kept in an empty run
+                ("", 1., 1., false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_void_tags_become_markers() {
+        let input = "[span]a[wait=0.5][voice=line_012/][size=48]b[icon=coin/][/size][/span]";
+        let elements = parse(&Segment::dummy(input)).expect("Failed to parse");
+
+        let result =
+            to_spans(elements, &default_style(), None).expect("Failed to parse text recursive");
+
+        let runs = &result[0].runs;
+        assert_eq!(runs.len(), 4);
+        assert_eq!(runs[1].text, "");
+        assert_eq!(
+            runs[1].marker,
+            Some(InlineMarker {
+                tag: "voice".to_string(),
+                value: Some("line_012".to_string()),
+                attributes: vec![],
+            })
+        );
+        // the marker takes the pause before it
+        assert_eq!(runs[1].reveal_delay, 0.5);
+        assert_eq!(runs[2].reveal_delay, 0.);
+        assert_eq!(
+            (runs[1].source_range.start, runs[1].source_range.end),
+            (17, 34)
+        );
+
+        assert_eq!(runs[2].text, "b");
+        assert_eq!(runs[2].marker, None);
+        assert_eq!(
+            runs[3].marker.as_ref().map(|marker| marker.tag.as_str()),
+            Some("icon")
+        );
+        assert_eq!(runs[3].style.font_size, 48.);
+    }
+
     #[test]
     fn test_indent_attribute() {
         let input = "[span][indent=2.5]Indented text[/indent][/span]";
@@ -533,4 +1342,170 @@ mod tests {
         assert_eq!(result[0].runs[0].text, "Indented text");
         assert_eq!(result[0].runs[0].style.indent, 2.5);
     }
+
+    #[test]
+    fn test_font_family_attribute() {
+        let input =
+            "[span]A[font=serif]B[fontFamily=\"Source Han Sans\"]C[/fontFamily][/font][/span]";
+        let elements = parse(&Segment::dummy(input)).expect("Failed to parse");
+
+        let result =
+            to_spans(elements, &default_style(), None).expect("Failed to parse text recursive");
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].runs[0].style.font_family, None);
+        assert_eq!(
+            result[0].runs[1].style.font_family.as_deref(),
+            Some("serif")
+        );
+        assert_eq!(
+            result[0].runs[2].style.font_family.as_deref(),
+            Some("Source Han Sans")
+        );
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let first = Segment {
+            id: Some(SegmentId::Lite(1)),
+            content: "[size=abc]a[/size]".into(),
+        };
+        let second = Segment {
+            id: Some(SegmentId::Lite(2)),
+            content: "b\n[color=red blink=1]c[/color][unknown=1]d[/unknown]".into(),
+        };
+        let parsed_segments = vec![
+            (&first, parse(&first).expect("Failed to parse")),
+            (&second, parse(&second).expect("Failed to parse")),
+        ];
+
+        let (result, diagnostics) = to_spans_with_config(
+            parsed_segments,
+            &default_style(),
+            None,
+            &ParserConfig::default(),
+            &TagRegistry::default(),
+            None,
+        );
+
+        // the runs of both segments are still converted
+        let texts = result[0]
+            .runs
+            .iter()
+            .map(|run| run.text.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(texts, ["a", "b\n", "c", "d"]);
+        assert_eq!(result[0].runs[0].style.font_size, default_style().font_size);
+
+        let summary = diagnostics
+            .iter()
+            .map(|diagnostic| {
+                (
+                    diagnostic.severity,
+                    diagnostic.code,
+                    diagnostic.source_range.segment_id.clone(),
+                    diagnostic.line,
+                    diagnostic.column,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                (
+                    Severity::Warning,
+                    DiagnosticCode::InvalidValue,
+                    Some(SegmentId::Lite(1)),
+                    1,
+                    1
+                ),
+                (
+                    Severity::Warning,
+                    DiagnosticCode::UnknownAttribute,
+                    Some(SegmentId::Lite(2)),
+                    2,
+                    1
+                ),
+                (
+                    Severity::Warning,
+                    DiagnosticCode::UnknownTag,
+                    Some(SegmentId::Lite(2)),
+                    2,
+                    29
+                ),
+            ]
+        );
+        assert_eq!(diagnostics[0].source_range.end, 18);
+        assert_eq!(
+            diagnostics[0].message,
+            "invalid value of tag `size`: cannot parse string value `abc` to type `f64`, ignored"
+        );
+    }
+
+    #[test]
+    fn test_variables() {
+        let variables =
+            std::collections::HashMap::from([("player_name".to_string(), "[b]艾".to_string())]);
+        let segment = Segment::dummy(
+            "[color=#f00]Hi, [var=player_name]![var=missing][var=player_name size=40][/color]",
+        );
+        let elements = parse(&segment).expect("Failed to parse");
+        let (result, diagnostics) = to_spans_with_config(
+            vec![(&segment, elements)],
+            &default_style(),
+            None,
+            &ParserConfig::default(),
+            &TagRegistry::default(),
+            Some(&variables),
+        );
+
+        // the values are literal text, with the style around the tag
+        let runs = &result[0].runs;
+        let texts = runs.iter().map(|run| run.text.as_str()).collect::<Vec<_>>();
+        assert_eq!(texts, ["Hi, ", "[b]艾", "!", "[b]艾"]);
+        assert_eq!(runs[1].style.fill_color, runs[0].style.fill_color);
+        assert_eq!(runs[1].style.font_size, default_style().font_size);
+        assert_eq!(runs[3].style.font_size, 40.);
+
+        // the value maps back to the tag
+        assert_eq!(
+            (runs[1].source_range.start, runs[1].source_range.end),
+            (16, 33)
+        );
+        assert_eq!(runs[1].source_offset(0), 16);
+        assert_eq!(runs[1].source_offset(1), 33);
+        assert_eq!(runs[1].source_offset(runs[1].text.len()), 33);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, DiagnosticCode::UnknownVariable);
+        assert_eq!(
+            (
+                diagnostics[0].source_range.start,
+                diagnostics[0].source_range.end
+            ),
+            (34, 47)
+        );
+    }
+
+    #[test]
+    fn test_lenient_text_source_offsets() {
+        let segment = Segment::dummy("[span]a[/b]]c[/span]");
+        let (elements, _) = parse_lenient(&segment);
+        let (result, _) = to_spans_with_config(
+            vec![(&segment, elements)],
+            &default_style(),
+            None,
+            &ParserConfig::default(),
+            &TagRegistry::default(),
+            None,
+        );
+
+        let runs = &result[0].runs;
+        let texts = runs.iter().map(|run| run.text.as_str()).collect::<Vec<_>>();
+        assert_eq!(texts, ["a", "[/b]", "]", "c"]);
+        // the tag characters of the text are not escaped in the source
+        assert!(runs[1].source_map.is_empty());
+        assert_eq!(runs[1].source_offset(4), 11);
+        assert_eq!(runs[2].source_offset(1), 12);
+    }
 }
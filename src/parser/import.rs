@@ -0,0 +1,600 @@
+mod html;
+mod renpy;
+mod text_mesh_pro;
+
+pub use html::*;
+pub use renpy::*;
+pub use text_mesh_pro::*;
+
+use crate::parser::{Diagnostic, DiagnosticCode, Element, Segment, Severity, SourceRange};
+
+/// Parse an interpolation at the start of a text, returning its length and its expression.
+type ParseInterpolation = fn(&str) -> Option<(usize, &str)>;
+
+/// The syntax of a rich-text dialect, for tokenizing its text into tags and texts.
+struct Dialect {
+    open: char,
+    close: char,
+    /// parse the head of a tag between `open` and `close`, such as `color=#f00`
+    parse_head: fn(&str) -> Option<Head>,
+    /// decode an escape at the start of a text, such as `{{` or `&amp;`,
+    /// returning its length in the source and the decoded text
+    unescape: fn(&str) -> Option<(usize, String)>,
+    /// whether a tag has no content and no end tag, such as `<br>`
+    is_void: fn(&str) -> bool,
+    /// whether an unclosed tag is a problem, rather than closed at the end of the text
+    reports_unclosed: bool,
+    /// a tag whose content is text without tags, such as `<noparse>`
+    verbatim_tag: Option<&'static str>,
+    /// whether `<!-- -->` comments are skipped
+    comments: bool,
+    /// whether values after `=` may be quoted, in which `close` does not end the tag,
+    /// such as `<span title="a>b">`
+    quoted_values: bool,
+    /// parse an interpolation at the start of a text, such as `[name]` of Ren'Py,
+    /// returning its length in the source and the expression in it
+    parse_interpolation: Option<ParseInterpolation>,
+}
+
+impl Dialect {
+    /// The length of the head of a tag, which ends at the first `close` outside quoted values.
+    /// An unclosed quote is not a quote, so the head ends at the first `close` then.
+    fn head_len(&self, head: &str) -> Option<usize> {
+        if self.quoted_values {
+            let mut quote = None;
+            let mut after_equals = false;
+            for (i, ch) in head.char_indices() {
+                match quote {
+                    Some(open_quote) if ch == open_quote => quote = None,
+                    Some(_) => {}
+                    None if ch == self.close => return Some(i),
+                    None if after_equals && (ch == '"' || ch == '\'') => {
+                        quote = Some(ch);
+                        after_equals = false;
+                    }
+                    None if ch == '=' => after_equals = true,
+                    None if ch.is_whitespace() => {}
+                    None => after_equals = false,
+                }
+            }
+        }
+        head.find(self.close)
+    }
+
+    fn interpolation<'a>(&self, text: &'a str) -> Option<(usize, &'a str)> {
+        self.parse_interpolation.and_then(|parse| parse(text))
+    }
+}
+
+/// The head of a tag in a dialect.
+struct Head {
+    name: String,
+    value: Option<String>,
+    attributes: Vec<(String, String)>,
+    /// whether the head ends with a slash, such as `<br/>`
+    self_closing: bool,
+}
+
+/// A tag of a dialect, which is not mapped to huozi tags yet.
+struct RawTag {
+    head: Head,
+    start: usize,
+    head_end: usize,
+    end: usize,
+    inner: Vec<RawNode>,
+}
+
+enum RawNode {
+    Text {
+        start: usize,
+        end: usize,
+        content: String,
+    },
+    Tag(RawTag),
+    /// an interpolation of the dialect, which is a huozi `var` tag if it is a variable name
+    Interpolation {
+        start: usize,
+        end: usize,
+        expression: String,
+    },
+}
+
+/// How a tag of a dialect is imported.
+enum Mapped {
+    /// a huozi tag with the font size in it, for relative sizes of the tags inside
+    Block {
+        tag: String,
+        value: Option<String>,
+        attributes: Vec<(String, String)>,
+        font_size: f64,
+    },
+    /// a void huozi tag, such as `wait`
+    Void {
+        tag: String,
+        value: Option<String>,
+        attributes: Vec<(String, String)>,
+    },
+    /// a text in place of the tag, such as a line break
+    Text(String),
+    /// a ruby, whose annotations are the texts of the tags named `annotation` in it
+    Ruby { annotation: &'static str },
+    /// the content of the tag without a tag, followed by a line break if `newline` is set
+    Unwrap { newline: bool },
+    /// nothing, e.g. a comment
+    Drop,
+}
+
+/// Collects the diagnostics of an import, located in the imported text.
+struct Importer<'a> {
+    segment: &'a Segment<'a>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Importer<'_> {
+    fn warn(&mut self, code: DiagnosticCode, start: usize, end: usize, message: String) {
+        let source_range = SourceRange {
+            segment_id: self.segment.id.clone(),
+            start,
+            end,
+        };
+        self.diagnostics.push(Diagnostic::new(
+            Severity::Warning,
+            code,
+            source_range,
+            &self.segment.content,
+            message,
+        ));
+    }
+
+    /// Report a construct of the dialect which has no equivalent in huozi.
+    fn unsupported(&mut self, tag: &RawTag, message: String) {
+        self.warn(
+            DiagnosticCode::Unsupported,
+            tag.start,
+            tag.head_end,
+            message,
+        );
+    }
+
+    /// A `var` tag of an interpolated variable, or the text of an interpolation which is not
+    /// a variable name, such as `[name!u]` or `[a + b]`, which is reported.
+    fn interpolation(&mut self, start: usize, end: usize, expression: String) -> Element {
+        let is_name = !expression.is_empty()
+            && expression
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == '.');
+        if !is_name {
+            self.warn(
+                DiagnosticCode::Unsupported,
+                start,
+                end,
+                format!(
+                    "interpolation `{}` is not a variable name, kept as text",
+                    &self.segment.content[start..end]
+                ),
+            );
+            return self.text(start, end, self.segment.content[start..end].to_string());
+        }
+
+        Element::Void {
+            start,
+            end,
+            tag: "var".to_string(),
+            value: Some(expression),
+            attributes: vec![],
+            segment_id: self.segment.id.clone(),
+        }
+    }
+
+    fn text(&self, start: usize, end: usize, content: String) -> Element {
+        Element::Text {
+            start,
+            end,
+            content,
+            segment_id: self.segment.id.clone(),
+        }
+    }
+}
+
+/// Tokenize the text of a dialect and build the tree of its tags. Mismatched end tags close
+/// the tags inside them, and end tags without tags are dropped.
+fn parse_raw(dialect: &Dialect, importer: &mut Importer) -> Vec<RawNode> {
+    let segment = importer.segment;
+    let content: &str = &segment.content;
+    let mut nodes = vec![];
+    let mut stack: Vec<RawTag> = vec![];
+    let mut position = 0;
+
+    fn push(nodes: &mut Vec<RawNode>, stack: &mut [RawTag], node: RawNode) {
+        match stack.last_mut() {
+            Some(tag) => tag.inner.push(node),
+            None => nodes.push(node),
+        }
+    }
+
+    while position < content.len() {
+        let rest = &content[position..];
+
+        if dialect.comments && rest.starts_with("<!--") {
+            position += rest.find("-->").map_or(rest.len(), |end| end + 3);
+            continue;
+        }
+
+        if let Some((len, decoded)) = (dialect.unescape)(rest) {
+            let text = RawNode::Text {
+                start: position,
+                end: position + len,
+                content: decoded,
+            };
+            push(&mut nodes, &mut stack, text);
+            position += len;
+            continue;
+        }
+
+        if let Some((len, expression)) = dialect.interpolation(rest) {
+            let node = RawNode::Interpolation {
+                start: position,
+                end: position + len,
+                expression: expression.to_string(),
+            };
+            push(&mut nodes, &mut stack, node);
+            position += len;
+            continue;
+        }
+
+        let tag = rest
+            .strip_prefix(dialect.open)
+            .and_then(|head| Some((head, dialect.head_len(head)?)));
+        let Some((head, head_len)) = tag else {
+            // a text until the next tag or escape, or a tag character which starts no tag
+            let len = rest
+                .char_indices()
+                .skip(1)
+                .find(|(i, ch)| {
+                    *ch == dialect.open
+                        || (dialect.unescape)(&rest[*i..]).is_some()
+                        || dialect.interpolation(&rest[*i..]).is_some()
+                })
+                .map_or(rest.len(), |(i, _)| i);
+            if rest.starts_with(dialect.open) {
+                importer.warn(
+                    DiagnosticCode::Syntax,
+                    position,
+                    position + dialect.open.len_utf8(),
+                    format!("`{}` starts no tag, treated as text", dialect.open),
+                );
+            }
+            let text = RawNode::Text {
+                start: position,
+                end: position + len,
+                content: rest[..len].to_string(),
+            };
+            push(&mut nodes, &mut stack, text);
+            position += len;
+            continue;
+        };
+        let head = &head[..head_len];
+        let start = position;
+        let head_end = start + dialect.open.len_utf8() + head_len + dialect.close.len_utf8();
+
+        if let Some(name) = head.strip_prefix('/') {
+            let name = name.trim().to_lowercase();
+            match stack.iter().rposition(|tag| tag.head.name == name) {
+                Some(index) => {
+                    // the tags opened after the matched tag are closed before its end tag
+                    while stack.len() > index + 1 {
+                        let mut tag = stack.pop().unwrap();
+                        if dialect.reports_unclosed {
+                            importer.warn(
+                                DiagnosticCode::UnclosedTag,
+                                tag.start,
+                                tag.head_end,
+                                format!(
+                                    "tag `{}` is closed by the end tag of its parent",
+                                    tag.head.name
+                                ),
+                            );
+                        }
+                        tag.end = start;
+                        push(&mut nodes, &mut stack, RawNode::Tag(tag));
+                    }
+                    let mut tag = stack.pop().unwrap();
+                    tag.end = head_end;
+                    push(&mut nodes, &mut stack, RawNode::Tag(tag));
+                }
+                None => importer.warn(
+                    DiagnosticCode::UnmatchedEndTag,
+                    start,
+                    head_end,
+                    format!("end tag of `{}` without tag, ignored", name),
+                ),
+            }
+        } else if let Some(head) = (dialect.parse_head)(head) {
+            let mut tag = RawTag {
+                start,
+                head_end,
+                end: head_end,
+                inner: vec![],
+                head,
+            };
+            if Some(tag.head.name.as_str()) == dialect.verbatim_tag {
+                let end_tag = format!("{}/{}{}", dialect.open, tag.head.name, dialect.close);
+                let text_len = content[head_end..].find(&end_tag);
+                let text_end = text_len.map_or(content.len(), |len| head_end + len);
+                tag.end = text_len.map_or(text_end, |_| text_end + end_tag.len());
+                tag.inner.push(RawNode::Text {
+                    start: head_end,
+                    end: text_end,
+                    content: content[head_end..text_end].to_string(),
+                });
+                position = tag.end;
+                push(&mut nodes, &mut stack, RawNode::Tag(tag));
+                continue;
+            } else if tag.head.self_closing || (dialect.is_void)(&tag.head.name) {
+                push(&mut nodes, &mut stack, RawNode::Tag(tag));
+            } else {
+                stack.push(tag);
+            }
+        } else {
+            importer.warn(
+                DiagnosticCode::Syntax,
+                start,
+                head_end,
+                format!(
+                    "invalid tag `{}`, treated as text",
+                    &content[start..head_end]
+                ),
+            );
+            let text = RawNode::Text {
+                start,
+                end: head_end,
+                content: content[start..head_end].to_string(),
+            };
+            push(&mut nodes, &mut stack, text);
+        }
+        position = head_end;
+    }
+
+    while let Some(mut tag) = stack.pop() {
+        if dialect.reports_unclosed {
+            importer.warn(
+                DiagnosticCode::UnclosedTag,
+                tag.start,
+                tag.head_end,
+                format!(
+                    "tag `{}` is not closed, closed at the end of the text",
+                    tag.head.name
+                ),
+            );
+        }
+        tag.end = content.len();
+        push(&mut nodes, &mut stack, RawNode::Tag(tag));
+    }
+
+    nodes
+}
+
+/// Map the tree of a dialect to huozi elements by the mapping of the dialect.
+fn to_elements(
+    nodes: Vec<RawNode>,
+    font_size: f64,
+    map: fn(&RawTag, f64, &mut Importer) -> Mapped,
+    importer: &mut Importer,
+) -> Vec<Element> {
+    let mut elements = vec![];
+    let count = nodes.len();
+
+    for (index, node) in nodes.into_iter().enumerate() {
+        let tag = match node {
+            RawNode::Text {
+                start,
+                end,
+                content,
+            } => {
+                elements.push(importer.text(start, end, content));
+                continue;
+            }
+            RawNode::Interpolation {
+                start,
+                end,
+                expression,
+            } => {
+                elements.push(importer.interpolation(start, end, expression));
+                continue;
+            }
+            RawNode::Tag(tag) => tag,
+        };
+
+        match map(&tag, font_size, importer) {
+            Mapped::Block {
+                tag: name,
+                value,
+                attributes,
+                font_size,
+            } => elements.push(Element::Block {
+                start: tag.start,
+                end: tag.end,
+                inner: to_elements(tag.inner, font_size, map, importer),
+                tag: name,
+                value,
+                attributes,
+                segment_id: importer.segment.id.clone(),
+            }),
+            Mapped::Void {
+                tag: name,
+                value,
+                attributes,
+            } => elements.push(Element::Void {
+                start: tag.start,
+                end: tag.head_end,
+                tag: name,
+                value,
+                attributes,
+                segment_id: importer.segment.id.clone(),
+            }),
+            Mapped::Text(text) => elements.push(importer.text(tag.start, tag.end, text)),
+            Mapped::Ruby { annotation } => {
+                let mut annotations = vec![];
+                let mut base = vec![];
+                for node in tag.inner {
+                    match node {
+                        RawNode::Tag(inner) if inner.head.name == annotation => {
+                            annotations.push(raw_text(&inner.inner));
+                        }
+                        node => base.push(node),
+                    }
+                }
+                elements.push(Element::Block {
+                    start: tag.start,
+                    end: tag.end,
+                    inner: to_elements(base, font_size, map, importer),
+                    tag: "ruby".to_string(),
+                    value: Some(annotations.join("|")).filter(|value| !value.is_empty()),
+                    attributes: vec![],
+                    segment_id: importer.segment.id.clone(),
+                });
+            }
+            Mapped::Unwrap { newline } => {
+                let end = tag.end;
+                elements.extend(to_elements(tag.inner, font_size, map, importer));
+                if newline && index + 1 < count {
+                    elements.push(importer.text(end, end, "\n".to_string()));
+                }
+            }
+            Mapped::Drop => {}
+        }
+    }
+
+    elements
+}
+
+/// The text in the nodes, without tags.
+fn raw_text(nodes: &[RawNode]) -> String {
+    nodes
+        .iter()
+        .map(|node| match node {
+            RawNode::Text { content, .. } => content.clone(),
+            RawNode::Tag(tag) => raw_text(&tag.inner),
+            RawNode::Interpolation { expression, .. } => expression.clone(),
+        })
+        .collect()
+}
+
+/// Import the text of a dialect to huozi elements, with the diagnostics of what cannot be mapped.
+fn import(
+    input: &Segment<'_>,
+    font_size: f64,
+    dialect: &Dialect,
+    map: fn(&RawTag, f64, &mut Importer) -> Mapped,
+    prepare: fn(Vec<RawNode>) -> Vec<RawNode>,
+) -> (Vec<Element>, Vec<Diagnostic>) {
+    let mut importer = Importer {
+        segment: input,
+        diagnostics: vec![],
+    };
+    let nodes = prepare(parse_raw(dialect, &mut importer));
+    let elements = to_elements(nodes, font_size, map, &mut importer);
+
+    let mut diagnostics = importer.diagnostics;
+    diagnostics.sort_by_key(|diagnostic| diagnostic.source_range.start);
+    (elements, diagnostics)
+}
+
+/// Parse the head of a tag in markup like HTML, such as `span style="color: red"`, or such as
+/// `color=red` or `#ff0000` if `has_value` is set, which is TextMeshPro.
+fn parse_markup_head(head: &str, has_value: bool) -> Option<Head> {
+    let (head, self_closing) = match head.trim_end().strip_suffix('/') {
+        Some(head) => (head, true),
+        None => (head, false),
+    };
+
+    let name_len = head
+        .find(|c: char| c.is_whitespace() || (has_value && c == '='))
+        .unwrap_or(head.len());
+    let name = head[..name_len].to_lowercase();
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_alphanumeric() || "#-_:".contains(c))
+    {
+        return None;
+    }
+
+    let mut rest = &head[name_len..];
+    let mut value = None;
+    if has_value && let Some(after) = rest.strip_prefix('=') {
+        let (parsed, after) = attribute_value(after)?;
+        value = Some(parsed);
+        rest = after;
+    }
+
+    let mut attributes = vec![];
+    loop {
+        let trimmed = rest.trim_start();
+        if trimmed.is_empty() {
+            break;
+        }
+        if trimmed.len() == rest.len() {
+            // attributes are separated by spaces
+            return None;
+        }
+        let key_len = trimmed
+            .find(|c: char| c.is_whitespace() || c == '=')
+            .unwrap_or(trimmed.len());
+        let key = trimmed[..key_len].to_lowercase();
+        rest = &trimmed[key_len..];
+        match rest.trim_start().strip_prefix('=') {
+            Some(after) => {
+                let (parsed, after) = attribute_value(after.trim_start())?;
+                attributes.push((key, parsed));
+                rest = after;
+            }
+            None => attributes.push((key, String::new())),
+        }
+    }
+
+    Some(Head {
+        name,
+        value,
+        attributes,
+        self_closing,
+    })
+}
+
+/// Parse a quoted or bare value, returning it and the rest of the head.
+fn attribute_value(input: &str) -> Option<(String, &str)> {
+    match input.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let end = input[1..].find(quote)? + 1;
+            Some((input[1..end].to_string(), &input[end + 1..]))
+        }
+        _ => {
+            let end = input.find(char::is_whitespace).unwrap_or(input.len());
+            Some((input[..end].to_string(), &input[end..]))
+        }
+    }
+}
+
+/// Resolve a font size relative to the current size, such as `120%`, `1.5em`, `+10` or `*2`,
+/// or an absolute size such as `24` or `24px`.
+fn font_size(value: &str, current: f64) -> Option<f64> {
+    let value = value.trim();
+    let size = if let Some(percent) = value.strip_suffix('%') {
+        current * percent.trim().parse::<f64>().ok()? / 100.
+    } else if let Some(em) = value.strip_suffix("em") {
+        current * em.trim().parse::<f64>().ok()?
+    } else if let Some(scale) = value.strip_prefix('*') {
+        current * scale.trim().parse::<f64>().ok()?
+    } else if value.starts_with('+') || value.starts_with('-') {
+        current + value.parse::<f64>().ok()?
+    } else {
+        value
+            .strip_suffix("px")
+            .unwrap_or(value)
+            .trim()
+            .parse()
+            .ok()?
+    };
+
+    (size > 0. && size.is_finite()).then_some(size)
+}
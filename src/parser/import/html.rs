@@ -0,0 +1,226 @@
+use crate::parser::{Diagnostic, Element, Segment, TextStyle};
+
+use super::{Dialect, Head, Importer, Mapped, RawTag, font_size, import, parse_markup_head};
+
+const HTML: Dialect = Dialect {
+    open: '<',
+    close: '>',
+    parse_head,
+    unescape,
+    is_void,
+    reports_unclosed: true,
+    verbatim_tag: None,
+    comments: true,
+    quoted_values: true,
+    parse_interpolation: None,
+};
+
+/// Import a subset of HTML, such as `<span style="color: red">red</span>`, to huozi elements,
+/// with the diagnostics of the tags and styles which cannot be mapped.
+///
+/// - `color`, `font-size`, `font-family` and unitless `line-height` in the `style` attribute
+///   of `span` are mapped to the huozi tags, where relative sizes such as `120%` and `1.5em`
+///   are resolved from the font size of `base_style`. So are the `color` and `face`
+///   attributes of `font`.
+/// - `<br>` is mapped to a line break, and `p` and `div` are followed by a line break.
+/// - `<ruby>` with `<rt>` is mapped to `ruby`, where several `<rt>` are mono ruby.
+/// - Character references such as `&lt;` and `&#x5b57;` are unescaped, and comments are dropped.
+///
+/// Whitespace is kept as it is. Other tags, such as `<b>` and `<img>`, are reported and
+/// dropped, keeping the text in them.
+pub fn import_html(input: &Segment<'_>, base_style: &TextStyle) -> (Vec<Element>, Vec<Diagnostic>) {
+    import(input, base_style.font_size, &HTML, map, |nodes| nodes)
+}
+
+fn parse_head(head: &str) -> Option<Head> {
+    parse_markup_head(head, false)
+}
+
+fn is_void(name: &str) -> bool {
+    matches!(name, "br" | "img" | "hr" | "wbr")
+}
+
+fn unescape(text: &str) -> Option<(usize, String)> {
+    let reference = text.strip_prefix('&')?;
+    let end = reference.find(';')?;
+    let name = &reference[..end];
+
+    let decoded = match name {
+        "lt" => '<',
+        "gt" => '>',
+        "amp" => '&',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        _ => {
+            let code = match name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => name.strip_prefix('#')?.parse().ok()?,
+            };
+            char::from_u32(code)?
+        }
+    };
+
+    Some((end + 2, decoded.to_string()))
+}
+
+fn map(tag: &RawTag, current_size: f64, importer: &mut Importer) -> Mapped {
+    let name = tag.head.name.as_str();
+    let attribute = |key: &str| {
+        tag.head
+            .attributes
+            .iter()
+            .find(|(attribute, _)| attribute == key)
+            .map(|(_, value)| value.as_str())
+    };
+
+    match name {
+        "span" => {
+            let mut attributes = vec![];
+            let mut font_size = current_size;
+            for declaration in attribute("style").unwrap_or_default().split(';') {
+                let Some((property, value)) = declaration.split_once(':') else {
+                    continue;
+                };
+                let (property, value) = (property.trim().to_lowercase(), value.trim());
+                match style_attribute(&property, value, font_size) {
+                    Some((key, value)) => {
+                        if key == "size" {
+                            font_size = value.parse().unwrap_or(font_size);
+                        }
+                        attributes.push((key.to_string(), value));
+                    }
+                    None => importer.unsupported(
+                        tag,
+                        format!("style `{}: {}` has no equivalent, ignored", property, value),
+                    ),
+                }
+            }
+            style_block(attributes, font_size)
+        }
+        "font" => {
+            let mut attributes = vec![];
+            if let Some(color) = attribute("color") {
+                attributes.push(("color".to_string(), color.to_string()));
+            }
+            if let Some(face) = attribute("face") {
+                attributes.push(("font".to_string(), font_family(face)));
+            }
+            if attribute("size").is_some() {
+                importer.unsupported(
+                    tag,
+                    "the size of `<font>` has no equivalent, ignored".into(),
+                );
+            }
+            style_block(attributes, current_size)
+        }
+        "br" => Mapped::Text("\n".to_string()),
+        "p" | "div" => Mapped::Unwrap { newline: true },
+        "ruby" => Mapped::Ruby { annotation: "rt" },
+        "rp" => Mapped::Drop,
+        _ => {
+            importer.unsupported(
+                tag,
+                format!("HTML tag `<{}>` has no equivalent, ignored", name),
+            );
+            if is_void(name) {
+                Mapped::Drop
+            } else {
+                Mapped::Unwrap { newline: false }
+            }
+        }
+    }
+}
+
+/// Map a CSS declaration to a huozi attribute, resolving relative font sizes.
+fn style_attribute(
+    property: &str,
+    value: &str,
+    current_size: f64,
+) -> Option<(&'static str, String)> {
+    match property {
+        "color" => Some(("color", value.to_string())),
+        "font-size" => Some(("size", font_size(value, current_size)?.to_string())),
+        "font-family" => Some(("font", font_family(value))),
+        "line-height" => {
+            let line_height = value.parse::<f64>().ok()?;
+            Some(("lineHeight", line_height.to_string()))
+        }
+        _ => None,
+    }
+}
+
+/// The first family in a list of font families, without quotes.
+fn font_family(value: &str) -> String {
+    let family = value.split(',').next().unwrap_or_default().trim();
+    family.trim_matches(|c| c == '"' || c == '\'').to_string()
+}
+
+/// A `style` tag with the attributes, or no tag without attributes.
+fn style_block(attributes: Vec<(String, String)>, font_size: f64) -> Mapped {
+    if attributes.is_empty() {
+        return Mapped::Unwrap { newline: false };
+    }
+    Mapped::Block {
+        tag: "style".to_string(),
+        value: None,
+        attributes,
+        font_size,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::*;
+
+    #[test]
+    fn test_import_html() {
+        let input = Segment::dummy(concat!(
+            "<p>a&lt;<b>b</b></p><!-- note -->",
+            "<span style=\"color: red; font-size: 150%; font-family: 'Noto Sans', serif\">",
+            "c<span style=\"font-size: 0.5em; text-align: center\">d</span></span><br/>",
+            "<ruby>漢<rp>(</rp><rt>かん</rt>字<rt>じ</rt></ruby><font color=\"#00f\">&#x5b57;</font>"
+        ));
+        let (elements, diagnostics) = import_html(&input, &TextStyle::default());
+
+        assert_eq!(
+            elements_to_markup(&elements, &ParserConfig::default()).unwrap(),
+            concat!(
+                "a<b\n",
+                "[style color=red size=48 font=\"Noto Sans\"]c[style size=24]d[/style][/style]\n",
+                "[ruby=かん|じ]漢字[/ruby][style color=#00f]字[/style]"
+            )
+        );
+
+        let messages = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            [
+                "HTML tag `<b>` has no equivalent, ignored",
+                "style `text-align: center` has no equivalent, ignored"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_import_html_quoted_close() {
+        let input = Segment::dummy(r#"<span title="a>b" style='color: red'>c</span><b x=1>d</b>"#);
+        let (elements, diagnostics) = import_html(&input, &TextStyle::default());
+
+        assert_eq!(
+            elements_to_markup(&elements, &ParserConfig::default()).unwrap(),
+            "[style color=red]c[/style]d"
+        );
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            (
+                diagnostics[0].source_range.start,
+                diagnostics[0].source_range.end
+            ),
+            (45, 52)
+        );
+    }
+}
@@ -0,0 +1,268 @@
+use crate::parser::{Diagnostic, Element, Segment, TextStyle};
+
+use super::{Dialect, Head, Importer, Mapped, RawNode, RawTag, font_size, import};
+
+const RENPY: Dialect = Dialect {
+    open: '{',
+    close: '}',
+    parse_head,
+    unescape,
+    is_void,
+    reports_unclosed: true,
+    verbatim_tag: None,
+    comments: false,
+    quoted_values: false,
+    parse_interpolation: Some(parse_interpolation),
+};
+
+/// Import Ren'Py text with text tags, such as `{color=#f00}red{/color}`, to huozi elements,
+/// with the diagnostics of the tags which cannot be mapped.
+///
+/// - `color`, `font` and `size` are mapped to the huozi tags, where relative sizes such as
+///   `{size=+10}` and `{size=*2}` are resolved from the font size of `base_style`.
+/// - `{cps=*2}` is mapped to `speed`, and `{w=0.5}` to `wait`.
+/// - `{rb}` followed by `{rt}` is mapped to `ruby`.
+/// - `[name]` interpolating a variable is mapped to `[var=name]`. Interpolations with
+///   conversions or format specs, such as `[name!u]`, are reported and kept as text.
+/// - `{{` is unescaped to `{`, and `[[` to `[`.
+/// - Comments such as `{#note}` are dropped.
+///
+/// Other tags, such as `{b}` and `{w}` which waits for a click, are reported and dropped,
+/// keeping the text in them.
+pub fn import_renpy(
+    input: &Segment<'_>,
+    base_style: &TextStyle,
+) -> (Vec<Element>, Vec<Diagnostic>) {
+    import(input, base_style.font_size, &RENPY, map, pair_ruby)
+}
+
+fn parse_head(head: &str) -> Option<Head> {
+    // comments such as `{#a note}` are dropped, whatever is in them
+    if head.starts_with('#') {
+        return Some(Head {
+            name: "#".to_string(),
+            value: None,
+            attributes: vec![],
+            self_closing: false,
+        });
+    }
+
+    let (name, value) = match head.split_once('=') {
+        Some((name, value)) => (name, Some(value.to_string())),
+        None => (head, None),
+    };
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+
+    Some(Head {
+        name: name.to_lowercase(),
+        value,
+        attributes: vec![],
+        self_closing: false,
+    })
+}
+
+fn unescape(text: &str) -> Option<(usize, String)> {
+    if text.starts_with("{{") {
+        Some((2, "{".to_string()))
+    } else if text.starts_with("[[") {
+        Some((2, "[".to_string()))
+    } else {
+        None
+    }
+}
+
+/// Parse an interpolation such as `[name]`, which is not `[[`.
+fn parse_interpolation(text: &str) -> Option<(usize, &str)> {
+    let rest = text
+        .strip_prefix('[')
+        .filter(|rest| !rest.starts_with('['))?;
+    let end = rest.find([']', '[', '\n'])?;
+    rest[end..]
+        .starts_with(']')
+        .then(|| (end + 2, &rest[..end]))
+}
+
+fn is_void(name: &str) -> bool {
+    name.starts_with('#')
+        || matches!(
+            name,
+            "w" | "p" | "nw" | "fast" | "done" | "clear" | "image" | "space" | "vspace"
+        )
+}
+
+/// Join each `{rb}` with the `{rt}` after it as a ruby.
+fn pair_ruby(nodes: Vec<RawNode>) -> Vec<RawNode> {
+    let mut paired: Vec<RawNode> = vec![];
+    for node in nodes {
+        let node = match node {
+            RawNode::Tag(mut tag) => {
+                tag.inner = pair_ruby(tag.inner);
+                tag
+            }
+            text => {
+                paired.push(text);
+                continue;
+            }
+        };
+
+        match paired.last_mut() {
+            Some(RawNode::Tag(base)) if node.head.name == "rt" && base.head.name == "rb" => {
+                base.head.name = "ruby".to_string();
+                base.end = node.end;
+                base.inner.push(RawNode::Tag(node));
+            }
+            _ => paired.push(RawNode::Tag(node)),
+        }
+    }
+    paired
+}
+
+fn map(tag: &RawTag, current_size: f64, importer: &mut Importer) -> Mapped {
+    let name = tag.head.name.as_str();
+    let value = tag.head.value.as_deref();
+
+    match (name, value) {
+        ("color" | "font", Some(value)) => Mapped::Block {
+            tag: name.to_string(),
+            value: Some(value.to_string()),
+            attributes: vec![],
+            font_size: current_size,
+        },
+        ("size", Some(value)) => match font_size(value, current_size) {
+            Some(size) => Mapped::Block {
+                tag: "size".to_string(),
+                value: Some(size.to_string()),
+                attributes: vec![],
+                font_size: size,
+            },
+            None => {
+                importer.unsupported(tag, format!("invalid size `{}`, ignored", value));
+                Mapped::Unwrap { newline: false }
+            }
+        },
+        ("cps", Some(value)) if value.starts_with('*') => Mapped::Block {
+            tag: "speed".to_string(),
+            value: Some(value[1..].to_string()),
+            attributes: vec![],
+            font_size: current_size,
+        },
+        ("w", Some(value)) => Mapped::Void {
+            tag: "wait".to_string(),
+            value: Some(value.to_string()),
+            attributes: vec![],
+        },
+        ("ruby", _) => Mapped::Ruby { annotation: "rt" },
+        _ if name.starts_with('#') => Mapped::Drop,
+        _ => {
+            importer.unsupported(
+                tag,
+                format!("Ren'Py tag `{{{}}}` has no equivalent, ignored", name),
+            );
+            if name == "p" {
+                Mapped::Text("\n".to_string())
+            } else if is_void(name) {
+                Mapped::Drop
+            } else {
+                Mapped::Unwrap { newline: false }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::*;
+
+    #[test]
+    fn test_import_renpy() {
+        let input = Segment::dummy(
+            "{b}a{/b}{color=#f00}b{size=+8}c{/size}{/color}{w=0.5}{{d{#note}{rb}漢字{/rb}{rt}かんじ{/rt}{p}e",
+        );
+        let (elements, diagnostics) = import_renpy(&input, &TextStyle::default());
+
+        assert_eq!(
+            elements_to_markup(&elements, &ParserConfig::default()).unwrap(),
+            "a[color=#f00]b[size=40]c[/size][/color][wait=0.5]{d[ruby=かんじ]漢字[/ruby]\ne"
+        );
+
+        let codes = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.code, diagnostic.source_range.start))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            codes,
+            [
+                (DiagnosticCode::Unsupported, 0),
+                (DiagnosticCode::Unsupported, 96)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_import_renpy_malformed() {
+        let (elements, diagnostics) = import_renpy(
+            &Segment::dummy("{i}a{/b}{color=red}b"),
+            &TextStyle::default(),
+        );
+
+        assert_eq!(
+            elements_to_markup(&elements, &ParserConfig::default()).unwrap(),
+            "a[color=red]b[/color]"
+        );
+        let codes = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.code)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            codes,
+            [
+                DiagnosticCode::UnclosedTag,
+                DiagnosticCode::Unsupported,
+                DiagnosticCode::UnmatchedEndTag,
+                DiagnosticCode::UnclosedTag
+            ]
+        );
+    }
+
+    #[test]
+    fn test_import_renpy_interpolation() {
+        let input = Segment::dummy("[[a] [player.name] [name!u] [b");
+        let (elements, diagnostics) = import_renpy(&input, &TextStyle::default());
+
+        assert_eq!(
+            elements_to_markup(&elements, &ParserConfig::default()).unwrap(),
+            "[[a]] [var=player.name] [[name!u]] [[b"
+        );
+        let var = elements.iter().find_map(|element| match element {
+            Element::Void { start, end, .. } => Some((*start, *end)),
+            _ => None,
+        });
+        assert_eq!(var, Some((5, 18)));
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, DiagnosticCode::Unsupported);
+        assert_eq!(
+            (
+                diagnostics[0].source_range.start,
+                diagnostics[0].source_range.end
+            ),
+            (19, 27)
+        );
+    }
+
+    #[test]
+    fn test_import_renpy_comments() {
+        let (elements, diagnostics) = import_renpy(
+            &Segment::dummy("a{#a note, with spaces}b{#}c"),
+            &TextStyle::default(),
+        );
+
+        assert_eq!(
+            elements_to_markup(&elements, &ParserConfig::default()).unwrap(),
+            "abc"
+        );
+        assert!(diagnostics.is_empty());
+    }
+}
@@ -0,0 +1,124 @@
+use crate::parser::{Diagnostic, Element, Segment, TextStyle};
+
+use super::{Dialect, Head, Importer, Mapped, RawTag, font_size, import, parse_markup_head};
+
+const TEXT_MESH_PRO: Dialect = Dialect {
+    open: '<',
+    close: '>',
+    parse_head,
+    unescape: |_| None,
+    is_void,
+    reports_unclosed: false,
+    verbatim_tag: Some("noparse"),
+    comments: false,
+    quoted_values: true,
+    parse_interpolation: None,
+};
+
+/// Import Unity TextMeshPro rich text, such as `<color=red>red</color>`, to huozi elements,
+/// with the diagnostics of the tags which cannot be mapped.
+///
+/// - `color` (or `<#ff0000>`), `font` and `size` are mapped to the huozi tags, where relative
+///   sizes such as `<size=120%>`, `<size=1.5em>` and `<size=+10>` are resolved from the font size
+///   of `base_style`.
+/// - `<br>` is mapped to a line break, and the text in `<noparse>` is kept as it is.
+/// - `<sprite>` is mapped to a void tag, which is an inline marker for the application.
+///
+/// Tags are closed at the end of the text if they are not closed, as TextMeshPro does.
+/// Other tags, such as `<b>` and `<align>`, are reported and dropped, keeping the text in them.
+pub fn import_text_mesh_pro(
+    input: &Segment<'_>,
+    base_style: &TextStyle,
+) -> (Vec<Element>, Vec<Diagnostic>) {
+    import(input, base_style.font_size, &TEXT_MESH_PRO, map, |nodes| {
+        nodes
+    })
+}
+
+fn parse_head(head: &str) -> Option<Head> {
+    // `<#ff0000>` is a short color tag, which is closed by `</color>`
+    if head.starts_with('#') {
+        return Some(Head {
+            name: "color".to_string(),
+            value: Some(head.trim().to_string()),
+            attributes: vec![],
+            self_closing: false,
+        });
+    }
+    parse_markup_head(head, true)
+}
+
+fn is_void(name: &str) -> bool {
+    matches!(name, "br" | "sprite" | "space" | "pos" | "page")
+}
+
+fn map(tag: &RawTag, current_size: f64, importer: &mut Importer) -> Mapped {
+    let name = tag.head.name.as_str();
+    let value = tag.head.value.as_deref();
+
+    match (name, value) {
+        ("color" | "font", Some(value)) => block(name, value, current_size),
+        ("size", Some(value)) => match font_size(value, current_size) {
+            Some(size) => block("size", &size.to_string(), size),
+            None => {
+                importer.unsupported(tag, format!("invalid size `{}`, ignored", value));
+                Mapped::Unwrap { newline: false }
+            }
+        },
+        ("br", _) => Mapped::Text("\n".to_string()),
+        ("noparse", _) => Mapped::Unwrap { newline: false },
+        ("sprite", _) => Mapped::Void {
+            tag: "sprite".to_string(),
+            value: value.map(str::to_string),
+            attributes: tag.head.attributes.clone(),
+        },
+        _ => {
+            importer.unsupported(
+                tag,
+                format!("TextMeshPro tag `<{}>` has no equivalent, ignored", name),
+            );
+            if is_void(name) {
+                Mapped::Drop
+            } else {
+                Mapped::Unwrap { newline: false }
+            }
+        }
+    }
+}
+
+fn block(tag: &str, value: &str, font_size: f64) -> Mapped {
+    Mapped::Block {
+        tag: tag.to_string(),
+        value: Some(value.to_string()),
+        attributes: vec![],
+        font_size,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::*;
+
+    #[test]
+    fn test_import_text_mesh_pro() {
+        let input = Segment::dummy(concat!(
+            "<b>a</b><color=\"red\">b<size=150%>c</size></color><#00ff00>d</color>",
+            "<br><sprite name=\"coin\"><noparse><b>e</b></noparse><size=2em>f"
+        ));
+        let (elements, diagnostics) = import_text_mesh_pro(&input, &TextStyle::default());
+
+        assert_eq!(
+            elements_to_markup(&elements, &ParserConfig::default()).unwrap(),
+            concat!(
+                "a[color=red]b[size=48]c[/size][/color][color=#00ff00]d[/color]",
+                "\n[sprite name=coin/]<b>e</b>[size=64]f[/size]"
+            )
+        );
+        // only the bold tag is reported, the unclosed size is closed at the end
+        let codes = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.code, diagnostic.source_range.start))
+            .collect::<Vec<_>>();
+        assert_eq!(codes, [(DiagnosticCode::Unsupported, 0)]);
+    }
+}
@@ -0,0 +1,8 @@
+/// A marker at a position in the text from a void tag, such as `[icon=coin/]` or
+/// `[voice=line_012/]`, for the application to place an inline object or trigger an event there.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct InlineMarker {
+    pub tag: String,
+    pub value: Option<String>,
+    pub attributes: Vec<(String, String)>,
+}
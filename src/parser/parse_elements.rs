@@ -1,53 +1,27 @@
 use nom::{
-    IResult, Parser,
+    IResult, Input, Parser,
     branch::alt,
-    bytes::complete::{is_not, tag},
-    character::complete::{char, multispace0},
-    combinator::{cut, eof, map, not, value, verify},
+    bytes::complete::{take_till1, take_while1},
+    character::complete::{char, multispace0, multispace1, satisfy},
+    combinator::{cut, eof, map, not, opt, value, verify},
     error::context,
     multi::{fold_many0, many_till, many0},
     sequence::{preceded, separated_pair, terminated},
 };
-use nom_language::error::{VerboseError, convert_error};
+use nom_language::error::{VerboseError, VerboseErrorKind};
 use nom_locate::LocatedSpan;
-use std::sync::OnceLock;
 
-use crate::parser::{Segment, SegmentId};
+use crate::parser::{
+    Diagnostic, DiagnosticCode, ParseError, ParserConfig, Segment, SegmentId, Severity, SourceRange,
+};
 
 // Type alias for input with location tracking
 pub type Span<'a> = LocatedSpan<&'a str, Option<SegmentId>>;
 
-// Global caches for tag symbols
-// Note: These are shared across all generic parameter combinations.
-// Convention: Use only ONE symbol combination throughout the program's lifetime.
-static DOUBLE_OPEN: OnceLock<String> = OnceLock::new();
-static DOUBLE_CLOSE: OnceLock<String> = OnceLock::new();
-static END_PREFIX: OnceLock<String> = OnceLock::new();
-static EXCLUDED_CHARS: OnceLock<String> = OnceLock::new();
-
-/// Get the double open tag string (e.g., "[[" for '[')
-/// Initialized on first call and cached for subsequent calls.
-fn get_double_open<const OPEN: char>() -> &'static str {
-    DOUBLE_OPEN.get_or_init(|| format!("{}{}", OPEN, OPEN))
-}
-
-/// Get the double close tag string (e.g., "]]" for ']')
-/// Initialized on first call and cached for subsequent calls.
-fn get_double_close<const CLOSE: char>() -> &'static str {
-    DOUBLE_CLOSE.get_or_init(|| format!("{}{}", CLOSE, CLOSE))
-}
-
-/// Get the end tag prefix string (e.g., "[/" for '[')
-/// Initialized on first call and cached for subsequent calls.
-fn get_end_prefix<const OPEN: char>() -> &'static str {
-    END_PREFIX.get_or_init(|| format!("{}/", OPEN))
-}
-
-/// Get the excluded characters for string parsing
-/// Initialized on first call and cached for subsequent calls.
-fn get_excluded_chars<const OPEN: char, const CLOSE: char>() -> &'static str {
-    EXCLUDED_CHARS.get_or_init(|| format!("\"\'{}{}{}= \t\n\r", OPEN, CLOSE, '/'))
-}
+/// Tags which are always void by default, i.e. they have no content and no end tag, such as
+/// `[wait=0.5]`. Any other tag can be written as void by ending its head with a slash, such as
+/// `[icon=coin/]`.
+pub const VOID_TAGS: &[&str] = &["wait", "var"];
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Element {
@@ -63,33 +37,58 @@ pub enum Element {
         inner: Vec<Element>,
         tag: String,
         value: Option<String>,
+        /// the attributes after the tag name or value, such as `size=40` in
+        /// `[color=red size=40]`, in the order they are written
+        attributes: Vec<(String, String)>,
+        segment_id: Option<SegmentId>,
+    },
+    /// A tag without content and end tag, which marks a position in the text.
+    Void {
+        start: usize,
+        end: usize,
+        tag: String,
+        value: Option<String>,
+        attributes: Vec<(String, String)>,
+        segment_id: Option<SegmentId>,
     },
 }
 
-pub type ParseResult<'a, T, E = VerboseError<Span<'a>>> = IResult<Span<'a>, T, E>;
+/// The parts of a tag head such as `[color=red size=40]`.
+struct TagHead {
+    key: String,
+    value: Option<String>,
+    attributes: Vec<(String, String)>,
+    /// whether the head ends with a slash, as a void tag
+    is_void: bool,
+}
 
-/// Parse plain text with support for [[ and ]] escape sequences
-/// [[ -> [
-/// ]] -> ]
-/// Single [ or ] will stop the parser (not consume them)
-fn plain_text_content<const OPEN: char, const CLOSE: char>(
-    input: Span<'_>,
-) -> ParseResult<'_, String> {
-    use nom::bytes::complete::take_while1;
+pub type ParseResult<'a, T, E = VerboseError<Span<'a>>> = IResult<Span<'a>, T, E>;
 
-    let double_open = get_double_open::<OPEN>();
-    let double_close = get_double_close::<CLOSE>();
+/// Parse an escaped tag character, which is doubled such as `[[` -> `[` and `]]` -> `]`,
+/// or follows the escape character such as `\[` -> `[` and `\\` -> `\`.
+fn escaped_char<'a>(input: Span<'a>, config: &ParserConfig) -> ParseResult<'a, char> {
+    match config.escape {
+        None => alt((
+            value(config.open, (char(config.open), char(config.open))),
+            value(config.close, (char(config.close), char(config.close))),
+        ))
+        .parse(input),
+        Some(escape) => {
+            preceded(char(escape), satisfy(|c| config.escape_len(c).is_some())).parse(input)
+        }
+    }
+}
 
+/// Parse plain text with support for escape sequences.
+/// Single tag characters will stop the parser (not consume them)
+fn plain_text_content<'a>(input: Span<'a>, config: &ParserConfig) -> ParseResult<'a, String> {
     context(
         "PlainTextContent",
         fold_many0(
             alt((
-                // [[ -> [
-                value(OPEN.to_string(), tag(double_open)),
-                // ]] -> ]
-                value(CLOSE.to_string(), tag(double_close)),
-                // Regular text (not starting with [ or ])
-                map(take_while1(|c| c != OPEN && c != CLOSE), |s: Span| {
+                map(|input| escaped_char(input, config), |c| c.to_string()),
+                // Regular text (not starting with tag characters)
+                map(take_while1(|c| !config.is_special(c)), |s: Span| {
                     s.fragment().to_string()
                 }),
             )),
@@ -103,32 +102,39 @@ fn plain_text_content<const OPEN: char, const CLOSE: char>(
     .parse(input)
 }
 
-fn string_quoted_single(input: Span<'_>) -> ParseResult<'_, String> {
-    context(
-        "String Quoted Single",
+/// Parse the content of a value quoted by `quote`, in which the quote is doubled,
+/// such as `"say ""hi"""` -> `say "hi"`.
+fn quoted_content(quote: char) -> impl Fn(Span<'_>) -> ParseResult<'_, String> {
+    move |input| {
         preceded(
-            char('\''),
+            char(quote),
             cut(terminated(
-                map(is_not("\'"), |s: Span| s.fragment().to_string()),
-                char('\''),
+                fold_many0(
+                    alt((
+                        map(take_till1(|c| c == quote), |s: Span| {
+                            s.fragment().to_string()
+                        }),
+                        value(quote.to_string(), (char(quote), char(quote))),
+                    )),
+                    String::new,
+                    |mut acc, item| {
+                        acc.push_str(&item);
+                        acc
+                    },
+                ),
+                char(quote),
             )),
-        ),
-    )
-    .parse(input)
+        )
+        .parse(input)
+    }
+}
+
+fn string_quoted_single(input: Span<'_>) -> ParseResult<'_, String> {
+    context("String Quoted Single", quoted_content('\'')).parse(input)
 }
 
 fn string_quoted_double(input: Span<'_>) -> ParseResult<'_, String> {
-    context(
-        "String Quoted",
-        preceded(
-            char('\"'),
-            cut(terminated(
-                map(is_not("\""), |s: Span| s.fragment().to_string()),
-                char('\"'),
-            )),
-        ),
-    )
-    .parse(input)
+    context("String Quoted", quoted_content('"')).parse(input)
 }
 
 fn string_quoted(input: Span<'_>) -> ParseResult<'_, String> {
@@ -139,28 +145,32 @@ fn string_quoted(input: Span<'_>) -> ParseResult<'_, String> {
     .parse(input)
 }
 
-fn string_without_space<const OPEN: char, const CLOSE: char>(
-    input: Span<'_>,
-) -> ParseResult<'_, String> {
-    let chars = get_excluded_chars::<OPEN, CLOSE>();
+fn string_without_space<'a>(input: Span<'a>, config: &ParserConfig) -> ParseResult<'a, String> {
+    let is_excluded = |c: char| {
+        matches!(c, '"' | '\'' | '=' | ' ' | '\t' | '\n' | '\r')
+            || c == config.open
+            || c == config.close
+            || c == config.slash
+    };
     context(
         "String without Space",
-        map(preceded(multispace0, is_not(chars)), |s: Span| {
+        map(preceded(multispace0, take_till1(is_excluded)), |s: Span| {
             s.fragment().to_string()
         }),
     )
     .parse(input)
 }
 
-fn plain_text<const OPEN: char, const CLOSE: char>(input: Span<'_>) -> ParseResult<'_, Element> {
+fn plain_text<'a>(input: Span<'a>, config: &ParserConfig) -> ParseResult<'a, Element> {
     let start_offset = input.location_offset();
     let segment_id = input.extra.clone();
 
     let (remaining, content) = context(
         "PlainText",
-        verify(plain_text_content::<OPEN, CLOSE>, |s: &String| {
-            !s.is_empty()
-        }),
+        verify(
+            |input| plain_text_content(input, config),
+            |s: &String| !s.is_empty(),
+        ),
     )
     .parse(input)?;
 
@@ -177,82 +187,115 @@ fn plain_text<const OPEN: char, const CLOSE: char>(input: Span<'_>) -> ParseResu
     ))
 }
 
-fn tag_head_keypair<const OPEN: char, const CLOSE: char>(
-    input: Span<'_>,
-) -> ParseResult<'_, (String, Option<String>)> {
+fn tag_head_keypair<'a>(
+    input: Span<'a>,
+    config: &ParserConfig,
+) -> ParseResult<'a, (String, Option<String>)> {
     context(
         "TagHeadKeyPair",
         alt((
             map(
                 separated_pair(
-                    preceded(multispace0, tag_key::<OPEN, CLOSE>),
+                    preceded(multispace0, |input| tag_key(input, config)),
                     preceded(multispace0, char('=')),
-                    preceded(multispace0, tag_value::<OPEN, CLOSE>),
+                    preceded(multispace0, |input| tag_value(input, config)),
                 ),
                 |(k, v)| (k, Some(v)),
             ),
-            map(preceded(multispace0, tag_key::<OPEN, CLOSE>), |s| (s, None)),
+            map(preceded(multispace0, |input| tag_key(input, config)), |s| {
+                (s, None)
+            }),
             value(("".to_string(), None), multispace0),
         )),
     )
     .parse(input)
 }
 
-fn tag_key<const OPEN: char, const CLOSE: char>(input: Span<'_>) -> ParseResult<'_, String> {
-    context("TagKey", string_without_space::<OPEN, CLOSE>).parse(input)
+fn tag_attribute<'a>(input: Span<'a>, config: &ParserConfig) -> ParseResult<'a, (String, String)> {
+    context(
+        "TagAttribute",
+        preceded(
+            multispace1,
+            separated_pair(
+                |input| tag_key(input, config),
+                preceded(multispace0, char('=')),
+                preceded(multispace0, |input| tag_value(input, config)),
+            ),
+        ),
+    )
+    .parse(input)
 }
 
-fn tag_value<const OPEN: char, const CLOSE: char>(input: Span<'_>) -> ParseResult<'_, String> {
+fn tag_key<'a>(input: Span<'a>, config: &ParserConfig) -> ParseResult<'a, String> {
+    context("TagKey", |input| string_without_space(input, config)).parse(input)
+}
+
+fn tag_value<'a>(input: Span<'a>, config: &ParserConfig) -> ParseResult<'a, String> {
     context(
         "TagValue",
-        alt((string_without_space::<OPEN, CLOSE>, string_quoted)),
+        alt((|input| string_without_space(input, config), string_quoted)),
     )
     .parse(input)
 }
 
-fn tag_head<const OPEN: char, const CLOSE: char>(
-    input: Span<'_>,
-) -> ParseResult<'_, (String, Option<String>)> {
+fn tag_head<'a>(input: Span<'a>, config: &ParserConfig) -> ParseResult<'a, TagHead> {
     context(
         "TagHead",
-        preceded(
-            (char(OPEN), not(char('/'))),
-            cut(terminated(
-                tag_head_keypair::<OPEN, CLOSE>,
-                preceded(multispace0, char(CLOSE)),
-            )),
+        map(
+            preceded(
+                (char(config.open), not(char(config.slash))),
+                cut((
+                    |input| tag_head_keypair(input, config),
+                    many0(|input| tag_attribute(input, config)),
+                    terminated(
+                        map(opt(preceded(multispace0, char(config.slash))), |slash| {
+                            slash.is_some()
+                        }),
+                        preceded(multispace0, char(config.close)),
+                    ),
+                )),
+            ),
+            |((key, value), attributes, is_void)| TagHead {
+                key,
+                value,
+                attributes,
+                is_void,
+            },
         ),
     )
     .parse(input)
 }
 
-fn tag_end<const OPEN: char, const CLOSE: char>(input: Span<'_>) -> ParseResult<'_, String> {
-    let end_prefix = get_end_prefix::<OPEN>();
+fn tag_end<'a>(input: Span<'a>, config: &ParserConfig) -> ParseResult<'a, String> {
     context(
         "TagEnd",
         preceded(
-            tag(end_prefix),
+            (char(config.open), char(config.slash)),
             cut(terminated(
-                alt((tag_key::<OPEN, CLOSE>, value("".to_string(), multispace0))),
-                preceded(multispace0, char(CLOSE)),
+                alt((
+                    |input| tag_key(input, config),
+                    value("".to_string(), multispace0),
+                )),
+                preceded(multispace0, char(config.close)),
             )),
         ),
     )
     .parse(input)
 }
 
-fn closed_tag<const OPEN: char, const CLOSE: char>(input: Span<'_>) -> ParseResult<'_, Element> {
+fn closed_tag<'a>(input: Span<'a>, config: &ParserConfig) -> ParseResult<'a, Element> {
     let start_offset = input.location_offset();
+    let segment_id = input.extra.clone();
 
-    let (remaining, ((key, value), inner, _)) = context(
+    let (remaining, (head, inner, _)) = context(
         "Tag",
         verify(
             (
-                tag_head::<OPEN, CLOSE>,
-                elements::<OPEN, CLOSE>,
-                tag_end::<OPEN, CLOSE>,
+                |input| tag_head(input, config),
+                |input| elements(input, config),
+                |input| tag_end(input, config),
             ),
-            |&((ref head_key, _), _, ref end_key)| head_key == end_key,
+            |(head, _, end_key)| !head.is_void && &head.key == end_key,
         ),
     )
     .parse(input)?;
@@ -265,34 +308,66 @@ fn closed_tag<const OPEN: char, const CLOSE: char>(input: Span<'_>) -> ParseResu
             start: start_offset,
             end: end_offset,
             inner,
-            tag: key.to_string(),
-            value: value.map(|s| s.to_string()),
+            tag: head.key,
+            value: head.value,
+            attributes: head.attributes,
+            segment_id,
         },
     ))
 }
 
-fn element<const OPEN: char, const CLOSE: char>(input: Span<'_>) -> ParseResult<'_, Element> {
+fn void_tag<'a>(input: Span<'a>, config: &ParserConfig) -> ParseResult<'a, Element> {
+    let start_offset = input.location_offset();
+    let segment_id = input.extra.clone();
+
+    let (remaining, head) = context(
+        "VoidTag",
+        verify(
+            |input| tag_head(input, config),
+            |head| head.is_void || config.is_void_tag(&head.key),
+        ),
+    )
+    .parse(input)?;
+
+    let end_offset = remaining.location_offset();
+
+    Ok((
+        remaining,
+        Element::Void {
+            start: start_offset,
+            end: end_offset,
+            tag: head.key,
+            value: head.value,
+            attributes: head.attributes,
+            segment_id,
+        },
+    ))
+}
+
+fn element<'a>(input: Span<'a>, config: &ParserConfig) -> ParseResult<'a, Element> {
     context(
         "Element",
-        alt((plain_text::<OPEN, CLOSE>, closed_tag::<OPEN, CLOSE>)),
+        alt((
+            |input| plain_text(input, config),
+            |input| void_tag(input, config),
+            |input| closed_tag(input, config),
+        )),
     )
     .parse(input)
 }
 
-fn elements<const OPEN: char, const CLOSE: char>(input: Span<'_>) -> ParseResult<'_, Vec<Element>> {
-    context("Element[]", many0(element::<OPEN, CLOSE>)).parse(input)
+fn elements<'a>(input: Span<'a>, config: &ParserConfig) -> ParseResult<'a, Vec<Element>> {
+    context("Element[]", many0(|input| element(input, config))).parse(input)
 }
 
 /// Parse input with custom tag symbols.
 ///
+/// This is equivalent to calling `parse_with_config` with `ParserConfig::with_delimiters`.
+///
 /// # Type Parameters
 /// * `OPEN` - The opening tag character (e.g., '[', '<', '{')
 /// * `CLOSE` - The closing tag character (e.g., ']', '>', '}')
 ///
-/// # Convention
-/// Only one symbol combination should be used throughout the program's lifetime.
-/// Mixing different symbol combinations in the same program run may produce incorrect results.
-///
 /// # Examples
 /// ```ignore
 /// // Use square brackets
@@ -306,23 +381,20 @@ fn elements<const OPEN: char, const CLOSE: char>(input: Span<'_>) -> ParseResult
 /// ```
 pub fn parse_with<const OPEN: char, const CLOSE: char>(
     input: &Segment<'_>,
-) -> Result<Vec<Element>, String> {
+) -> Result<Vec<Element>, ParseError> {
+    parse_with_config(input, &ParserConfig::with_delimiters(OPEN, CLOSE))
+}
+
+/// Parse input with the tag characters and void tags in the config.
+pub fn parse_with_config(
+    input: &Segment<'_>,
+    config: &ParserConfig,
+) -> Result<Vec<Element>, ParseError> {
     let span = Span::new_extra(&input.content, input.id.clone());
-    match context("Root", many_till(element::<OPEN, CLOSE>, eof)).parse(span) {
+    match context("Root", many_till(|input| element(input, config), eof)).parse(span) {
         Ok((_, (r, _))) => Ok(r),
         Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => {
-            // Convert Span-based error to str-based error for convert_error
-            let converted_error: VerboseError<&str> = VerboseError {
-                errors: e
-                    .errors
-                    .into_iter()
-                    .map(|(span, kind)| (*span.fragment(), kind))
-                    .collect(),
-            };
-            Err(convert_error(
-                input.content.as_ref(),
-                converted_error,
-            ))
+            Err(ParseError::Syntax(syntax_diagnostic(input, e)))
         }
         Err(nom::Err::Incomplete(_)) => {
             unreachable!("it should not reach this branch, may be a bug.");
@@ -330,9 +402,260 @@ pub fn parse_with<const OPEN: char, const CLOSE: char>(
     }
 }
 
+/// Describe the innermost error of the parser, with the characters expected at it.
+fn syntax_diagnostic(input: &Segment<'_>, error: VerboseError<Span<'_>>) -> Diagnostic {
+    let content: &str = &input.content;
+    let Some((position, kind)) = error.errors.first() else {
+        unreachable!("a parser error should have a location, may be a bug.");
+    };
+    let offset = position.location_offset();
+    let end = offset + content[offset..].chars().next().map_or(0, char::len_utf8);
+
+    let innermost_context = error.errors.iter().find_map(|(_, kind)| match kind {
+        VerboseErrorKind::Context(name) => Some(*name),
+        _ => None,
+    });
+    let what = match innermost_context {
+        Some("Tag") => "tag",
+        Some("TagHead") => "tag head",
+        Some("TagEnd") => "end tag",
+        Some("VoidTag") => "void tag",
+        _ => "text",
+    };
+    let message = match kind {
+        _ if offset == content.len() => format!("unexpected end of text in {}", what),
+        VerboseErrorKind::Nom(nom::error::ErrorKind::Verify) if what == "tag" => {
+            "the end tag does not match the tag".to_string()
+        }
+        _ => format!("invalid {}", what),
+    };
+
+    let mut expected = vec![];
+    for (position, kind) in &error.errors {
+        if let VerboseErrorKind::Char(ch) = kind
+            && position.location_offset() == offset
+        {
+            let token = format!("`{}`", ch);
+            if !expected.contains(&token) {
+                expected.push(token);
+            }
+        }
+    }
+
+    let mut diagnostic = Diagnostic::new(
+        Severity::Error,
+        DiagnosticCode::Syntax,
+        SourceRange {
+            segment_id: input.id.clone(),
+            start: offset,
+            end,
+        },
+        content,
+        message,
+    );
+    diagnostic.expected = expected;
+    diagnostic
+}
+
 /// Parse input with default square bracket tags `[]`.
 ///
 /// This is equivalent to calling `parse_with::<'[', ']'>(input)`.
-pub fn parse(input: &Segment<'_>) -> Result<Vec<Element>, String> {
+pub fn parse(input: &Segment<'_>) -> Result<Vec<Element>, ParseError> {
     parse_with::<'[', ']'>(input)
 }
+
+/// Parse input with default square bracket tags `[]`, recovering from malformed markup.
+///
+/// This is equivalent to calling `parse_lenient_with::<'[', ']'>(input)`.
+pub fn parse_lenient(input: &Segment<'_>) -> (Vec<Element>, Vec<Diagnostic>) {
+    parse_lenient_with::<'[', ']'>(input)
+}
+
+/// Parse input with custom tag symbols, recovering from malformed markup.
+///
+/// This is equivalent to calling `parse_lenient_with_config` with
+/// `ParserConfig::with_delimiters`.
+pub fn parse_lenient_with<const OPEN: char, const CLOSE: char>(
+    input: &Segment<'_>,
+) -> (Vec<Element>, Vec<Diagnostic>) {
+    parse_lenient_with_config(input, &ParserConfig::with_delimiters(OPEN, CLOSE))
+}
+
+/// Parse input with the config, recovering from malformed markup rather than failing,
+/// with the diagnostics of the recovered markup.
+///
+/// - A tag which is not closed is closed at the end of the segment, or at the end tag of
+///   a tag which contains it, such as `[b]` in `[i][b]text[/i]`.
+/// - An end tag without a tag, such as `[/b]` in `text[/b]`, is treated as text.
+/// - A tag character which starts no tag, such as `]` in `a]b`, is treated as text.
+///
+/// Well-formed input is parsed to the same elements as `parse_with_config`.
+pub fn parse_lenient_with_config(
+    input: &Segment<'_>,
+    config: &ParserConfig,
+) -> (Vec<Element>, Vec<Diagnostic>) {
+    match parse_with_config(input, config) {
+        Ok(elements) => (elements, vec![]),
+        Err(_) => recover(input, config),
+    }
+}
+
+/// A tag which is opened but not closed yet, while recovering.
+struct OpenTag {
+    start: usize,
+    head_end: usize,
+    head: TagHead,
+    inner: Vec<Element>,
+}
+
+impl OpenTag {
+    fn close(self, end: usize, segment_id: Option<SegmentId>) -> Element {
+        Element::Block {
+            start: self.start,
+            end,
+            inner: self.inner,
+            tag: self.head.key,
+            value: self.head.value,
+            attributes: self.head.attributes,
+            segment_id,
+        }
+    }
+}
+
+/// Parse the elements one by one, matching the tags with their end tags by a stack.
+fn recover(input: &Segment<'_>, config: &ParserConfig) -> (Vec<Element>, Vec<Diagnostic>) {
+    let content: &str = &input.content;
+    let segment_id = input.id.clone();
+    let error = |start: usize, end: usize, code: DiagnosticCode, message: String| {
+        let source_range = SourceRange {
+            segment_id: segment_id.clone(),
+            start,
+            end,
+        };
+        Diagnostic::new(Severity::Error, code, source_range, content, message)
+    };
+
+    let mut elements = vec![];
+    let mut stack: Vec<OpenTag> = vec![];
+    let mut diagnostics = vec![];
+    let mut input = Span::new_extra(content, segment_id.clone());
+
+    fn push(elements: &mut Vec<Element>, stack: &mut [OpenTag], element: Element) {
+        match stack.last_mut() {
+            Some(open_tag) => open_tag.inner.push(element),
+            None => elements.push(element),
+        }
+    }
+
+    while !input.fragment().is_empty() {
+        let start = input.location_offset();
+
+        if let Ok((remaining, text)) = plain_text(input.clone(), config) {
+            push(&mut elements, &mut stack, text);
+            input = remaining;
+        } else if let Ok((remaining, key)) = tag_end(input.clone(), config) {
+            let end = remaining.location_offset();
+            match stack.iter().rposition(|open_tag| open_tag.head.key == key) {
+                Some(index) => {
+                    // the tags opened after the matched tag are closed before its end tag
+                    while stack.len() > index + 1 {
+                        let open_tag = stack.pop().unwrap();
+                        diagnostics.push(error(
+                            open_tag.start,
+                            open_tag.head_end,
+                            DiagnosticCode::UnclosedTag,
+                            format!(
+                                "tag `{}` is closed by the end tag of its parent",
+                                open_tag.head.key
+                            ),
+                        ));
+                        let block = open_tag.close(start, segment_id.clone());
+                        push(&mut elements, &mut stack, block);
+                    }
+                    let block = stack.pop().unwrap().close(end, segment_id.clone());
+                    push(&mut elements, &mut stack, block);
+                }
+                None => {
+                    diagnostics.push(error(
+                        start,
+                        end,
+                        DiagnosticCode::UnmatchedEndTag,
+                        format!("end tag of `{}` without tag, treated as text", key),
+                    ));
+                    let text = Element::Text {
+                        start,
+                        end,
+                        content: content[start..end].to_string(),
+                        segment_id: segment_id.clone(),
+                    };
+                    push(&mut elements, &mut stack, text);
+                }
+            }
+            input = remaining;
+        } else if let Ok((remaining, head)) = tag_head(input.clone(), config) {
+            let end = remaining.location_offset();
+            if head.is_void || config.is_void_tag(&head.key) {
+                let void = Element::Void {
+                    start,
+                    end,
+                    tag: head.key,
+                    value: head.value,
+                    attributes: head.attributes,
+                    segment_id: segment_id.clone(),
+                };
+                push(&mut elements, &mut stack, void);
+            } else {
+                stack.push(OpenTag {
+                    start,
+                    head_end: end,
+                    head,
+                    inner: vec![],
+                });
+            }
+            input = remaining;
+        } else {
+            let ch = input.fragment().chars().next().unwrap();
+            let end = start + ch.len_utf8();
+            diagnostics.push(error(
+                start,
+                end,
+                DiagnosticCode::Syntax,
+                format!("`{}` starts no tag, treated as text", ch),
+            ));
+            let text = Element::Text {
+                start,
+                end,
+                content: ch.to_string(),
+                segment_id: segment_id.clone(),
+            };
+            push(&mut elements, &mut stack, text);
+            input = input.take_from(ch.len_utf8());
+        }
+    }
+
+    // the tags which are still open are closed at the end of the segment
+    while let Some(open_tag) = stack.pop() {
+        let mut diagnostic = error(
+            open_tag.start,
+            open_tag.head_end,
+            DiagnosticCode::UnclosedTag,
+            format!(
+                "tag `{}` is not closed, closed at the end of the text",
+                open_tag.head.key
+            ),
+        );
+        diagnostic.expected = vec![format!(
+            "`{}{}{}{}`",
+            config.open, config.slash, open_tag.head.key, config.close
+        )];
+        diagnostics.push(diagnostic);
+
+        let block = open_tag.close(content.len(), segment_id.clone());
+        push(&mut elements, &mut stack, block);
+    }
+
+    // the problems are reported in the order of the source
+    diagnostics.sort_by_key(|diagnostic| diagnostic.source_range.start);
+
+    (elements, diagnostics)
+}
@@ -0,0 +1,85 @@
+use crate::parser::VOID_TAGS;
+
+/// The characters of the tag syntax and the tags known to the parser.
+///
+/// Parsers with different configs can be used together in one program, e.g. game scripts
+/// with `【】` and UI strings with `[]`:
+///
+/// ```rust
+/// use huozi::parser::{ParserConfig, Segment, parse_with_config};
+///
+/// let script = ParserConfig {
+///     open: '【',
+///     close: '】',
+///     ..Default::default()
+/// };
+/// assert!(parse_with_config(&Segment::dummy("【b】text【/b】"), &script).is_ok());
+/// assert!(parse_with_config(&Segment::dummy("[b]text[/b]"), &ParserConfig::default()).is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParserConfig {
+    /// the character which opens a tag, `[` by default
+    pub open: char,
+    /// the character which closes a tag, `]` by default
+    pub close: char,
+    /// the character after `open` in an end tag, and before `close` in a void tag, `/` by default
+    pub slash: char,
+    /// the character before `open`, `close` or itself to write it as text, such as `\[`,
+    /// or `None` to write them by doubling `open` or `close`, such as `[[`, which is the default.
+    ///
+    /// An escape character must be escaped to be written as text, even if it is not followed by
+    /// `open` or `close`.
+    pub escape: Option<char>,
+    /// tags which are always void, and need no slash at the end of their heads,
+    /// `VOID_TAGS` by default
+    pub void_tags: Vec<String>,
+    /// whether `Huozi` recovers from malformed markup in segments as `parse_lenient_with_config`,
+    /// rather than failing the parsing. `false` by default, which keeps scripts validated strictly.
+    pub lenient: bool,
+}
+
+impl Default for ParserConfig {
+    fn default() -> Self {
+        Self {
+            open: '[',
+            close: ']',
+            slash: '/',
+            escape: None,
+            void_tags: VOID_TAGS.iter().map(|tag| tag.to_string()).collect(),
+            lenient: false,
+        }
+    }
+}
+
+impl ParserConfig {
+    /// The default config with custom open and close tag characters.
+    pub fn with_delimiters(open: char, close: char) -> Self {
+        Self {
+            open,
+            close,
+            ..Default::default()
+        }
+    }
+
+    /// Whether a tag is always void.
+    pub fn is_void_tag(&self, tag: &str) -> bool {
+        self.void_tags.iter().any(|void_tag| void_tag == tag)
+    }
+
+    /// The length of the escape before a character in the source, if the character must be
+    /// escaped to be written as text.
+    pub(crate) fn escape_len(&self, ch: char) -> Option<usize> {
+        match self.escape {
+            None if ch == self.open || ch == self.close => Some(ch.len_utf8()),
+            Some(escape) if ch == self.open || ch == self.close || ch == escape => {
+                Some(escape.len_utf8())
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether a character ends a text, which is a tag character or the escape character.
+    pub(crate) fn is_special(&self, ch: char) -> bool {
+        ch == self.open || ch == self.close || Some(ch) == self.escape
+    }
+}
@@ -0,0 +1,40 @@
+use std::{convert::Infallible, str::FromStr};
+
+/// How the annotation of a ruby is distributed over its base text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RubyMode {
+    /// The annotation is centered over the whole base text,
+    /// which is kept on the same line.
+    #[default]
+    Group,
+    /// Each annotation is centered over one character of the base text,
+    /// which may be broken between characters.
+    Mono,
+}
+
+/// A ruby annotation of a text run, e.g. furigana, pinyin or zhuyin.
+///
+/// It is parsed from the value of a `ruby` tag, where `|` separates the annotations of
+/// mono ruby, e.g. `[ruby=かん|じ]漢字[/ruby]`, otherwise it is group ruby,
+/// e.g. `[ruby=かんじ]漢字[/ruby]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Ruby {
+    /// the annotation of the base text for group ruby, or of each base character for mono ruby.
+    pub annotations: Vec<String>,
+    pub mode: RubyMode,
+}
+
+impl FromStr for Ruby {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let annotations = s.split('|').map(str::to_string).collect::<Vec<_>>();
+        let mode = if annotations.len() > 1 {
+            RubyMode::Mono
+        } else {
+            RubyMode::Group
+        };
+
+        Ok(Self { annotations, mode })
+    }
+}
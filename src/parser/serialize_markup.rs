@@ -0,0 +1,560 @@
+use std::fmt;
+
+use crate::parser::*;
+
+/// A reason why elements or text spans cannot be written as markup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SerializeError {
+    /// a tag or an attribute name which is empty, or has a character which ends a name,
+    /// such as a space or the close character
+    InvalidName(String),
+}
+
+impl fmt::Display for SerializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SerializeError::InvalidName(name) => {
+                write!(f, "name `{}` cannot be written as markup", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SerializeError {}
+
+type SerializeResult = Result<(), SerializeError>;
+
+/// A tag of a style, such as `[color=#ff0000]`, written around the runs with the style.
+type StyleTag = (&'static str, Option<String>);
+
+/// Write elements back to markup with the tag characters in the config.
+///
+/// The markup is canonical rather than a copy of the source: tag characters in texts are
+/// escaped, values are quoted only if they cannot be written bare, and void tags have a
+/// slash unless they are always void in the config. Parsing the markup gives the same
+/// elements except for their positions.
+///
+/// Any value can be written, but a tag or an attribute name which the parser would not read
+/// as a name, e.g. `a b`, fails the writing.
+pub fn elements_to_markup(
+    elements: &[Element],
+    config: &ParserConfig,
+) -> Result<String, SerializeError> {
+    let mut markup = String::new();
+    push_elements(&mut markup, elements, config)?;
+    Ok(markup)
+}
+
+/// Write text spans back to markup with the tag characters in the config, with the fewest
+/// tags to change `base_style` to the style of each run.
+///
+/// Runs in a row which share a style are wrapped in one tag, e.g. a red run and a red large run
+/// are written as `[color=#ff0000]a[size=40]b[/size][/color]`. Spans are wrapped in `span` tags
+/// if there are more than one. Style which cannot be set by tags is not written, e.g. removing
+/// the stroke of `base_style`. The names of markers fail the writing as in
+/// `elements_to_markup`.
+pub fn spans_to_markup(
+    spans: &[TextSpan],
+    base_style: &TextStyle,
+    config: &ParserConfig,
+) -> Result<String, SerializeError> {
+    let base_tags = style_tags(base_style);
+    let mut markup = String::new();
+
+    for span in spans {
+        let runs = span
+            .runs
+            .iter()
+            .map(|run| {
+                let mut tags = style_tags(&run.style);
+                tags.retain(|tag| !base_tags.contains(tag));
+                (tags, run)
+            })
+            .collect::<Vec<_>>();
+
+        if spans.len() > 1 {
+            push_head(&mut markup, "span", None, &[], false, config)?;
+            push_runs(&mut markup, &runs, &mut vec![], config)?;
+            push_end(&mut markup, "span", config);
+        } else {
+            push_runs(&mut markup, &runs, &mut vec![], config)?;
+        }
+    }
+
+    Ok(markup)
+}
+
+fn push_elements(
+    markup: &mut String,
+    elements: &[Element],
+    config: &ParserConfig,
+) -> SerializeResult {
+    for element in elements {
+        match element {
+            Element::Text { content, .. } => push_text(markup, content, config),
+            Element::Block {
+                inner,
+                tag,
+                value,
+                attributes,
+                ..
+            } => {
+                push_head(markup, tag, value.as_deref(), attributes, false, config)?;
+                push_elements(markup, inner, config)?;
+                push_end(markup, tag, config);
+            }
+            Element::Void {
+                tag,
+                value,
+                attributes,
+                ..
+            } => {
+                let is_void = !config.is_void_tag(tag);
+                push_head(markup, tag, value.as_deref(), attributes, is_void, config)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Write the runs, wrapping the runs in a row with the style tags which are not applied yet.
+fn push_runs(
+    markup: &mut String,
+    runs: &[(Vec<StyleTag>, &TextRun)],
+    applied: &mut Vec<StyleTag>,
+    config: &ParserConfig,
+) -> SerializeResult {
+    let mut index = 0;
+    while index < runs.len() {
+        let (tags, run) = &runs[index];
+        // the tag shared by the most runs in a row, or the first of them
+        let mut longest: Option<(&StyleTag, usize)> = None;
+        for tag in tags.iter().filter(|tag| !applied.contains(tag)) {
+            let end = index
+                + runs[index..]
+                    .iter()
+                    .take_while(|(tags, _)| tags.contains(tag))
+                    .count();
+            if longest.is_none_or(|(_, longest_end)| end > longest_end) {
+                longest = Some((tag, end));
+            }
+        }
+        let Some((tag, end)) = longest else {
+            push_run(markup, run, config)?;
+            index += 1;
+            continue;
+        };
+
+        push_head(markup, tag.0, tag.1.as_deref(), &[], false, config)?;
+        applied.push(tag.clone());
+        push_runs(markup, &runs[index..end], applied, config)?;
+        applied.pop();
+        push_end(markup, tag.0, config);
+
+        index = end;
+    }
+    Ok(())
+}
+
+fn push_run(markup: &mut String, run: &TextRun, config: &ParserConfig) -> SerializeResult {
+    if run.reveal_delay > 0. {
+        let delay = run.reveal_delay.to_string();
+        push_head(
+            markup,
+            "wait",
+            Some(&delay),
+            &[],
+            !config.is_void_tag("wait"),
+            config,
+        )?;
+    }
+
+    if let Some(marker) = &run.marker {
+        let is_void = !config.is_void_tag(&marker.tag);
+        push_head(
+            markup,
+            &marker.tag,
+            marker.value.as_deref(),
+            &marker.attributes,
+            is_void,
+            config,
+        )?;
+    } else if let Some(ruby) = &run.ruby {
+        push_head(
+            markup,
+            "ruby",
+            Some(&ruby.annotations.join("|")),
+            &[],
+            false,
+            config,
+        )?;
+        push_text(markup, &run.text, config);
+        push_end(markup, "ruby", config);
+    } else {
+        push_text(markup, &run.text, config);
+    }
+    Ok(())
+}
+
+/// The tags which set a style from the default style, in the order they are written.
+fn style_tags(style: &TextStyle) -> Vec<StyleTag> {
+    let mut tags = vec![];
+    if let Some(font_family) = &style.font_family {
+        tags.push(("font", Some(font_family.clone())));
+    }
+    tags.push(("size", Some(style.font_size.to_string())));
+    tags.push(("color", Some(style.fill_color.to_css_hex())));
+    tags.push(("lineHeight", Some(style.line_height.to_string())));
+    tags.push(("indent", Some(style.indent.to_string())));
+    if let Some(stroke) = &style.stroke {
+        let value = format!(
+            "{} {}",
+            stroke.stroke_color.to_css_hex(),
+            stroke.stroke_width
+        );
+        tags.push(("stroke", Some(value)));
+    }
+    if let Some(shadow) = &style.shadow {
+        let value = format!(
+            "{} {} {} {} {}",
+            shadow.shadow_offset_x,
+            shadow.shadow_offset_y,
+            shadow.shadow_blur,
+            shadow.shadow_color.to_css_hex(),
+            shadow.shadow_width
+        );
+        tags.push(("shadow", Some(value)));
+    }
+    tags.push(("speed", Some(style.reveal_speed.to_string())));
+    if style.reveal_instant {
+        tags.push(("instant", None));
+    }
+    tags
+}
+
+/// Write a text, escaping the tag characters in it.
+fn push_text(markup: &mut String, text: &str, config: &ParserConfig) {
+    for ch in text.chars() {
+        if config.escape_len(ch).is_some() {
+            markup.push(config.escape.unwrap_or(ch));
+        }
+        markup.push(ch);
+    }
+}
+
+fn push_head(
+    markup: &mut String,
+    tag: &str,
+    value: Option<&str>,
+    attributes: &[(String, String)],
+    is_void: bool,
+    config: &ParserConfig,
+) -> SerializeResult {
+    // a block tag may be nameless, such as `[]a[/]`, if there is nothing else in its head
+    let is_nameless_block = tag.is_empty() && value.is_none() && attributes.is_empty() && !is_void;
+    if !is_nameless_block {
+        check_name(tag, config)?;
+    }
+
+    markup.push(config.open);
+    markup.push_str(tag);
+    if let Some(value) = value {
+        markup.push('=');
+        push_value(markup, value, config);
+    }
+    for (key, value) in attributes {
+        check_name(key, config)?;
+        markup.push(' ');
+        markup.push_str(key);
+        markup.push('=');
+        push_value(markup, value, config);
+    }
+    if is_void {
+        markup.push(config.slash);
+    }
+    markup.push(config.close);
+    Ok(())
+}
+
+fn push_end(markup: &mut String, tag: &str, config: &ParserConfig) {
+    markup.push(config.open);
+    markup.push(config.slash);
+    markup.push_str(tag);
+    markup.push(config.close);
+}
+
+/// Whether a character ends a name or a bare value.
+fn is_delimiter(c: char, config: &ParserConfig) -> bool {
+    matches!(c, '"' | '\'' | '=' | ' ' | '\t' | '\n' | '\r')
+        || c == config.open
+        || c == config.close
+        || c == config.slash
+}
+
+fn check_name(name: &str, config: &ParserConfig) -> SerializeResult {
+    if name.is_empty() || name.chars().any(|c| is_delimiter(c, config)) {
+        return Err(SerializeError::InvalidName(name.to_string()));
+    }
+    Ok(())
+}
+
+/// Write a value bare if it can be, otherwise quoted with the quote which is not in it,
+/// or with double quotes in which double quotes are doubled if it has both quotes.
+fn push_value(markup: &mut String, value: &str, config: &ParserConfig) {
+    let is_bare = !value.is_empty() && !value.chars().any(|c| is_delimiter(c, config));
+    if is_bare {
+        markup.push_str(value);
+        return;
+    }
+
+    let quote = if value.contains('"') && !value.contains('\'') {
+        '\''
+    } else {
+        '"'
+    };
+    markup.push(quote);
+    for ch in value.chars() {
+        if ch == quote {
+            markup.push(quote);
+        }
+        markup.push(ch);
+    }
+    markup.push(quote);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::*;
+
+    fn round_trip(input: &str, config: &ParserConfig) -> String {
+        let elements = parse_with_config(&Segment::dummy(input), config).expect("Failed to parse");
+        let markup = elements_to_markup(&elements, config).unwrap();
+
+        // the positions differ, so the markup is compared by parsing it again
+        let reparsed =
+            parse_with_config(&Segment::dummy(&markup), config).expect("Failed to parse");
+        assert_eq!(
+            elements_to_markup(&reparsed, config).unwrap(),
+            markup,
+            "the markup is not stable"
+        );
+        markup
+    }
+
+    #[test]
+    fn test_elements_to_markup() {
+        let config = ParserConfig::default();
+        assert_eq!(
+            round_trip(
+                "a[[b]] [color = red size=40]c[wait=0.5][icon=coin/][/color]",
+                &config
+            ),
+            "a[[b]] [color=red size=40]c[wait=0.5][icon=coin/][/color]"
+        );
+        assert_eq!(
+            round_trip(
+                r#"[font="Source Han Sans"]a[/font][x='say "hi"' y="a/b"]b[/x]"#,
+                &config
+            ),
+            r#"[font="Source Han Sans"]a[/font][x='say "hi"' y="a/b"]b[/x]"#
+        );
+    }
+
+    /// The elements without their positions.
+    fn without_positions(elements: Vec<Element>) -> Vec<Element> {
+        elements
+            .into_iter()
+            .map(|element| match element {
+                Element::Text { content, .. } => Element::Text {
+                    start: 0,
+                    end: 0,
+                    content,
+                    segment_id: None,
+                },
+                Element::Block {
+                    inner,
+                    tag,
+                    value,
+                    attributes,
+                    ..
+                } => Element::Block {
+                    start: 0,
+                    end: 0,
+                    inner: without_positions(inner),
+                    tag,
+                    value,
+                    attributes,
+                    segment_id: None,
+                },
+                Element::Void {
+                    tag,
+                    value,
+                    attributes,
+                    ..
+                } => Element::Void {
+                    start: 0,
+                    end: 0,
+                    tag,
+                    value,
+                    attributes,
+                    segment_id: None,
+                },
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_values_round_trip() {
+        let text = |content: &str| Element::Text {
+            start: 0,
+            end: 0,
+            content: content.to_string(),
+            segment_id: None,
+        };
+        let values = [
+            r#"say "hi""#,
+            "it's",
+            r#"it's "this""#,
+            r#"''"""#,
+            "a]b[c/d=e",
+            "a b\nc",
+            "",
+        ];
+
+        for value in values {
+            let elements = vec![
+                Element::Block {
+                    start: 0,
+                    end: 0,
+                    inner: vec![text("a][")],
+                    tag: "x".to_string(),
+                    value: Some(value.to_string()),
+                    attributes: vec![("y".to_string(), value.to_string())],
+                    segment_id: None,
+                },
+                Element::Void {
+                    start: 0,
+                    end: 0,
+                    tag: "icon".to_string(),
+                    value: Some(value.to_string()),
+                    attributes: vec![],
+                    segment_id: None,
+                },
+            ];
+
+            for config in [
+                ParserConfig::default(),
+                ParserConfig {
+                    open: '<',
+                    close: '>',
+                    ..Default::default()
+                },
+            ] {
+                let markup = elements_to_markup(&elements, &config).unwrap();
+                let parsed = parse_with_config(&Segment::dummy(&markup), &config)
+                    .unwrap_or_else(|error| panic!("{} in `{}`", error, markup));
+                assert_eq!(without_positions(parsed), elements, "in `{}`", markup);
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_names() {
+        let config = ParserConfig::default();
+        let void = |tag: &str, attributes: Vec<(String, String)>| Element::Void {
+            start: 0,
+            end: 0,
+            tag: tag.to_string(),
+            value: None,
+            attributes,
+            segment_id: None,
+        };
+
+        for name in ["a b", "a]", "a=b", "a/", "\"a\"", ""] {
+            assert_eq!(
+                elements_to_markup(&[void(name, vec![])], &config),
+                Err(SerializeError::InvalidName(name.to_string()))
+            );
+            assert_eq!(
+                elements_to_markup(
+                    &[void("icon", vec![(name.to_string(), "v".to_string())])],
+                    &config
+                ),
+                Err(SerializeError::InvalidName(name.to_string()))
+            );
+        }
+
+        // a block may be nameless if there is nothing else in its head
+        let nameless = Element::Block {
+            start: 0,
+            end: 0,
+            inner: vec![],
+            tag: String::new(),
+            value: None,
+            attributes: vec![],
+            segment_id: None,
+        };
+        assert_eq!(elements_to_markup(&[nameless], &config).unwrap(), "[][/]");
+    }
+
+    #[test]
+    fn test_elements_to_markup_with_config() {
+        let config = ParserConfig {
+            open: '【',
+            close: '】',
+            escape: Some('\\'),
+            ..Default::default()
+        };
+        assert_eq!(
+            round_trip(r"\【a\\\】【b】c【/b】", &config),
+            r"\【a\\\】【b】c【/b】"
+        );
+    }
+
+    #[test]
+    fn test_spans_to_markup() {
+        let config = ParserConfig::default();
+        let input = concat!(
+            "[color=red]a[size=40]b[/size][/color]",
+            "[wait=0.5]c[ruby=かん|じ]漢字[/ruby][icon=coin/]",
+            "[stroke=\"#fff 2\"][[d]][/stroke][instant]e[/instant]"
+        );
+        let elements = parse(&Segment::dummy(input)).expect("Failed to parse");
+        let spans = to_spans(elements, &TextStyle::default(), None).unwrap();
+
+        let markup = spans_to_markup(&spans, &TextStyle::default(), &config).unwrap();
+        assert_eq!(
+            markup,
+            concat!(
+                "[color=#ff0000]a[size=40]b[/size][/color]",
+                "[wait=0.5]c[ruby=かん|じ]漢字[/ruby][icon=coin/]",
+                "[stroke=\"#ffffff 2\"][[d]][/stroke][instant]e[/instant]"
+            )
+        );
+
+        // the style of the base is not written
+        let base_style = TextStyle {
+            font_size: 40.,
+            ..Default::default()
+        };
+        assert_eq!(
+            spans_to_markup(&spans, &base_style, &config).unwrap(),
+            concat!(
+                "[color=#ff0000][size=32]a[/size]b[/color]",
+                "[size=32][wait=0.5]c[ruby=かん|じ]漢字[/ruby][icon=coin/]",
+                "[stroke=\"#ffffff 2\"][[d]][/stroke][instant]e[/instant][/size]"
+            )
+        );
+    }
+
+    #[test]
+    fn test_multiple_spans_to_markup() {
+        let elements = parse(&Segment::dummy("[span]a[/span]b")).expect("Failed to parse");
+        let spans = to_spans(elements, &TextStyle::default(), None).unwrap();
+
+        assert_eq!(
+            spans_to_markup(&spans, &TextStyle::default(), &ParserConfig::default()).unwrap(),
+            "[span]a[/span][span]b[/span]"
+        );
+    }
+}
@@ -0,0 +1,377 @@
+use std::{collections::HashMap, fmt};
+
+use csscolorparser::Color;
+use serde::{Deserialize, Serialize};
+
+use crate::parser::{ShadowStyle, StrokeStyle, TextStyle};
+
+/// A named partial style used by the tag of its name, such as `[name]Alice[/name]`.
+///
+/// The fields which are set replace those of the style around the tag, and the others are
+/// kept, so a prefab which only sets `fill_color` keeps the size and the indent.
+/// A prefab may extend another prefab, whose fields are used where it sets none,
+/// down to the fields of `stroke` and `shadow`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct StylePrefab {
+    /// the name of the prefab this prefab extends.
+    pub extends: Option<String>,
+    pub font_family: Option<String>,
+    pub font_size: Option<f64>,
+    pub fill_color: Option<Color>,
+    pub line_height: Option<f64>,
+    pub indent: Option<f64>,
+    pub stroke: Option<StrokePrefab>,
+    pub shadow: Option<ShadowPrefab>,
+    pub reveal_speed: Option<f64>,
+    pub reveal_instant: Option<bool>,
+    /// start a new `TextSpan` for the text in the tag as `span` does, which is also the case
+    /// if a prefab it extends does.
+    pub new_span: bool,
+}
+
+impl StylePrefab {
+    /// Set the fields of the prefab on the style, ignoring `extends`.
+    pub fn apply(&self, style: &mut TextStyle) {
+        if let Some(font_family) = &self.font_family {
+            style.font_family = Some(font_family.clone());
+        }
+        if let Some(font_size) = self.font_size {
+            style.font_size = font_size;
+        }
+        if let Some(fill_color) = &self.fill_color {
+            style.fill_color = fill_color.clone();
+        }
+        if let Some(line_height) = self.line_height {
+            style.line_height = line_height;
+        }
+        if let Some(indent) = self.indent {
+            style.indent = indent;
+        }
+        if let Some(stroke) = &self.stroke {
+            stroke.apply(style.stroke.get_or_insert_with(StrokeStyle::default));
+        }
+        if let Some(shadow) = &self.shadow {
+            shadow.apply(style.shadow.get_or_insert_with(ShadowStyle::default));
+        }
+        if let Some(reveal_speed) = self.reveal_speed {
+            style.reveal_speed = reveal_speed;
+        }
+        if let Some(reveal_instant) = self.reveal_instant {
+            style.reveal_instant = reveal_instant;
+        }
+    }
+
+    /// The prefab with the fields of `base` where it sets none.
+    fn or(self, base: &StylePrefab) -> StylePrefab {
+        StylePrefab {
+            extends: base.extends.clone(),
+            font_family: self.font_family.or_else(|| base.font_family.clone()),
+            font_size: self.font_size.or(base.font_size),
+            fill_color: self.fill_color.or_else(|| base.fill_color.clone()),
+            line_height: self.line_height.or(base.line_height),
+            indent: self.indent.or(base.indent),
+            stroke: match (self.stroke, &base.stroke) {
+                (Some(stroke), Some(base)) => Some(stroke.or(base)),
+                (stroke, base) => stroke.or_else(|| base.clone()),
+            },
+            shadow: match (self.shadow, &base.shadow) {
+                (Some(shadow), Some(base)) => Some(shadow.or(base)),
+                (shadow, base) => shadow.or_else(|| base.clone()),
+            },
+            reveal_speed: self.reveal_speed.or(base.reveal_speed),
+            reveal_instant: self.reveal_instant.or(base.reveal_instant),
+            new_span: self.new_span || base.new_span,
+        }
+    }
+}
+
+/// A prefab with every field of the style set, which replaces the whole style around the tag.
+impl From<TextStyle> for StylePrefab {
+    fn from(style: TextStyle) -> Self {
+        Self {
+            extends: None,
+            font_family: style.font_family,
+            font_size: Some(style.font_size),
+            fill_color: Some(style.fill_color),
+            line_height: Some(style.line_height),
+            indent: Some(style.indent),
+            stroke: style.stroke.map(StrokePrefab::from),
+            shadow: style.shadow.map(ShadowPrefab::from),
+            reveal_speed: Some(style.reveal_speed),
+            reveal_instant: Some(style.reveal_instant),
+            new_span: false,
+        }
+    }
+}
+
+/// The stroke of a prefab, whose fields which are set replace those of the stroke around the tag.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct StrokePrefab {
+    pub stroke_color: Option<Color>,
+    pub stroke_width: Option<f32>,
+}
+
+impl StrokePrefab {
+    fn apply(&self, stroke: &mut StrokeStyle) {
+        if let Some(stroke_color) = &self.stroke_color {
+            stroke.stroke_color = stroke_color.clone();
+        }
+        if let Some(stroke_width) = self.stroke_width {
+            stroke.stroke_width = stroke_width;
+        }
+    }
+
+    fn or(self, base: &StrokePrefab) -> StrokePrefab {
+        StrokePrefab {
+            stroke_color: self.stroke_color.or_else(|| base.stroke_color.clone()),
+            stroke_width: self.stroke_width.or(base.stroke_width),
+        }
+    }
+}
+
+impl From<StrokeStyle> for StrokePrefab {
+    fn from(stroke: StrokeStyle) -> Self {
+        Self {
+            stroke_color: Some(stroke.stroke_color),
+            stroke_width: Some(stroke.stroke_width),
+        }
+    }
+}
+
+/// The shadow of a prefab, whose fields which are set replace those of the shadow around the tag.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ShadowPrefab {
+    pub shadow_color: Option<Color>,
+    pub shadow_offset_x: Option<f32>,
+    pub shadow_offset_y: Option<f32>,
+    pub shadow_blur: Option<f32>,
+    pub shadow_width: Option<f32>,
+}
+
+impl ShadowPrefab {
+    fn apply(&self, shadow: &mut ShadowStyle) {
+        if let Some(shadow_color) = &self.shadow_color {
+            shadow.shadow_color = shadow_color.clone();
+        }
+        if let Some(shadow_offset_x) = self.shadow_offset_x {
+            shadow.shadow_offset_x = shadow_offset_x;
+        }
+        if let Some(shadow_offset_y) = self.shadow_offset_y {
+            shadow.shadow_offset_y = shadow_offset_y;
+        }
+        if let Some(shadow_blur) = self.shadow_blur {
+            shadow.shadow_blur = shadow_blur;
+        }
+        if let Some(shadow_width) = self.shadow_width {
+            shadow.shadow_width = shadow_width;
+        }
+    }
+
+    fn or(self, base: &ShadowPrefab) -> ShadowPrefab {
+        ShadowPrefab {
+            shadow_color: self.shadow_color.or_else(|| base.shadow_color.clone()),
+            shadow_offset_x: self.shadow_offset_x.or(base.shadow_offset_x),
+            shadow_offset_y: self.shadow_offset_y.or(base.shadow_offset_y),
+            shadow_blur: self.shadow_blur.or(base.shadow_blur),
+            shadow_width: self.shadow_width.or(base.shadow_width),
+        }
+    }
+}
+
+impl From<ShadowStyle> for ShadowPrefab {
+    fn from(shadow: ShadowStyle) -> Self {
+        Self {
+            shadow_color: Some(shadow.shadow_color),
+            shadow_offset_x: Some(shadow.shadow_offset_x),
+            shadow_offset_y: Some(shadow.shadow_offset_y),
+            shadow_blur: Some(shadow.shadow_blur),
+            shadow_width: Some(shadow.shadow_width),
+        }
+    }
+}
+
+/// A prefab which cannot be resolved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StylePrefabError {
+    /// there is no prefab of the name
+    Unknown(String),
+    /// a prefab extends a prefab which does not exist
+    UnknownBase { prefab: String, base: String },
+    /// the prefabs extend each other in a cycle, from the first prefab back to itself
+    Cycle(Vec<String>),
+}
+
+impl fmt::Display for StylePrefabError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StylePrefabError::Unknown(name) => write!(f, "unknown style prefab `{}`", name),
+            StylePrefabError::UnknownBase { prefab, base } => write!(
+                f,
+                "style prefab `{}` extends unknown prefab `{}`",
+                prefab, base
+            ),
+            StylePrefabError::Cycle(names) => {
+                let names = names
+                    .iter()
+                    .map(|name| format!("`{}`", name))
+                    .collect::<Vec<_>>();
+                write!(f, "style prefabs extend in a cycle: {}", names.join(" -> "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for StylePrefabError {}
+
+/// Resolve the prefab of the name with the prefabs it extends into a single prefab without
+/// `extends`.
+pub fn resolve_style_prefab(
+    prefabs: &HashMap<String, StylePrefab>,
+    name: &str,
+) -> Result<StylePrefab, StylePrefabError> {
+    let mut resolved = prefabs
+        .get(name)
+        .ok_or_else(|| StylePrefabError::Unknown(name.to_string()))?
+        .clone();
+    let mut chain = vec![name];
+
+    while let Some(base_name) = resolved.extends.take() {
+        if let Some(position) = chain.iter().position(|name| *name == base_name) {
+            let mut cycle = chain[position..]
+                .iter()
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>();
+            cycle.push(base_name);
+            return Err(StylePrefabError::Cycle(cycle));
+        }
+        let Some((base_name, base)) = prefabs.get_key_value(&base_name) else {
+            return Err(StylePrefabError::UnknownBase {
+                prefab: chain.last().unwrap().to_string(),
+                base: base_name,
+            });
+        };
+
+        chain.push(base_name);
+        resolved = resolved.or(base);
+    }
+
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prefab(extends: Option<&str>, font_size: Option<f64>, indent: Option<f64>) -> StylePrefab {
+        StylePrefab {
+            extends: extends.map(str::to_string),
+            font_size,
+            indent,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn resolve_extends_chain() {
+        let prefabs = HashMap::from([
+            ("base".to_string(), prefab(None, Some(20.), Some(1.))),
+            ("name".to_string(), prefab(Some("base"), None, Some(2.))),
+            ("hero".to_string(), prefab(Some("name"), Some(40.), None)),
+        ]);
+
+        let hero = resolve_style_prefab(&prefabs, "hero").unwrap();
+        assert_eq!(hero.font_size, Some(40.));
+        assert_eq!(hero.indent, Some(2.));
+        assert_eq!(hero.extends, None);
+
+        let mut style = TextStyle::default();
+        hero.apply(&mut style);
+        assert_eq!((style.font_size, style.indent), (40., 2.));
+        assert_eq!(style.line_height, TextStyle::default().line_height);
+    }
+
+    #[test]
+    fn resolve_stroke_and_shadow_by_field() {
+        let red = "#f00".parse::<Color>().unwrap();
+        let base = StylePrefab {
+            stroke: Some(StrokePrefab {
+                stroke_color: Some(red.clone()),
+                stroke_width: Some(2.),
+            }),
+            shadow: Some(ShadowPrefab {
+                shadow_blur: Some(4.),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let child = StylePrefab {
+            extends: Some("base".to_string()),
+            stroke: Some(StrokePrefab {
+                stroke_width: Some(5.),
+                ..Default::default()
+            }),
+            shadow: Some(ShadowPrefab {
+                shadow_offset_x: Some(3.),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let prefabs = HashMap::from([("base".to_string(), base), ("child".to_string(), child)]);
+
+        let child = resolve_style_prefab(&prefabs, "child").unwrap();
+        let stroke = child.stroke.as_ref().unwrap();
+        assert_eq!(stroke.stroke_color, Some(red.clone()));
+        assert_eq!(stroke.stroke_width, Some(5.));
+
+        // unset fields of the shadow are kept from the style around the tag
+        let mut style = TextStyle {
+            shadow: Some(ShadowStyle {
+                shadow_width: 1.,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        child.apply(&mut style);
+        let stroke = style.stroke.unwrap();
+        assert_eq!((stroke.stroke_color, stroke.stroke_width), (red, 5.));
+        let shadow = style.shadow.unwrap();
+        assert_eq!(
+            (
+                shadow.shadow_offset_x,
+                shadow.shadow_blur,
+                shadow.shadow_width
+            ),
+            (3., 4., 1.)
+        );
+        assert_eq!(
+            shadow.shadow_offset_y,
+            ShadowStyle::default().shadow_offset_y
+        );
+    }
+
+    #[test]
+    fn resolve_cycle_and_unknown_base() {
+        let prefabs = HashMap::from([
+            ("a".to_string(), prefab(Some("b"), None, None)),
+            ("b".to_string(), prefab(Some("c"), None, None)),
+            ("c".to_string(), prefab(Some("b"), None, None)),
+            ("d".to_string(), prefab(Some("missing"), None, None)),
+        ]);
+
+        assert_eq!(
+            resolve_style_prefab(&prefabs, "a").unwrap_err(),
+            StylePrefabError::Cycle(vec!["b".into(), "c".into(), "b".into()])
+        );
+        assert_eq!(
+            resolve_style_prefab(&prefabs, "d").unwrap_err().to_string(),
+            "style prefab `d` extends unknown prefab `missing`"
+        );
+        assert_eq!(
+            resolve_style_prefab(&prefabs, "e").unwrap_err(),
+            StylePrefabError::Unknown("e".into())
+        );
+    }
+}
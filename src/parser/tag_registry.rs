@@ -0,0 +1,242 @@
+use std::{collections::HashMap, str::FromStr};
+
+use crate::parser::{InlineMarker, ShadowStyle, StrokeStyle, TextStyle};
+
+/// What a tag handler can change at a tag.
+pub struct TagContext<'a> {
+    /// the style of the text in the tag, which is restored after the end tag.
+    /// Changes to the style at a void tag are discarded.
+    pub style: &'a mut TextStyle,
+    /// the pause in seconds before revealing the text after the tag
+    pub reveal_delay: &'a mut f64,
+    pub(crate) markers: &'a mut Vec<InlineMarker>,
+}
+
+impl TagContext<'_> {
+    /// Emit a marker at the position of the tag, e.g. to start an effect which is not a style.
+    pub fn emit_marker(&mut self, marker: InlineMarker) {
+        self.markers.push(marker);
+    }
+}
+
+/// A handler of a tag, which is called with the value of the tag, such as `red` in
+/// `[color=red]`, or of an attribute with the same name, such as `color=red` in
+/// `[style color=red]`.
+///
+/// It is implemented for closures, e.g.
+///
+/// ```rust
+/// use huozi::parser::{InlineMarker, TagContext, TagRegistry};
+///
+/// let mut registry = TagRegistry::default();
+/// registry.register("emotion", |context: &mut TagContext, value: Option<&str>| {
+///     match value {
+///         Some("angry") => {
+///             context.style.fill_color = "#c00".parse().unwrap();
+///             context.emit_marker(InlineMarker {
+///                 tag: "shake".to_string(),
+///                 ..Default::default()
+///             });
+///             Ok(())
+///         }
+///         _ => Err("unknown emotion".to_string()),
+///     }
+/// });
+/// ```
+pub trait TagHandler: Send + Sync {
+    /// Change the context by the value of the tag, or reject the value with the reason,
+    /// in which case the tag is ignored.
+    fn apply(&self, context: &mut TagContext, value: Option<&str>) -> Result<(), String>;
+}
+
+impl<F> TagHandler for F
+where
+    F: Fn(&mut TagContext, Option<&str>) -> Result<(), String> + Send + Sync,
+{
+    fn apply(&self, context: &mut TagContext, value: Option<&str>) -> Result<(), String> {
+        self(context, value)
+    }
+}
+
+/// The handlers of tags by their names.
+///
+/// The default registry has the handlers of the built-in tags, which can be replaced by
+/// registering a handler with the same name. The `span` and `ruby` tags change the structure
+/// of the text rather than its style, so they are not handled by the registry.
+///
+/// A tag without a value or attributes, such as `[size]`, is looked up in the style prefabs
+/// first, so a prefab is not shadowed by a handler with the same name. Other tags go to their
+/// handlers, and a tag without a handler or a prefab is treated as `span`, or as an inline
+/// marker if it is void.
+pub struct TagRegistry {
+    handlers: HashMap<String, Box<dyn TagHandler>>,
+}
+
+impl Default for TagRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register_builtin_tags();
+        registry
+    }
+}
+
+impl TagRegistry {
+    /// A registry without any handlers, even of the built-in tags.
+    pub fn empty() -> Self {
+        Self {
+            handlers: HashMap::new(),
+        }
+    }
+
+    /// Register the handler of a tag, replacing the previous handler of the same name.
+    pub fn register<S: Into<String>>(&mut self, name: S, handler: impl TagHandler + 'static) {
+        self.handlers.insert(name.into(), Box::new(handler));
+    }
+
+    /// Get the handler of a tag by its name.
+    pub fn get(&self, name: &str) -> Option<&dyn TagHandler> {
+        self.handlers.get(name).map(|handler| handler.as_ref())
+    }
+
+    fn register_builtin_tags(&mut self) {
+        self.register("font", font_family);
+        self.register("fontFamily", font_family);
+        self.register("size", font_size);
+        self.register("color", fill_color);
+        self.register("fillColor", fill_color);
+        self.register("lineHeight", line_height);
+        self.register("indent", indent);
+
+        self.register("stroke", stroke);
+        self.register("strokeColor", stroke_color);
+        self.register("strokeWidth", stroke_width);
+
+        self.register("shadow", shadow);
+        self.register("shadowOffsetX", shadow_offset_x);
+        self.register("shadowOffsetY", shadow_offset_y);
+        self.register("shadowWidth", shadow_width);
+        self.register("shadowBlur", shadow_blur);
+        self.register("shadowColor", shadow_color);
+
+        self.register("speed", reveal_speed);
+        self.register("instant", reveal_instant);
+        self.register("wait", wait);
+
+        // a tag which only has attributes, such as `[style color=red size=40]`
+        self.register("style", |_: &mut TagContext, _: Option<&str>| Ok(()));
+    }
+}
+
+type HandlerResult = Result<(), String>;
+
+fn font_family(context: &mut TagContext, value: Option<&str>) -> HandlerResult {
+    context.style.font_family = Some(required(value)?.to_string());
+    Ok(())
+}
+
+fn font_size(context: &mut TagContext, value: Option<&str>) -> HandlerResult {
+    context.style.font_size = parse_value(value)?;
+    Ok(())
+}
+
+fn fill_color(context: &mut TagContext, value: Option<&str>) -> HandlerResult {
+    context.style.fill_color = parse_value(value)?;
+    Ok(())
+}
+
+fn line_height(context: &mut TagContext, value: Option<&str>) -> HandlerResult {
+    context.style.line_height = parse_value(value)?;
+    Ok(())
+}
+
+fn indent(context: &mut TagContext, value: Option<&str>) -> HandlerResult {
+    context.style.indent = parse_value(value)?;
+    Ok(())
+}
+
+fn stroke(context: &mut TagContext, value: Option<&str>) -> HandlerResult {
+    context.style.stroke = Some(parse_value(value)?);
+    Ok(())
+}
+
+fn stroke_color(context: &mut TagContext, value: Option<&str>) -> HandlerResult {
+    stroke_style(context.style).stroke_color = parse_value(value)?;
+    Ok(())
+}
+
+fn stroke_width(context: &mut TagContext, value: Option<&str>) -> HandlerResult {
+    stroke_style(context.style).stroke_width = parse_value(value)?;
+    Ok(())
+}
+
+fn shadow(context: &mut TagContext, value: Option<&str>) -> HandlerResult {
+    context.style.shadow = Some(parse_value(value)?);
+    Ok(())
+}
+
+fn shadow_offset_x(context: &mut TagContext, value: Option<&str>) -> HandlerResult {
+    shadow_style(context.style).shadow_offset_x = parse_value(value)?;
+    Ok(())
+}
+
+fn shadow_offset_y(context: &mut TagContext, value: Option<&str>) -> HandlerResult {
+    shadow_style(context.style).shadow_offset_y = parse_value(value)?;
+    Ok(())
+}
+
+fn shadow_width(context: &mut TagContext, value: Option<&str>) -> HandlerResult {
+    shadow_style(context.style).shadow_width = parse_value(value)?;
+    Ok(())
+}
+
+fn shadow_blur(context: &mut TagContext, value: Option<&str>) -> HandlerResult {
+    shadow_style(context.style).shadow_blur = parse_value(value)?;
+    Ok(())
+}
+
+fn shadow_color(context: &mut TagContext, value: Option<&str>) -> HandlerResult {
+    shadow_style(context.style).shadow_color = parse_value(value)?;
+    Ok(())
+}
+
+fn reveal_speed(context: &mut TagContext, value: Option<&str>) -> HandlerResult {
+    let speed: f64 = parse_value(value)?;
+    if !(speed > 0. && speed.is_finite()) {
+        return Err("the reveal speed is not positive".to_string());
+    }
+    context.style.reveal_speed = speed;
+    Ok(())
+}
+
+fn reveal_instant(context: &mut TagContext, _: Option<&str>) -> HandlerResult {
+    context.style.reveal_instant = true;
+    Ok(())
+}
+
+fn wait(context: &mut TagContext, value: Option<&str>) -> HandlerResult {
+    *context.reveal_delay += parse_value::<f64>(value)?.max(0.);
+    Ok(())
+}
+
+fn required(value: Option<&str>) -> Result<&str, String> {
+    value.ok_or_else(|| "a value is required".to_string())
+}
+
+fn parse_value<T: FromStr>(value: Option<&str>) -> Result<T, String> {
+    let value = required(value)?;
+    value.parse::<T>().map_err(|_| {
+        format!(
+            "cannot parse string value `{}` to type `{}`",
+            value,
+            std::any::type_name::<T>()
+        )
+    })
+}
+
+fn stroke_style(style: &mut TextStyle) -> &mut StrokeStyle {
+    style.stroke.get_or_insert_with(StrokeStyle::default)
+}
+
+fn shadow_style(style: &mut TextStyle) -> &mut ShadowStyle {
+    style.shadow.get_or_insert_with(ShadowStyle::default)
+}
@@ -1,3 +1,5 @@
+use crate::parser::inline_marker::InlineMarker;
+use crate::parser::ruby::Ruby;
 use crate::parser::source_range::SourceRange;
 use crate::parser::text_style::TextStyle;
 
@@ -39,4 +41,32 @@ pub struct TextRun {
     pub text: String,
     pub style: TextStyle,
     pub source_range: SourceRange,
+    /// the ruby annotation over the text, which is the base text of the ruby.
+    pub ruby: Option<Ruby>,
+    /// anchors of `(offset in text, offset in source)` where the text stops being a verbatim copy
+    /// of the source from `source_range.start`, e.g. after an escaped tag character or between
+    /// the joined texts of a ruby. Empty if the whole text is copied verbatim.
+    pub source_map: Vec<(usize, usize)>,
+    /// the pause in seconds before revealing the text in a typewriter reveal, from `wait` tags.
+    pub reveal_delay: f64,
+    /// the marker from a void tag, for a run with empty text which stands at the position
+    /// of the tag in the run stream.
+    pub marker: Option<InlineMarker>,
+}
+
+impl TextRun {
+    /// The byte offset in the source segment of a byte offset in the text, which is at most
+    /// the end of `source_range`, e.g. for the value of a `var` tag.
+    pub fn source_offset(&self, offset: usize) -> usize {
+        let source_offset = match self
+            .source_map
+            .iter()
+            .rev()
+            .find(|(text_offset, _)| *text_offset <= offset)
+        {
+            Some((text_offset, source_offset)) => source_offset + offset - text_offset,
+            None => self.source_range.start + offset,
+        };
+        source_offset.min(self.source_range.end)
+    }
 }
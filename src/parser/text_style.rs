@@ -6,24 +6,32 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase", default)]
 pub struct TextStyle {
-    // pub font_face: Font
+    /// the name of a font family registered by `Huozi::register_font`, or `None` for the fallback chain.
+    pub font_family: Option<String>,
     pub font_size: f64,
     pub fill_color: Color,
     pub line_height: f64,
     pub indent: f64,
     pub stroke: Option<StrokeStyle>,
     pub shadow: Option<ShadowStyle>,
+    /// the multiplier of the typewriter reveal speed, e.g. 2 reveals characters twice as fast.
+    pub reveal_speed: f64,
+    /// reveal all characters at once in a typewriter reveal.
+    pub reveal_instant: bool,
 }
 
 impl Default for TextStyle {
     fn default() -> Self {
         Self {
+            font_family: None,
             font_size: 32.,
             fill_color: Color::new(0., 0., 0., 1.),
             line_height: 1.5,
             indent: 0.,
             stroke: None,
             shadow: None,
+            reveal_speed: 1.,
+            reveal_instant: false,
         }
     }
 }
@@ -0,0 +1,37 @@
+use std::collections::HashMap;
+
+/// A resolver of the variables in `[var=name]` tags, such as the name of the player.
+///
+/// The value of a variable is inserted as text with the style around the tag, so tag
+/// characters in it are shown as they are rather than parsed. It is implemented for maps
+/// and closures, e.g.
+///
+/// ```rust
+/// use std::collections::HashMap;
+/// use huozi::parser::VariableResolver;
+///
+/// let names = HashMap::from([("player_name".to_string(), "[Alice]".to_string())]);
+/// assert_eq!(names.resolve("player_name").as_deref(), Some("[Alice]"));
+///
+/// let count = |name: &str| (name == "coins").then(|| 42.to_string());
+/// assert_eq!(count.resolve("coins").as_deref(), Some("42"));
+/// ```
+pub trait VariableResolver: Send + Sync {
+    /// The value of a variable, or `None` if it is unknown, in which case nothing is inserted.
+    fn resolve(&self, name: &str) -> Option<String>;
+}
+
+impl<F> VariableResolver for F
+where
+    F: Fn(&str) -> Option<String> + Send + Sync,
+{
+    fn resolve(&self, name: &str) -> Option<String> {
+        self(name)
+    }
+}
+
+impl VariableResolver for HashMap<String, String> {
+    fn resolve(&self, name: &str) -> Option<String> {
+        self.get(name).cloned()
+    }
+}
@@ -1,4 +1,4 @@
-/**
+/*
  * This implementation is ported from https://github.com/mapbox/tiny-sdf by Mapbox, which is licensed under the BSD 2-Clause license.
  * It's based directly on the algorithm published in the Felzenszwalb/Huttenlocher paper,
  * and is not a port of the existing C++ implementation provided by the paper's authors.
@@ -41,7 +41,7 @@ impl TinySDF {
     }
     pub fn calculate(
         &mut self,
-        bitmap: &Vec<u8>,
+        bitmap: &[u8],
         glyph_width: u32,
         glyph_height: u32,
         grid_count: u32,
@@ -110,9 +110,9 @@ impl TinySDF {
 
         let mut data = vec![0; len];
 
-        for i in 0..len {
+        for (i, value) in data.iter_mut().enumerate() {
             let d = self.grid_outer[i].sqrt() - self.grid_inner[i].sqrt();
-            data[i] = (255. - 255. * (d / self.radius + self.cutoff))
+            *value = (255. - 255. * (d / self.radius + self.cutoff))
                 .round()
                 .clamp(0., 255.) as u8;
         }
@@ -122,16 +122,17 @@ impl TinySDF {
 }
 
 // 2D Euclidean squared distance transform by Felzenszwalb & Huttenlocher https://cs.brown.edu/~pff/papers/dt-final.pdf
+#[allow(clippy::too_many_arguments)]
 pub fn edt(
-    data: &mut Vec<f64>,
+    data: &mut [f64],
     x0: u32,
     y0: u32,
     width: u32,
     height: u32,
     grid_size: u32,
-    f: &mut Vec<f64>,
-    v: &mut Vec<u16>,
-    z: &mut Vec<f64>,
+    f: &mut [f64],
+    v: &mut [u16],
+    z: &mut [f64],
 ) {
     for x in x0..(x0 + width) {
         edt1d(
@@ -160,13 +161,13 @@ pub fn edt(
 
 // 1D squared distance transform
 pub fn edt1d(
-    grid: &mut Vec<f64>,
+    grid: &mut [f64],
     offset: usize,
     stride: usize,
     length: usize,
-    f: &mut Vec<f64>,
-    v: &mut Vec<u16>,
-    z: &mut Vec<f64>,
+    f: &mut [f64],
+    v: &mut [u16],
+    z: &mut [f64],
 ) {
     v[0] = 0;
     z[0] = -INF;
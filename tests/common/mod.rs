@@ -0,0 +1,409 @@
+//! Fonts for the integration tests, built in memory so that the tests do not depend on the
+//! git-LFS assets of the examples.
+//!
+//! The glyphs are plain boxes. `test_font` follows the vertical metrics of Source Han Sans
+//! (an `hhea` content area of 1160 / -288, `OS/2` typographic metrics of 880 / -120 and a `BASE`
+//! ideographic em box of the same extent), covers ASCII, Latin-1, kana, CJK symbols and the CJK
+//! Unified Ideographs block, and has `halt` and `vhal` features which halve full-width
+//! punctuation in horizontal and vertical lines, and a `vert` feature which substitutes the
+//! vertical forms `︵` and `︶` for `（` and `）`.
+//! `latin_font` covers ASCII only and has a taller content area.
+
+#![allow(dead_code)]
+
+use std::sync::OnceLock;
+
+/// A font covering Latin and CJK text.
+pub fn test_font() -> Vec<u8> {
+    static FONT: OnceLock<Vec<u8>> = OnceLock::new();
+    FONT.get_or_init(|| {
+        let chars = (' '..='~')
+            .chain('\u{A0}'..='\u{FF}')
+            .chain('\u{300}'..='\u{36F}')
+            .chain([
+                '\u{2014}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2026}',
+            ])
+            .chain('\u{3000}'..='\u{303F}')
+            .chain('\u{3041}'..='\u{30FF}')
+            .chain('\u{4E00}'..='\u{9FFF}')
+            .chain(['\u{FE35}', '\u{FE36}'])
+            .chain('\u{FF01}'..='\u{FF5E}');
+
+        FontBuilder {
+            hhea: (1160, -288, 0),
+            typo: (880, -120, 0),
+            ideographic_em_box: Some((880, -120)),
+        }
+        .build(chars.collect())
+    })
+    .clone()
+}
+
+/// A font covering ASCII only, whose content area differs from `test_font`.
+pub fn latin_font() -> Vec<u8> {
+    static FONT: OnceLock<Vec<u8>> = OnceLock::new();
+    FONT.get_or_init(|| {
+        FontBuilder {
+            hhea: (1100, -400, 0),
+            typo: (800, -200, 200),
+            ideographic_em_box: None,
+        }
+        .build((' '..='~').collect())
+    })
+    .clone()
+}
+
+const UNITS_PER_EM: u16 = 1000;
+
+/// The advance and the outline box `(x_min, y_min, x_max, y_max)` of a glyph, in font units.
+fn glyph_shape(ch: char) -> (u16, Option<(i16, i16, i16, i16)>) {
+    match ch {
+        ' ' | '\u{A0}' => (250, None),
+        '\u{3000}' => (1000, None),
+        // combining marks have no advance and sit above the base
+        '\u{300}'..='\u{36F}' => (0, Some((-350, 600, -150, 750))),
+        ch if is_full_width_punctuation(ch) => (1000, Some(punctuation_box(ch))),
+        '\u{3000}'..='\u{30FF}' | '\u{4E00}'..='\u{9FFF}' | '\u{FF01}'..='\u{FF5E}' => {
+            (1000, Some((60, -70, 940, 830)))
+        }
+        'i' | 'j' | 'l' | '.' | ',' | ':' | ';' | '\'' | '!' | '|' => {
+            (250, Some((60, 0, 190, 700)))
+        }
+        'm' | 'w' | 'M' | 'W' => (800, Some((50, 0, 750, 700))),
+        'g' | 'p' | 'q' | 'y' => (550, Some((50, -200, 500, 500))),
+        'a'..='z' => (550, Some((50, 0, 500, 500))),
+        _ => (550, Some((50, 0, 500, 700))),
+    }
+}
+
+/// The full-width punctuation whose glyph fills a part of the em box only.
+fn is_full_width_punctuation(ch: char) -> bool {
+    "、。，．：；！？「」『』（）《》〈〉【】〔〕“”‘’・…—～︵︶".contains(ch)
+}
+
+/// The outline box of full-width punctuation, on the side next to the text it belongs to.
+fn punctuation_box(ch: char) -> (i16, i16, i16, i16) {
+    match ch {
+        '、' | '。' | '，' | '．' | '：' | '；' | '！' | '？' => (100, -60, 350, 200),
+        '「' | '『' | '（' | '《' | '〈' | '【' | '〔' | '“' | '‘' => {
+            (600, -100, 900, 800)
+        }
+        '」' | '』' | '）' | '》' | '〉' | '】' | '〕' | '”' | '’' => {
+            (100, -100, 400, 800)
+        }
+        _ => (100, 300, 900, 400),
+    }
+}
+
+struct FontBuilder {
+    /// the ascender, descender and line gap of `hhea`
+    hhea: (i16, i16, i16),
+    /// the typographic ascender, descender and line gap of `OS/2`
+    typo: (i16, i16, i16),
+    /// the top and the bottom of the ideographic em box of `BASE`
+    ideographic_em_box: Option<(i16, i16)>,
+}
+
+impl FontBuilder {
+    /// Build a TrueType font whose glyph `i + 1` is `chars[i]`, and glyph 0 is `.notdef`.
+    fn build(&self, mut chars: Vec<char>) -> Vec<u8> {
+        chars.sort_unstable();
+        chars.dedup();
+
+        let shapes = std::iter::once((500, Some((50, 0, 450, 700))))
+            .chain(chars.iter().map(|&ch| glyph_shape(ch)))
+            .collect::<Vec<_>>();
+        let glyph_count = shapes.len() as u16;
+
+        let mut glyf = Writer::default();
+        let mut loca = Writer::default();
+        let mut hmtx = Writer::default();
+        for &(advance, outline) in &shapes {
+            loca.u32(glyf.len() as u32);
+            hmtx.u16(advance);
+            if let Some((x_min, y_min, x_max, y_max)) = outline {
+                hmtx.i16(x_min);
+                glyf.i16(1);
+                for value in [x_min, y_min, x_max, y_max] {
+                    glyf.i16(value);
+                }
+                // a single clockwise contour of four on-curve points
+                glyf.u16(3).u16(0);
+                glyf.bytes(&[1; 4]);
+                for dx in [x_min, 0, x_max - x_min, 0] {
+                    glyf.i16(dx);
+                }
+                for dy in [y_min, y_max - y_min, 0, y_min - y_max] {
+                    glyf.i16(dy);
+                }
+                glyf.align();
+            } else {
+                hmtx.i16(0);
+            }
+        }
+        loca.u32(glyf.len() as u32);
+
+        let advance_max = shapes.iter().map(|&(advance, _)| advance).max().unwrap();
+        let (ascender, descender, line_gap) = self.hhea;
+
+        let mut head = Writer::default();
+        head.u32(0x0001_0000)
+            .u32(0x0001_0000)
+            .u32(0)
+            .u32(0x5F0F_3CF5);
+        head.u16(0).u16(UNITS_PER_EM).u32(0).u32(0).u32(0).u32(0);
+        head.i16(-350).i16(-200).i16(1000).i16(900);
+        head.u16(0).u16(8).i16(2).i16(1).i16(0);
+
+        let mut hhea = Writer::default();
+        hhea.u32(0x0001_0000)
+            .i16(ascender)
+            .i16(descender)
+            .i16(line_gap);
+        hhea.u16(advance_max).i16(-350).i16(-350).i16(1000);
+        hhea.i16(1).i16(0).i16(0);
+        hhea.bytes(&[0; 8]).i16(0).u16(glyph_count);
+
+        let mut maxp = Writer::default();
+        maxp.u32(0x0001_0000).u16(glyph_count).u16(4).u16(1);
+        maxp.u16(0).u16(0).u16(2).bytes(&[0; 16]);
+
+        let mut os2 = Writer::default();
+        os2.u16(4).i16(500).u16(400).u16(5).u16(0);
+        os2.bytes(&[0; 20]).i16(0);
+        os2.bytes(&[0; 10]).bytes(&[0; 16]).bytes(b"NONE");
+        os2.u16(0x40).u16(0x20).u16(0xFFFF);
+        os2.i16(self.typo.0).i16(self.typo.1).i16(self.typo.2);
+        os2.u16(ascender as u16).u16(-descender as u16);
+        os2.u32(1).u32(0).i16(500).i16(700).u16(0).u16(32).u16(0);
+
+        let mut post = Writer::default();
+        post.u32(0x0003_0000).bytes(&[0; 28]);
+
+        let mut tables = vec![
+            (*b"cmap", cmap(&chars)),
+            (*b"glyf", glyf),
+            (*b"head", head),
+            (*b"hhea", hhea),
+            (*b"hmtx", hmtx),
+            (*b"loca", loca),
+            (*b"maxp", maxp),
+            (*b"OS/2", os2),
+            (*b"post", post),
+        ];
+        if let Some(em_box) = self.ideographic_em_box {
+            tables.push((*b"BASE", base(em_box)));
+        }
+        let glyph = |ch: char| chars.binary_search(&ch).ok().map(|i| i as u16 + 1);
+        let halves = chars
+            .iter()
+            .filter(|&&ch| is_full_width_punctuation(ch))
+            .filter_map(|&ch| glyph(ch))
+            .collect::<Vec<_>>();
+        if !halves.is_empty() {
+            tables.push((
+                *b"GPOS",
+                layout_table(vec![
+                    (*b"halt", 1, single_pos(&halves, 0x0004, -500)),
+                    (*b"vhal", 1, single_pos(&halves, 0x0008, -500)),
+                ]),
+            ));
+        }
+        let vertical_forms = [('（', '︵'), ('）', '︶')]
+            .into_iter()
+            .filter_map(|(ch, vertical)| glyph(ch).zip(glyph(vertical)))
+            .collect::<Vec<_>>();
+        if !vertical_forms.is_empty() {
+            tables.push((
+                *b"GSUB",
+                layout_table(vec![(*b"vert", 1, single_subst(&vertical_forms))]),
+            ));
+        }
+
+        assemble(tables)
+    }
+}
+
+/// A `cmap` table with a single format 12 subtable.
+fn cmap(chars: &[char]) -> Writer {
+    let mut groups: Vec<(u32, u32, u32)> = vec![];
+    for (i, &ch) in chars.iter().enumerate() {
+        let (code, glyph) = (ch as u32, i as u32 + 1);
+        match groups.last_mut() {
+            Some((start, end, start_glyph))
+                if *end + 1 == code && *start_glyph + (code - *start) == glyph =>
+            {
+                *end = code;
+            }
+            _ => groups.push((code, code, glyph)),
+        }
+    }
+
+    let mut cmap = Writer::default();
+    cmap.u16(0).u16(1).u16(3).u16(10).u32(12);
+    cmap.u16(12).u16(0);
+    cmap.u32(16 + 12 * groups.len() as u32)
+        .u32(0)
+        .u32(groups.len() as u32);
+    for (start, end, start_glyph) in groups {
+        cmap.u32(start).u32(end).u32(start_glyph);
+    }
+    cmap
+}
+
+/// A `BASE` table whose horizontal axis has the `ideo` and `idtp` baselines.
+fn base((top, bottom): (i16, i16)) -> Writer {
+    let mut base = Writer::default();
+    base.u32(0x0001_0000).u16(8).u16(0);
+    // Axis, at 8
+    base.u16(4).u16(14);
+    // BaseTagList, at 12
+    base.u16(2).bytes(b"ideo").bytes(b"idtp");
+    // BaseScriptList, at 22
+    base.u16(1).bytes(b"hani").u16(8);
+    // BaseScript, at 30
+    base.u16(6).u16(0).u16(0);
+    // BaseValues, at 36
+    base.u16(0).u16(2).u16(8).u16(12);
+    // BaseCoords
+    base.u16(1).i16(bottom).u16(1).i16(top);
+    base
+}
+
+/// A `GPOS` or `GSUB` table of the default script, with a feature of one lookup for each of
+/// `features`, given as the tag, the lookup type and the subtable.
+fn layout_table(features: Vec<([u8; 4], u16, Writer)>) -> Writer {
+    let count = features.len() as u16;
+    let feature_list = 28 + 2 * count;
+    let lookup_list = feature_list + 2 + 12 * count;
+
+    let mut table = Writer::default();
+    table
+        .u16(1)
+        .u16(0)
+        .u16(10)
+        .u16(feature_list)
+        .u16(lookup_list);
+    // ScriptList, at 10
+    table.u16(1).bytes(b"DFLT").u16(8);
+    // Script, at 18
+    table.u16(4).u16(0);
+    // LangSys, at 22
+    table.u16(0).u16(0xFFFF).u16(count);
+    for index in 0..count {
+        table.u16(index);
+    }
+    // FeatureList
+    table.u16(count);
+    for (index, (tag, _, _)) in (0..).zip(&features) {
+        table.bytes(tag).u16(2 + 6 * count + 6 * index);
+    }
+    for index in 0..count {
+        table.u16(0).u16(1).u16(index);
+    }
+    // LookupList
+    table.u16(count);
+    let mut offset = 2 + 2 * count;
+    for (_, _, subtable) in &features {
+        table.u16(offset);
+        offset += 8 + subtable.len() as u16;
+    }
+    for (_, lookup_type, subtable) in &features {
+        table.u16(*lookup_type).u16(0).u16(1).u16(8);
+        table.bytes(&subtable.0);
+    }
+    table
+}
+
+/// A SinglePos subtable which adjusts `glyphs` by a single value of `value_format`.
+fn single_pos(glyphs: &[u16], value_format: u16, value: i16) -> Writer {
+    let mut subtable = Writer::default();
+    subtable.u16(1).u16(8).u16(value_format).i16(value);
+    subtable.coverage(glyphs);
+    subtable
+}
+
+/// A SingleSubst subtable which substitutes each second glyph of `pairs` for the first.
+fn single_subst(pairs: &[(u16, u16)]) -> Writer {
+    let mut subtable = Writer::default();
+    let count = pairs.len() as u16;
+    subtable.u16(2).u16(6 + 2 * count).u16(count);
+    for &(_, substitute) in pairs {
+        subtable.u16(substitute);
+    }
+    subtable.coverage(&pairs.iter().map(|&(glyph, _)| glyph).collect::<Vec<_>>());
+    subtable
+}
+
+/// Lay out the tables after the table directory, sorted by tag.
+fn assemble(mut tables: Vec<([u8; 4], Writer)>) -> Vec<u8> {
+    tables.sort_by_key(|(tag, _)| *tag);
+
+    let mut font = Writer::default();
+    let count = tables.len() as u16;
+    let entry_selector = 15 - count.leading_zeros() as u16;
+    let search_range = (1 << entry_selector) * 16;
+    font.u32(0x0001_0000).u16(count).u16(search_range);
+    font.u16(entry_selector).u16(count * 16 - search_range);
+
+    let mut offset = 12 + 16 * tables.len();
+    for (tag, table) in &tables {
+        font.bytes(tag).u32(checksum(&table.0)).u32(offset as u32);
+        font.u32(table.len() as u32);
+        offset += table.len().next_multiple_of(4);
+    }
+    for (_, mut table) in tables {
+        table.align();
+        font.bytes(&table.0);
+    }
+    font.0
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+/// A big-endian byte buffer.
+#[derive(Default)]
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) -> &mut Self {
+        self.0.extend_from_slice(bytes);
+        self
+    }
+
+    fn u16(&mut self, value: u16) -> &mut Self {
+        self.bytes(&value.to_be_bytes())
+    }
+
+    fn i16(&mut self, value: i16) -> &mut Self {
+        self.bytes(&value.to_be_bytes())
+    }
+
+    fn u32(&mut self, value: u32) -> &mut Self {
+        self.bytes(&value.to_be_bytes())
+    }
+
+    /// A coverage table of sorted `glyphs`.
+    fn coverage(&mut self, glyphs: &[u16]) -> &mut Self {
+        self.u16(1).u16(glyphs.len() as u16);
+        for &glyph in glyphs {
+            self.u16(glyph);
+        }
+        self
+    }
+
+    /// Pad to a multiple of four bytes.
+    fn align(&mut self) {
+        self.0.resize(self.0.len().next_multiple_of(4), 0);
+    }
+}
@@ -0,0 +1,115 @@
+use huozi::{
+    Huozi, HuoziConfig,
+    glyph_vertices::GlyphVertices,
+    layout::{ColorSpace, LayoutResult, LayoutStyle, LineInfo},
+    parser::{Segment, TextStyle},
+};
+
+mod common;
+
+fn engine() -> Huozi {
+    Huozi::with_fonts(
+        vec![common::latin_font(), common::test_font()],
+        HuoziConfig::default(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn glyphs_come_from_the_first_font_containing_them() {
+    let mut huozi = engine();
+
+    assert_eq!(huozi.font_count(), 2);
+    assert_eq!(huozi.get_glyph('a').font_index, 0);
+    assert_eq!(huozi.get_glyph('中').font_index, 1);
+}
+
+#[test]
+fn missing_glyphs_fall_back_to_the_primary_font() {
+    let mut huozi = engine();
+
+    // a private use character which neither font contains
+    assert_eq!(huozi.get_glyph('\u{E000}').font_index, 0);
+}
+
+fn layout(text: &str) -> (Vec<GlyphVertices>, Vec<LineInfo>) {
+    let text_style = TextStyle {
+        font_size: 32.0,
+        line_height: 1.0,
+        ..Default::default()
+    };
+
+    let LayoutResult { glyphs, lines, .. } = engine()
+        .layout_plain(
+            &vec![Segment::dummy(text)],
+            &LayoutStyle::default(),
+            &text_style,
+            ColorSpace::SRGB,
+        )
+        .unwrap();
+
+    (glyphs, lines)
+}
+
+#[test]
+fn mixed_font_line_shares_baseline() {
+    // the baseline of each font in its own line box
+    let latin_baseline = layout("a").1[0].baseline;
+    let cjk_baseline = layout("中").1[0].baseline;
+    assert_ne!(latin_baseline, cjk_baseline);
+
+    let (glyphs, lines) = layout("a中");
+    let baseline = |glyph: &GlyphVertices, own_baseline: f32| {
+        glyph.y as f32 * glyph.scale_ratio + own_baseline
+    };
+
+    assert_eq!(glyphs.len(), 2);
+    assert_eq!(glyphs[0].row, glyphs[1].row);
+    assert!((baseline(&glyphs[0], latin_baseline) - lines[0].baseline).abs() <= 1.);
+    assert!((baseline(&glyphs[1], cjk_baseline) - lines[0].baseline).abs() <= 1.);
+}
+
+#[test]
+fn registered_font_family_is_preferred_then_falls_back() {
+    let mut huozi = Huozi::new(common::test_font());
+    let latin = huozi.register_font("latin", common::latin_font());
+
+    assert_eq!(huozi.font_family("latin"), Some(latin));
+    assert_eq!(huozi.font_family("unknown"), None);
+    assert_eq!(huozi.get_glyph('a').font_index, 0);
+    assert_eq!(huozi.get_font_glyph(Some(latin), 'a').font_index, latin);
+    assert_eq!(huozi.get_font_glyph(Some(latin), '中').font_index, 0);
+}
+
+#[test]
+fn fallback_glyph_is_cached_once_for_all_font_families() {
+    let mut huozi = Huozi::new(common::test_font());
+    let latin = huozi.register_font("latin", common::latin_font());
+
+    let u_min = huozi.get_glyph('中').u_min;
+    let version = huozi.image_version();
+
+    // `中` is not in the latin font, so it falls back to the same glyph in the primary font
+    assert_eq!(huozi.get_font_glyph(Some(latin), '中').u_min, u_min);
+    assert_eq!(huozi.image_version(), version);
+}
+
+#[test]
+fn font_tag_selects_registered_font_family() {
+    let mut huozi = Huozi::new(common::test_font());
+    huozi.register_font("latin", common::latin_font());
+
+    let text_style = TextStyle::default();
+    let (spans, _) = huozi
+        .parse_text(
+            &vec![Segment::dummy("[span]a[font=latin]a[/font][/span]")],
+            &text_style,
+            None,
+        )
+        .unwrap();
+    let LayoutResult { glyphs, .. } =
+        huozi.layout(&LayoutStyle::default(), &spans, ColorSpace::SRGB);
+
+    assert_eq!(glyphs.len(), 2);
+    assert_ne!(glyphs[0].fill[0].tex_coords, glyphs[1].fill[0].tex_coords);
+}
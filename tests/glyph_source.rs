@@ -0,0 +1,129 @@
+use huozi::{
+    Huozi,
+    glyph_vertices::GlyphVertices,
+    layout::{ColorSpace, LayoutResult, LayoutStyle},
+    parser::{DiagnosticCode, ParseError, Segment, SegmentId, SpanId, TextStyle, import_html},
+};
+
+mod common;
+
+fn layout(segments: Vec<Segment>) -> Vec<GlyphVertices> {
+    let (LayoutResult { glyphs, .. }, _) = Huozi::new(common::test_font())
+        .layout_parse(
+            &segments,
+            &LayoutStyle::default(),
+            &TextStyle::default(),
+            ColorSpace::SRGB,
+            None,
+        )
+        .unwrap();
+
+    glyphs
+}
+
+#[test]
+fn byte_ranges_point_into_the_escaped_source() {
+    let glyphs = layout(vec![Segment::dummy("[[中]]文")]);
+
+    let byte_ranges = glyphs
+        .iter()
+        .map(|glyph| glyph.byte_range.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(byte_ranges, [0..2, 2..5, 5..7, 7..10]);
+}
+
+#[test]
+fn plain_layout_fails_with_the_error_of_parsing() -> Result<(), ParseError> {
+    let mut huozi = Huozi::new(common::test_font());
+    let segments = vec![Segment::dummy("[b]中")];
+    let layout_style = LayoutStyle::default();
+    let text_style = TextStyle::default();
+
+    // markup is laid out as text without parsing
+    let plain = huozi.layout_plain(&segments, &layout_style, &text_style, ColorSpace::SRGB)?;
+    assert_eq!(plain.glyphs.len(), 4);
+
+    let parsed = huozi.layout_parse(
+        &segments,
+        &layout_style,
+        &text_style,
+        ColorSpace::SRGB,
+        None,
+    );
+    assert!(matches!(parsed, Err(ParseError::Syntax(_))));
+    Ok(())
+}
+
+#[test]
+fn glyphs_record_their_segments_spans_and_runs() {
+    let glyphs = layout(vec![
+        Segment {
+            id: Some(SegmentId::Lite(1)),
+            content: "[span]甲[color=#f00]乙[/color][/span]".into(),
+        },
+        Segment {
+            id: Some(SegmentId::Tag("second".to_string())),
+            content: "[span]丙[/span]".into(),
+        },
+    ]);
+
+    let sources = glyphs
+        .iter()
+        .map(|glyph| {
+            (
+                glyph.segment_id.clone(),
+                glyph.span_index,
+                glyph.run_index,
+                glyph.byte_range.clone(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        sources,
+        [
+            (Some(SegmentId::Lite(1)), 0, 0, 6..9),
+            (Some(SegmentId::Lite(1)), 0, 1, 21..24),
+            (Some(SegmentId::Tag("second".to_string())), 1, 0, 6..9),
+        ]
+    );
+    assert!(
+        glyphs
+            .iter()
+            .all(|glyph| glyph.span_id == Some(SpanId::Lite(0)))
+    );
+}
+
+#[test]
+fn ruby_annotations_point_to_their_base_text() {
+    let glyphs = layout(vec![Segment::dummy("[ruby=かんじ]漢字[/ruby]")]);
+
+    assert_eq!(glyphs.len(), 5);
+    assert_eq!(glyphs[0].byte_range, 16..19);
+    assert!(glyphs[2..].iter().all(|glyph| glyph.byte_range == (16..22)));
+}
+
+#[test]
+fn imported_elements_keep_their_source_ranges() {
+    let segment = Segment::dummy("<i>中</i><span style=\"color: red\">文</span>");
+    let text_style = TextStyle::default();
+    let (elements, mut diagnostics) = import_html(&segment, &text_style);
+
+    let mut huozi = Huozi::new(common::test_font());
+    let (LayoutResult { glyphs, .. }, layout_diagnostics) = huozi.layout_elements(
+        vec![(&segment, elements)],
+        &LayoutStyle::default(),
+        &text_style,
+        ColorSpace::SRGB,
+        None,
+    );
+    diagnostics.extend(layout_diagnostics);
+
+    let byte_ranges = glyphs
+        .iter()
+        .map(|glyph| glyph.byte_range.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(byte_ranges, [3..6, 35..38]);
+    assert_eq!(glyphs[1].fill[0].color, [1., 0., 0., 1.]);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, DiagnosticCode::Unsupported);
+}
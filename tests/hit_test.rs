@@ -0,0 +1,181 @@
+use huozi::{
+    Huozi,
+    glyph_vertices::GlyphVertices,
+    layout::{ColorSpace, HitTester, LayoutDirection, LayoutResult, LayoutStyle, LineInfo, Rect},
+    parser::{Segment, SegmentId, SourceRange, TextStyle},
+};
+
+mod common;
+
+type Layout = (Vec<GlyphVertices>, Vec<LineInfo>);
+
+fn layout(segments: Vec<Segment>, layout_style: &LayoutStyle) -> Layout {
+    let text_style = TextStyle {
+        font_size: 32.0,
+        line_height: 1.0,
+        ..Default::default()
+    };
+
+    let (LayoutResult { glyphs, lines, .. }, _) = Huozi::new(common::test_font())
+        .layout_parse(&segments, layout_style, &text_style, ColorSpace::SRGB, None)
+        .unwrap();
+
+    (glyphs, lines)
+}
+
+fn wrapped() -> Layout {
+    let layout_style = LayoutStyle {
+        box_width: Some(2. * 32.),
+        ..Default::default()
+    };
+    layout(vec![Segment::dummy("中文字")], &layout_style)
+}
+
+fn tester<'a>((glyphs, lines): &'a Layout, direction: &LayoutDirection) -> HitTester<'a> {
+    HitTester::new(glyphs, lines, direction)
+}
+
+#[test]
+fn points_hit_the_nearest_glyph() {
+    let layout = wrapped();
+    let tester = tester(&layout, &LayoutDirection::Horizontal);
+
+    let hit = tester.hit_test(40., 10.).unwrap();
+    assert_eq!(hit.glyph_index, 1);
+    assert_eq!((hit.source_range.start, hit.source_range.end), (3, 6));
+    assert!(!hit.trailing);
+    assert_eq!(hit.caret_offset(), 3);
+
+    let hit = tester.hit_test(60., 10.).unwrap();
+    assert!(hit.trailing);
+    assert_eq!(hit.caret_offset(), 6);
+
+    // points outside of the text hit the glyph at the end of the nearest line
+    let hit = tester.hit_test(200., 40.).unwrap();
+    assert_eq!(hit.glyph_index, 2);
+    assert!(hit.trailing);
+}
+
+#[test]
+fn glyphs_record_their_source_offsets_in_markup() {
+    let segments = vec![Segment {
+        id: Some(SegmentId::Lite(7)),
+        content: "中[color=#f00]文[/color]".into(),
+    }];
+    let layout = layout(segments, &LayoutStyle::default());
+    let tester = tester(&layout, &LayoutDirection::Horizontal);
+
+    let hit = tester.hit_test(40., 10.).unwrap();
+    assert_eq!(
+        hit.source_range,
+        SourceRange {
+            segment_id: Some(SegmentId::Lite(7)),
+            start: 15,
+            end: 18,
+        }
+    );
+}
+
+#[test]
+fn caret_moves_to_the_next_line_after_wrapping() {
+    let layout = wrapped();
+    let tester = tester(&layout, &LayoutDirection::Horizontal);
+
+    assert_eq!(
+        tester.caret_rect(None, 3),
+        Some(Rect {
+            x: 32.,
+            y: 0.,
+            width: 0.,
+            height: 32.,
+        })
+    );
+    // the start of the wrapped character is at the start of the next line
+    assert_eq!(
+        tester.caret_rect(None, 6),
+        Some(Rect {
+            x: 0.,
+            y: 32.,
+            width: 0.,
+            height: 32.,
+        })
+    );
+    // the end of text is after the last character
+    assert_eq!(tester.caret_rect(None, 9).map(|rect| rect.x), Some(32.));
+    assert_eq!(tester.caret_rect(None, 10), None);
+    assert_eq!(tester.caret_rect(Some(&SegmentId::Lite(1)), 3), None);
+}
+
+#[test]
+fn selection_covers_each_wrapped_line() {
+    let layout = wrapped();
+    let tester = tester(&layout, &LayoutDirection::Horizontal);
+
+    let rects = tester.selection_rects(&SourceRange {
+        segment_id: None,
+        start: 4,
+        end: 9,
+    });
+    assert_eq!(
+        rects,
+        [
+            Rect {
+                x: 32.,
+                y: 0.,
+                width: 32.,
+                height: 32.,
+            },
+            Rect {
+                x: 0.,
+                y: 32.,
+                width: 32.,
+                height: 32.,
+            },
+        ]
+    );
+}
+
+#[test]
+fn vertical_caret_lies_across_the_column() {
+    let layout_style = LayoutStyle {
+        direction: LayoutDirection::Vertical,
+        ..Default::default()
+    };
+    let layout = layout(vec![Segment::dummy("中文")], &layout_style);
+    let tester = tester(&layout, &LayoutDirection::Vertical);
+
+    assert_eq!(
+        tester.caret_rect(None, 3),
+        Some(Rect {
+            x: 0.,
+            y: 32.,
+            width: 32.,
+            height: 0.,
+        })
+    );
+    assert_eq!(tester.hit_test(16., 40.).unwrap().glyph_index, 1);
+}
+
+#[test]
+fn caret_lies_on_empty_lines() {
+    let layout = layout(vec![Segment::dummy("a\n\nb\n")], &LayoutStyle::default());
+    let tester = tester(&layout, &LayoutDirection::Horizontal);
+    let lines = &layout.1;
+    assert_eq!(lines.len(), 4);
+
+    // the break after `a` is on the first line
+    assert_eq!(tester.caret_rect(None, 1).map(|rect| rect.y), Some(0.));
+    // the empty lines between the breaks and at the end of the text
+    for (offset, line) in [(2, &lines[1]), (5, &lines[3])] {
+        let rect = tester.caret_rect(None, offset).unwrap();
+        assert_eq!((rect.x, rect.width), (0., 0.));
+        assert!((rect.y - (line.baseline - line.ascent)).abs() < 0.01);
+        assert!((rect.height - (line.ascent + line.descent)).abs() < 0.01);
+    }
+    assert_eq!(tester.caret_rect(None, 6), None);
+    // points on an empty line hit the nearest line with glyphs
+    assert_eq!(
+        tester.hit_test(0., lines[1].baseline).unwrap().glyph_index,
+        1
+    );
+}
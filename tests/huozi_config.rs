@@ -0,0 +1,153 @@
+use huozi::{
+    ConfigError, Huozi, HuoziConfig,
+    layout::{ColorSpace, LayoutResult, LayoutStyle},
+    parser::{Segment, TextStyle},
+};
+
+mod common;
+
+fn small_atlas() -> HuoziConfig {
+    HuoziConfig {
+        grid_size: 64.,
+        font_size: 48.,
+        texture_size: 1024,
+        buffer: 8.,
+        radius: 12.,
+        cutoff: 0.25,
+    }
+}
+
+#[test]
+fn default_config_matches_new() {
+    let huozi = Huozi::new(common::test_font());
+
+    assert_eq!(huozi.config(), &HuoziConfig::default());
+    assert_eq!(huozi.texture_image().dimensions(), (2048, 2048));
+}
+
+#[test]
+fn custom_config_drives_texture_size() {
+    let huozi = Huozi::with_config(common::test_font(), small_atlas()).unwrap();
+
+    assert_eq!(huozi.config(), &small_atlas());
+    assert_eq!(huozi.texture_image().dimensions(), (1024, 1024));
+    assert_eq!(small_atlas().cache_capacity(), 16 * 16 * 4);
+}
+
+#[test]
+fn layout_size_does_not_depend_on_atlas_parameters() {
+    let segments = vec![Segment::dummy("中文")];
+    let text_style = TextStyle {
+        font_size: 32.0,
+        line_height: 1.0,
+        ..Default::default()
+    };
+    let layout_style = LayoutStyle::default();
+
+    let LayoutResult {
+        width: default_width,
+        height: default_height,
+        ..
+    } = Huozi::new(common::test_font())
+        .layout_plain(&segments, &layout_style, &text_style, ColorSpace::SRGB)
+        .unwrap();
+    let LayoutResult {
+        glyphs,
+        width,
+        height,
+        ..
+    } = Huozi::with_config(common::test_font(), small_atlas())
+        .unwrap()
+        .layout_plain(&segments, &layout_style, &text_style, ColorSpace::SRGB)
+        .unwrap();
+
+    assert_eq!(glyphs.len(), 2);
+    assert_eq!(width, default_width);
+    assert_eq!(height, default_height);
+}
+
+#[test]
+fn glyphs_fill_grids_of_the_custom_size() {
+    let mut huozi = Huozi::with_config(common::test_font(), small_atlas()).unwrap();
+
+    let glyph = huozi.get_glyph('中');
+    assert_eq!(glyph.grid_count, 1);
+    assert!(((glyph.u_max - glyph.u_min) * 1024. - 64.).abs() < 0.01);
+    assert!(((glyph.v_max - glyph.v_min) * 1024. - 64.).abs() < 0.01);
+    // the glyph is rasterized in the atlas font size
+    assert!(glyph.metrics.height > 24 && glyph.metrics.height <= 48);
+}
+
+#[test]
+fn full_atlas_reuses_grids() {
+    let config = HuoziConfig {
+        grid_size: 32.,
+        font_size: 24.,
+        texture_size: 64,
+        buffer: 4.,
+        radius: 6.,
+        cutoff: 0.25,
+    };
+    assert_eq!(config.cache_capacity(), 2 * 2 * 4);
+    let mut huozi = Huozi::with_config(common::test_font(), config).unwrap();
+
+    for ch in "一二三四五六七八九十百千万亿兆京".chars().cycle().take(40) {
+        let glyph = huozi.get_glyph(ch);
+        assert!(glyph.page < 4 && glyph.index < 4);
+    }
+}
+
+#[test]
+fn invalid_configs_are_rejected() {
+    let invalid = [
+        HuoziConfig {
+            grid_size: 0.,
+            ..small_atlas()
+        },
+        HuoziConfig {
+            font_size: 0.,
+            ..small_atlas()
+        },
+        HuoziConfig {
+            radius: 0.,
+            ..small_atlas()
+        },
+        HuoziConfig {
+            cutoff: 1.5,
+            ..small_atlas()
+        },
+        HuoziConfig {
+            buffer: f64::NAN,
+            ..small_atlas()
+        },
+    ];
+    for config in invalid {
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+        assert!(Huozi::with_config(common::test_font(), config).is_err());
+    }
+
+    let large_font = HuoziConfig {
+        font_size: 64.,
+        ..small_atlas()
+    };
+    assert!(matches!(
+        large_font.validate(),
+        Err(ConfigError::GlyphExceedsGrid { .. })
+    ));
+
+    let small_texture = HuoziConfig {
+        texture_size: 32,
+        ..small_atlas()
+    };
+    assert_eq!(
+        small_texture.validate(),
+        Err(ConfigError::GridExceedsTexture {
+            grid_size: 64.,
+            texture_size: 32,
+        })
+    );
+    assert!(HuoziConfig::default().validate().is_ok());
+}
@@ -0,0 +1,129 @@
+use huozi::{
+    Huozi,
+    glyph_vertices::GlyphVertices,
+    layout::{
+        ColorSpace, KinsokuStyle, LayoutResult, LayoutStyle, LineBreakStrictness, PunctuationStyle,
+    },
+    parser::{Segment, TextStyle},
+};
+
+mod common;
+
+fn layout(text: &str, width_in_em: f64, kinsoku: KinsokuStyle) -> Vec<GlyphVertices> {
+    let layout_style = LayoutStyle {
+        box_width: Some(width_in_em * 32.0),
+        punctuation: PunctuationStyle {
+            compression: false,
+            hanging: false,
+            hanging_tolerance: 0.5,
+        },
+        kinsoku,
+        ..Default::default()
+    };
+    let text_style = TextStyle {
+        font_size: 32.0,
+        line_height: 1.0,
+        ..Default::default()
+    };
+
+    let LayoutResult { glyphs, .. } = Huozi::new(common::test_font())
+        .layout_plain(
+            &vec![Segment::dummy(text)],
+            &layout_style,
+            &text_style,
+            ColorSpace::SRGB,
+        )
+        .unwrap();
+
+    glyphs
+}
+
+fn rows(glyphs: &[GlyphVertices]) -> Vec<u32> {
+    glyphs.iter().map(|glyph| glyph.row).collect()
+}
+
+#[test]
+fn closing_punctuation_does_not_start_a_line() {
+    let glyphs = layout("中文字」", 3.0, KinsokuStyle::default());
+
+    // `字` is pushed out to the next line together with `」`
+    assert_eq!(rows(&glyphs), [0, 0, 1, 1]);
+    assert_eq!((glyphs[2].x, glyphs[2].col), (0, 0));
+    assert_eq!((glyphs[3].x, glyphs[3].col), (96, 1));
+}
+
+#[test]
+fn opening_bracket_does_not_end_a_line() {
+    let glyphs = layout("中文「字", 3.0, KinsokuStyle::default());
+
+    assert_eq!(rows(&glyphs), [0, 0, 1, 1]);
+    assert_eq!(glyphs[2].x, 0);
+}
+
+#[test]
+fn anywhere_breaks_before_the_overflowing_character() {
+    let kinsoku = KinsokuStyle {
+        strictness: LineBreakStrictness::Anywhere,
+        ..Default::default()
+    };
+    let glyphs = layout("中文字」", 3.0, kinsoku);
+
+    assert_eq!(rows(&glyphs), [0, 0, 0, 1]);
+}
+
+#[test]
+fn strict_keeps_small_kana_from_starting_a_line() {
+    let strict = KinsokuStyle {
+        strictness: LineBreakStrictness::Strict,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        rows(&layout("あいっ", 2.0, KinsokuStyle::default())),
+        [0, 0, 1]
+    );
+    assert_eq!(rows(&layout("あいっ", 2.0, strict)), [0, 1, 1]);
+}
+
+#[test]
+fn custom_prohibited_characters_replace_the_preset() {
+    let kinsoku = KinsokuStyle {
+        line_start_prohibited: Some("字".to_string()),
+        ..Default::default()
+    };
+
+    assert_eq!(rows(&layout("中文字", 2.0, kinsoku.clone())), [0, 1, 1]);
+    assert_eq!(rows(&layout("中文」", 2.0, kinsoku)), [0, 0, 1]);
+}
+
+#[test]
+fn unbreakable_line_breaks_before_the_overflowing_character() {
+    let glyphs = layout("「「「中", 3.0, KinsokuStyle::default());
+
+    assert_eq!(rows(&glyphs), [0, 0, 0, 1]);
+}
+
+#[test]
+fn pull_squeezes_punctuation_before_pushing_out() {
+    let kinsoku = KinsokuStyle {
+        pull: true,
+        ..Default::default()
+    };
+    let glyphs = layout("中，文。", 3.5, kinsoku);
+
+    // the blank of `，` is squeezed by half an em to pull in `。`
+    assert_eq!(rows(&glyphs), [0, 0, 0, 0]);
+    assert_eq!(glyphs[2].x, 144);
+    assert_eq!(glyphs[3].x + glyphs[3].width, 336);
+
+    // there is not enough blank to pull in `。` without `，`
+    let glyphs = layout(
+        "中中文。",
+        3.5,
+        KinsokuStyle {
+            pull: true,
+            ..Default::default()
+        },
+    );
+    assert_eq!(rows(&glyphs), [0, 0, 1, 1]);
+}
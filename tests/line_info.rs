@@ -0,0 +1,125 @@
+use huozi::{
+    Huozi,
+    layout::{
+        ColorSpace, LayoutDirection, LayoutResult, LayoutStyle, LineBreak, LineInfo,
+        PunctuationStyle,
+    },
+    parser::{Segment, TextStyle},
+};
+
+mod common;
+
+fn lines(text: &str, layout_style: &LayoutStyle) -> (Vec<LineInfo>, u32) {
+    let text_style = TextStyle {
+        font_size: 32.0,
+        line_height: 1.5,
+        ..Default::default()
+    };
+
+    let LayoutResult { lines, width, .. } = Huozi::new(common::test_font())
+        .layout_plain(
+            &vec![Segment::dummy(text)],
+            layout_style,
+            &text_style,
+            ColorSpace::SRGB,
+        )
+        .unwrap();
+
+    (lines, width)
+}
+
+#[test]
+fn lines_record_how_they_are_ended() {
+    let layout_style = LayoutStyle {
+        box_width: Some(2. * 32.),
+        ..Default::default()
+    };
+    let (lines, _) = lines("中文字\n\n中", &layout_style);
+
+    let summary = lines
+        .iter()
+        .map(|line| (line.glyph_range.clone(), line.line_break))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        summary,
+        [
+            (0..2, LineBreak::Wrap),
+            (2..3, LineBreak::Hard),
+            (3..3, LineBreak::Hard),
+            (3..4, LineBreak::End),
+        ]
+    );
+    for (line, width) in lines.iter().zip([64., 32., 0., 32.]) {
+        assert!((line.width - width).abs() < 0.01);
+    }
+}
+
+#[test]
+fn baselines_are_a_line_height_apart() {
+    let (lines, _) = lines("中\n\n文", &LayoutStyle::default());
+
+    assert_eq!(lines.len(), 3);
+    assert!((lines[1].baseline - lines[0].baseline - 48.).abs() < 0.01);
+    assert!((lines[2].baseline - lines[1].baseline - 48.).abs() < 0.01);
+    for line in &lines {
+        assert!(line.ascent > 0. && line.descent > 0.);
+        assert!(line.ascent + line.descent <= 48.);
+        assert!(line.baseline > line.ascent);
+    }
+}
+
+#[test]
+fn hanging_punctuation_is_reported_and_not_measured() {
+    let layout_style = LayoutStyle {
+        box_width: Some(2.5 * 32.),
+        punctuation: PunctuationStyle {
+            compression: false,
+            hanging: true,
+            hanging_tolerance: 0.5,
+        },
+        ..Default::default()
+    };
+    let (lines, _) = lines("中文。中", &layout_style);
+
+    assert_eq!(lines[0].glyph_range, 0..3);
+    assert!(lines[0].has_hanging_punctuation);
+    assert!((lines[0].width - 64.).abs() < 0.01);
+    assert!(!lines[1].has_hanging_punctuation);
+}
+
+#[test]
+fn trailing_spaces_stay_on_the_line_without_hanging() {
+    let layout_style = LayoutStyle {
+        box_width: Some(2. * 32.),
+        punctuation: PunctuationStyle {
+            compression: false,
+            hanging: true,
+            hanging_tolerance: 1.5,
+        },
+        ..Default::default()
+    };
+
+    let (wrapped_at_space, _) = lines("中文 中", &layout_style);
+    assert_eq!(wrapped_at_space[0].glyph_range, 0..3);
+    assert!(!wrapped_at_space[0].has_hanging_punctuation);
+    assert!((wrapped_at_space[0].width - 64.).abs() < 0.01);
+
+    // the space does not take the place of a punctuation mark hanging after it
+    let (hanging_after_space, _) = lines("中文 ，中", &layout_style);
+    assert_eq!(hanging_after_space[0].glyph_range, 0..4);
+    assert!(hanging_after_space[0].has_hanging_punctuation);
+    assert!((hanging_after_space[0].width - 64.).abs() < 0.01);
+}
+
+#[test]
+fn vertical_baselines_are_the_centers_of_columns() {
+    let layout_style = LayoutStyle {
+        direction: LayoutDirection::Vertical,
+        ..Default::default()
+    };
+    let (lines, width) = lines("中\n文", &layout_style);
+
+    assert_eq!(width, 96);
+    assert_eq!(lines[0].baseline, 72.);
+    assert_eq!(lines[1].baseline, 24.);
+}
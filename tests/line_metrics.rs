@@ -0,0 +1,58 @@
+use huozi::{
+    Huozi,
+    layout::{ColorSpace, LayoutResult, LayoutStyle, LineInfo, LineMetrics},
+    parser::{Segment, TextStyle},
+};
+
+mod common;
+
+fn first_line(line_metrics: LineMetrics) -> LineInfo {
+    let text_style = TextStyle {
+        font_size: 32.0,
+        line_height: 1.0,
+        ..Default::default()
+    };
+    let layout_style = LayoutStyle {
+        line_metrics,
+        ..Default::default()
+    };
+
+    let LayoutResult { lines, .. } = Huozi::new(common::test_font())
+        .layout_plain(
+            &vec![Segment::dummy("中文")],
+            &layout_style,
+            &text_style,
+            ColorSpace::SRGB,
+        )
+        .unwrap();
+
+    lines[0].clone()
+}
+
+#[test]
+fn ideographic_em_box_fills_a_solid_line() {
+    let line = first_line(LineMetrics::IdeographicEmBox);
+
+    // the em box of Source Han Sans is 880 above and 120 below the baseline
+    assert!((line.baseline - 0.88 * 32.).abs() < 0.5);
+    assert!((line.ascent + line.descent - 32.).abs() < 1.);
+}
+
+#[test]
+fn line_metrics_modes_move_the_baseline() {
+    let hhea = first_line(LineMetrics::Hhea);
+    let typo = first_line(LineMetrics::Typo);
+
+    // the content area of hhea is taller than the em box, and its baseline is lower
+    assert!(hhea.baseline > typo.baseline);
+    assert!(hhea.ascent + hhea.descent > typo.ascent + typo.descent);
+}
+
+#[test]
+fn default_line_metrics_are_hhea() {
+    assert_eq!(LayoutStyle::default().line_metrics, LineMetrics::Hhea);
+    assert_eq!(
+        first_line(LineMetrics::default()).baseline,
+        first_line(LineMetrics::Hhea).baseline
+    );
+}
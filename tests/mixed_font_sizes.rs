@@ -0,0 +1,85 @@
+use huozi::{
+    Huozi,
+    glyph_vertices::GlyphVertices,
+    layout::{ColorSpace, LayoutResult, LayoutStyle, LineInfo},
+    parser::{Segment, TextStyle},
+};
+
+mod common;
+
+fn layout(text: &str, layout_style: &LayoutStyle) -> (Vec<GlyphVertices>, Vec<LineInfo>, u32) {
+    let text_style = TextStyle {
+        font_size: 32.0,
+        line_height: 1.0,
+        ..Default::default()
+    };
+
+    let (
+        LayoutResult {
+            glyphs,
+            lines,
+            height,
+            ..
+        },
+        _,
+    ) = Huozi::new(common::test_font())
+        .layout_parse(
+            &vec![Segment::dummy(text)],
+            layout_style,
+            &text_style,
+            ColorSpace::SRGB,
+            None,
+        )
+        .unwrap();
+
+    (glyphs, lines, height)
+}
+
+/// the top of the line box of a glyph, in pixels
+fn top(glyph: &GlyphVertices) -> f32 {
+    glyph.y as f32 * glyph.scale_ratio
+}
+
+#[test]
+fn glyphs_of_different_sizes_share_the_baseline() {
+    let (glyphs, _, _) = layout(
+        "中[size=48]中[/size][size=64]中[/size]",
+        &LayoutStyle::default(),
+    );
+
+    // the same glyph is scaled around its baseline, so the tops of the glyph grids
+    // are on a line against the scale ratios if the baselines are the same
+    let grid_top = |glyph: &GlyphVertices| glyph.fill[0].position[1];
+    let slope = |a: &GlyphVertices, b: &GlyphVertices| {
+        (grid_top(b) - grid_top(a)) / (b.scale_ratio - a.scale_ratio)
+    };
+    assert!((slope(&glyphs[0], &glyphs[1]) - slope(&glyphs[1], &glyphs[2])).abs() < 0.5);
+
+    // smaller glyphs are moved down to the baseline of the largest one
+    assert!(top(&glyphs[0]) > top(&glyphs[1]));
+    assert_eq!(top(&glyphs[2]), 0.);
+}
+
+#[test]
+fn lines_advance_by_their_tallest_content() {
+    let (glyphs, lines, height) = layout("中\n[size=64]中[/size]\n中", &LayoutStyle::default());
+
+    assert_eq!(top(&glyphs[1]), 32.);
+    assert_eq!(top(&glyphs[2]), 32. + 64.);
+    assert_eq!(height, 32 + 64 + 32);
+    assert!(lines[1].baseline - lines[0].baseline > 32.);
+}
+
+#[test]
+fn wrapped_line_keeps_the_height_of_its_tallest_run() {
+    let layout_style = LayoutStyle {
+        box_width: Some(64. + 32. + 1.),
+        ..Default::default()
+    };
+    let (glyphs, _, height) = layout("[size=64]中[/size]中中", &layout_style);
+
+    // the line is wrapped in the small run, but is as tall as the large glyph
+    assert_eq!(glyphs[2].row, 1);
+    assert_eq!(top(&glyphs[2]), 64.);
+    assert_eq!(height, 64 + 32);
+}
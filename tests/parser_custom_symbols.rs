@@ -1,15 +1,12 @@
 // Integration tests for custom tag symbols in parser
 //
-// IMPORTANT: These tests are in a separate file because they use different
-// symbol combinations. Due to OnceLock caching optimization in the parser,
-// only ONE symbol combination should be used throughout a program's lifetime.
-//
 // This file uses Chinese corner brackets 【】 to demonstrate:
 // 1. Support for Unicode characters as tag symbols
 // 2. Support for non-ASCII symbols
 // 3. The custom symbol feature works correctly
+// 4. Different symbols can be used in the same program
 
-use huozi::parser::{parse_with, Element, Segment};
+use huozi::parser::{Element, ParserConfig, Segment, parse, parse_with, parse_with_config};
 
 #[test]
 fn unicode_brackets_basic() {
@@ -34,7 +31,9 @@ fn unicode_brackets_basic() {
                     segment_id: None,
                 }],
                 tag: "粗体".to_string(),
-                value: None
+                value: None,
+                attributes: vec![],
+                segment_id: None,
             }
         ]
     );
@@ -63,7 +62,9 @@ fn unicode_brackets_with_escape() {
                     segment_id: None,
                 }],
                 tag: "标签".to_string(),
-                value: None
+                value: None,
+                attributes: vec![],
+                segment_id: None,
             }
         ]
     );
@@ -102,7 +103,9 @@ fn unicode_brackets_nested() {
                             segment_id: None,
                         }],
                         tag: "内层".to_string(),
-                        value: None
+                        value: None,
+                        attributes: vec![],
+                        segment_id: None,
                     },
                     Element::Text {
                         start: 46,
@@ -112,7 +115,9 @@ fn unicode_brackets_nested() {
                     }
                 ],
                 tag: "外层".to_string(),
-                value: None
+                value: None,
+                attributes: vec![],
+                segment_id: None,
             }
         ]
     );
@@ -141,7 +146,9 @@ fn unicode_brackets_with_value() {
                     segment_id: None,
                 }],
                 tag: "颜色".to_string(),
-                value: Some("红色".to_string())
+                value: Some("红色".to_string()),
+                attributes: vec![],
+                segment_id: None,
             }
         ]
     );
@@ -170,7 +177,9 @@ fn unicode_brackets_with_quoted_value() {
                     segment_id: None,
                 }],
                 tag: "颜色".to_string(),
-                value: Some("红 色".to_string())
+                value: Some("红 色".to_string()),
+                attributes: vec![],
+                segment_id: None,
             }
         ]
     );
@@ -199,7 +208,9 @@ fn unicode_brackets_with_single_quoted_value() {
                     segment_id: None,
                 }],
                 tag: "颜色".to_string(),
-                value: Some("红 色".to_string())
+                value: Some("红 色".to_string()),
+                attributes: vec![],
+                segment_id: None,
             }
         ]
     );
@@ -228,7 +239,9 @@ fn unicode_brackets_mixed_content() {
                     segment_id: None,
                 }],
                 tag: "bold".to_string(),
-                value: None
+                value: None,
+                attributes: vec![],
+                segment_id: None,
             },
             Element::Text {
                 start: 33,
@@ -258,7 +271,9 @@ fn unicode_brackets_empty_tag() {
                 end: 32,
                 inner: vec![],
                 tag: "标签".to_string(),
-                value: None
+                value: None,
+                attributes: vec![],
+                segment_id: None,
             }
         ]
     );
@@ -280,7 +295,9 @@ fn unicode_brackets_multiline() {
                 segment_id: None,
             }],
             tag: "标签".to_string(),
-            value: None
+            value: None,
+            attributes: vec![],
+            segment_id: None,
         }]
     );
 }
@@ -299,3 +316,27 @@ fn unicode_brackets_quad_escape() {
         }]
     );
 }
+
+#[test]
+fn mixed_brackets_in_one_program() {
+    // script text with 【】 and UI text with [] are parsed in turn
+    let script = "【b】脚本【/b】[不是标签]";
+    let ui = "[b]界面[/b]【不是标签】";
+    for _ in 0..2 {
+        let script_elements = parse_with::<'【', '】'>(&Segment::dummy(script)).unwrap();
+        assert!(matches!(&script_elements[0], Element::Block { tag, .. } if tag == "b"));
+        assert!(
+            matches!(&script_elements[1], Element::Text { content, .. } if content == "[不是标签]")
+        );
+
+        let ui_elements = parse(&Segment::dummy(ui)).unwrap();
+        assert!(matches!(&ui_elements[0], Element::Block { tag, .. } if tag == "b"));
+        assert!(
+            matches!(&ui_elements[1], Element::Text { content, .. } if content == "【不是标签】")
+        );
+    }
+
+    let config = ParserConfig::with_delimiters('<', '>');
+    assert!(parse_with_config(&Segment::dummy("<b>a</b>"), &config).is_ok());
+    assert!(parse_with::<'【', '】'>(&Segment::dummy("<b>a</b>")).is_ok());
+}
@@ -1,13 +1,13 @@
 use huozi::{
     Huozi,
-    layout::{ColorSpace, LayoutStyle, PunctuationStyle},
+    layout::{ColorSpace, LayoutResult, LayoutStyle, PunctuationStyle},
     parser::{Segment, SegmentId, SourceRange, TextRun, TextSpan, TextStyle},
 };
 
-const TEST_FONT: &[u8] = include_bytes!("../examples/assets/SourceHanSansSC-Regular.otf");
+mod common;
 
 fn engine() -> Huozi {
-    Huozi::new(TEST_FONT.to_vec())
+    Huozi::new(common::test_font())
 }
 
 fn text_style() -> TextStyle {
@@ -37,7 +37,11 @@ fn punctuation_compression_is_opt_in() {
     let segments = vec![Segment::dummy("，。")];
     let text_style = text_style();
 
-    let (plain, _, plain_width, _) = engine()
+    let LayoutResult {
+        glyphs: plain,
+        width: plain_width,
+        ..
+    } = engine()
         .layout_plain(
             &segments,
             &layout_style(10.0),
@@ -48,7 +52,11 @@ fn punctuation_compression_is_opt_in() {
 
     let mut adjusted_style = layout_style(10.0);
     adjusted_style.punctuation.compression = true;
-    let (adjusted, _, adjusted_width, _) = engine()
+    let LayoutResult {
+        glyphs: adjusted,
+        width: adjusted_width,
+        ..
+    } = engine()
         .layout_plain(&segments, &adjusted_style, &text_style, ColorSpace::SRGB)
         .unwrap();
 
@@ -72,6 +80,10 @@ fn punctuation_compression_crosses_rich_text_runs_and_segments() {
                     start: 0,
                     end: 3,
                 },
+                ruby: None,
+                source_map: vec![],
+                reveal_delay: 0.,
+                marker: None,
             },
             TextRun {
                 text: "「".to_string(),
@@ -81,14 +93,22 @@ fn punctuation_compression_crosses_rich_text_runs_and_segments() {
                     start: 0,
                     end: 3,
                 },
+                ruby: None,
+                source_map: vec![],
+                reveal_delay: 0.,
+                marker: None,
             },
         ],
     }];
     let mut layout_style = layout_style(10.0);
     layout_style.punctuation.compression = true;
 
-    let (glyphs, segment_spans, total_width, _) =
-        engine().layout(&layout_style, &spans, ColorSpace::SRGB);
+    let LayoutResult {
+        glyphs,
+        segments: segment_spans,
+        width: total_width,
+        ..
+    } = engine().layout(&layout_style, &spans, ColorSpace::SRGB);
 
     assert_eq!(glyphs[1].x - glyphs[0].x, glyphs[0].width / 2);
     assert_eq!(segment_spans.len(), 2);
@@ -102,13 +122,19 @@ fn line_end_punctuation_hangs_by_at_most_half_an_em() {
     let segments = vec![Segment::dummy("中文。")];
     let text_style = text_style();
 
-    let (wrapped, _, _, _) = engine()
+    let LayoutResult {
+        glyphs: wrapped, ..
+    } = engine()
         .layout_plain(&segments, &layout_style(2.5), &text_style, ColorSpace::SRGB)
         .unwrap();
 
     let mut hanging_style = layout_style(2.5);
     hanging_style.punctuation.hanging = true;
-    let (hanging, _, total_width, _) = engine()
+    let LayoutResult {
+        glyphs: hanging,
+        width: total_width,
+        ..
+    } = engine()
         .layout_plain(&segments, &hanging_style, &text_style, ColorSpace::SRGB)
         .unwrap();
 
@@ -122,7 +148,11 @@ fn line_end_punctuation_hangs_by_at_most_half_an_em() {
 fn exact_fit_stays_on_line_and_repeated_punctuation_does_not_stack_hanging() {
     let text_style = text_style();
     let exact_fit = vec![Segment::dummy("中文")];
-    let (glyphs, _, total_width, _) = engine()
+    let LayoutResult {
+        glyphs,
+        width: total_width,
+        ..
+    } = engine()
         .layout_plain(
             &exact_fit,
             &layout_style(2.0),
@@ -138,7 +168,7 @@ fn exact_fit_stays_on_line_and_repeated_punctuation_does_not_stack_hanging() {
     let mut adjusted_style = layout_style(2.5);
     adjusted_style.punctuation.compression = true;
     adjusted_style.punctuation.hanging = true;
-    let (glyphs, _, _, _) = engine()
+    let LayoutResult { glyphs, .. } = engine()
         .layout_plain(&repeated, &adjusted_style, &text_style, ColorSpace::SRGB)
         .unwrap();
 
@@ -0,0 +1,103 @@
+use huozi::{
+    Huozi,
+    glyph_vertices::GlyphVertices,
+    layout::{
+        ColorSpace, LayoutResult, LayoutStyle, RevealProgress, reveal_alpha, reveal_progress,
+    },
+    parser::{Segment, TextStyle},
+};
+
+mod common;
+
+fn layout_result(text: &str) -> LayoutResult {
+    let layout_style = LayoutStyle {
+        reveal_interval: 0.1,
+        ..Default::default()
+    };
+
+    let (result, _) = Huozi::new(common::test_font())
+        .layout_parse(
+            &vec![Segment::dummy(text)],
+            &layout_style,
+            &TextStyle::default(),
+            ColorSpace::SRGB,
+            None,
+        )
+        .unwrap();
+
+    result
+}
+
+fn layout(text: &str) -> Vec<GlyphVertices> {
+    layout_result(text).glyphs
+}
+
+fn reveal_times(glyphs: &[GlyphVertices]) -> Vec<f32> {
+    glyphs
+        .iter()
+        .map(|glyph| (glyph.reveal_time * 1000.).round() / 1000.)
+        .collect()
+}
+
+#[test]
+fn glyphs_are_revealed_at_the_interval() {
+    let glyphs = layout("中文\n字");
+
+    // line breaks take no time
+    assert_eq!(reveal_times(&glyphs), [0., 0.1, 0.2]);
+}
+
+#[test]
+fn timing_tags_change_the_reveal_times() {
+    let glyphs = layout("甲[wait=0.5]乙[speed=2]丙丁[/speed][instant]戊己[/instant]庚");
+
+    assert_eq!(reveal_times(&glyphs), [0., 0.6, 0.7, 0.75, 0.8, 0.8, 0.8]);
+}
+
+#[test]
+fn pauses_at_the_end_count_in_the_reveal_duration() {
+    let plain = layout_result("abc");
+    let paused = layout_result("abc[wait=1]");
+
+    assert_eq!(reveal_times(&paused.glyphs), [0., 0.1, 0.2]);
+    assert!((plain.reveal_duration - 0.3).abs() < 1e-6);
+    assert!((paused.reveal_duration - 1.3).abs() < 1e-6);
+}
+
+#[test]
+fn ruby_annotations_are_revealed_with_their_base() {
+    let glyphs = layout("读[ruby=かんじ]漢字[/ruby]");
+
+    assert_eq!(glyphs.len(), 6);
+    assert!(
+        glyphs[3..]
+            .iter()
+            .all(|glyph| glyph.reveal_time == glyphs[1].reveal_time)
+    );
+}
+
+#[test]
+fn progress_counts_visible_glyphs_and_fades_the_frontier() {
+    let glyphs = layout("中文字");
+
+    assert_eq!(
+        reveal_progress(&glyphs, -1., 0.1),
+        RevealProgress {
+            visible_count: 0,
+            frontier_alpha: 0.,
+        }
+    );
+
+    let progress = reveal_progress(&glyphs, 0.15, 0.1);
+    assert_eq!(progress.visible_count, 2);
+    assert!((progress.frontier_alpha - 0.5).abs() < 1e-4);
+
+    assert_eq!(
+        reveal_progress(&glyphs, 1., 0.1),
+        RevealProgress {
+            visible_count: 3,
+            frontier_alpha: 1.,
+        }
+    );
+    assert_eq!(reveal_alpha(&glyphs[2], 0.2, 0.), 1.);
+}
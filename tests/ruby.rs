@@ -0,0 +1,158 @@
+use huozi::{
+    Huozi,
+    glyph_vertices::GlyphVertices,
+    layout::{ColorSpace, LayoutDirection, LayoutResult, LayoutStyle},
+    parser::{Segment, TextStyle},
+};
+
+mod common;
+
+fn layout(text: &str, layout_style: &LayoutStyle) -> (Vec<GlyphVertices>, u32, u32) {
+    let text_style = TextStyle {
+        font_size: 32.0,
+        line_height: 1.0,
+        ..Default::default()
+    };
+
+    let (
+        LayoutResult {
+            glyphs,
+            width,
+            height,
+            ..
+        },
+        _,
+    ) = Huozi::new(common::test_font())
+        .layout_parse(
+            &vec![Segment::dummy(text)],
+            layout_style,
+            &text_style,
+            ColorSpace::SRGB,
+            None,
+        )
+        .unwrap();
+
+    (glyphs, width, height)
+}
+
+/// (start, end) of the glyphs on the screen, in pixels.
+fn extent(glyphs: &[GlyphVertices], horizontal: bool) -> (f64, f64) {
+    let start = |glyph: &GlyphVertices| {
+        let position = if horizontal { glyph.x } else { glyph.y };
+        position as f64 * glyph.scale_ratio as f64
+    };
+    let length = |glyph: &GlyphVertices| {
+        let length = if horizontal {
+            glyph.width
+        } else {
+            glyph.height
+        };
+        length as f64 * glyph.scale_ratio as f64
+    };
+
+    (
+        glyphs.iter().map(start).fold(f64::MAX, f64::min),
+        glyphs
+            .iter()
+            .map(|glyph| start(glyph) + length(glyph))
+            .fold(0., f64::max),
+    )
+}
+
+fn assert_centered(annotation: &[GlyphVertices], base: &[GlyphVertices], horizontal: bool) {
+    let (start, end) = extent(annotation, horizontal);
+    let (base_start, base_end) = extent(base, horizontal);
+    let center = (start + end) / 2.;
+    let base_center = (base_start + base_end) / 2.;
+    assert!(
+        (center - base_center).abs() <= 1.,
+        "annotation centered at {center}, base at {base_center}"
+    );
+}
+
+#[test]
+fn group_ruby_is_centered_over_the_base() {
+    let (glyphs, _, height) = layout("[ruby=かんじ]漢字[/ruby]", &LayoutStyle::default());
+
+    // annotation glyphs follow the base glyphs
+    assert_eq!(glyphs.len(), 5);
+    assert_centered(&glyphs[2..], &glyphs[..2], true);
+    assert!(glyphs[2..].iter().all(|glyph| glyph.row == 0));
+
+    // the line grows by the half-sized annotation
+    assert_eq!(height, 48);
+    assert_eq!(glyphs[0].y as f32 * glyphs[0].scale_ratio, 16.);
+    assert_eq!(glyphs[2].y, 0);
+    assert_eq!(glyphs[2].scale_ratio * 2., glyphs[0].scale_ratio);
+}
+
+#[test]
+fn mono_ruby_annotates_each_character() {
+    let (glyphs, _, _) = layout("读[ruby=hàn|zì]汉字[/ruby]", &LayoutStyle::default());
+
+    assert_eq!(glyphs.len(), 3 + 3 + 2);
+    assert_centered(&glyphs[3..6], &glyphs[1..2], true);
+    assert_centered(&glyphs[6..], &glyphs[2..3], true);
+}
+
+#[test]
+fn mono_ruby_falls_back_to_group_on_mismatched_annotations() {
+    let (glyphs, _, _) = layout("[ruby=a|b|c]汉字[/ruby]", &LayoutStyle::default());
+
+    assert_eq!(glyphs.len(), 5);
+    assert_centered(&glyphs[2..], &glyphs[..2], true);
+}
+
+#[test]
+fn group_ruby_base_is_not_broken_and_only_its_line_grows() {
+    let layout_style = LayoutStyle {
+        box_width: Some(3. * 32.),
+        ..Default::default()
+    };
+    let (glyphs, _, height) = layout("中文[ruby=かんじ]漢字[/ruby]", &layout_style);
+
+    let rows = glyphs[..4]
+        .iter()
+        .map(|glyph| glyph.row)
+        .collect::<Vec<_>>();
+    assert_eq!(rows, [0, 0, 1, 1]);
+    assert_eq!(glyphs[0].y, 0);
+    assert_eq!(glyphs[2].y as f32 * glyphs[2].scale_ratio, 32. + 16.);
+    assert_eq!(height, 32 + 16 + 32);
+    assert!(glyphs[4..].iter().all(|glyph| glyph.row == 1));
+}
+
+#[test]
+fn mono_ruby_base_may_be_broken() {
+    let layout_style = LayoutStyle {
+        box_width: Some(3. * 32.),
+        ..Default::default()
+    };
+    let (glyphs, _, height) = layout("中文[ruby=hàn|zì]汉字[/ruby]", &layout_style);
+
+    let rows = glyphs[..4]
+        .iter()
+        .map(|glyph| glyph.row)
+        .collect::<Vec<_>>();
+    assert_eq!(rows, [0, 0, 0, 1]);
+    assert_eq!(height, 16 + 32 + 16 + 32);
+}
+
+#[test]
+fn vertical_ruby_is_beside_the_base_column() {
+    let layout_style = LayoutStyle {
+        direction: LayoutDirection::Vertical,
+        ..Default::default()
+    };
+    let (glyphs, width, _) = layout("[ruby=かんじ]漢字[/ruby]", &layout_style);
+
+    assert_eq!(glyphs.len(), 5);
+    assert_eq!(width, 48);
+    assert_centered(&glyphs[2..], &glyphs[..2], false);
+
+    // the annotation is on the right of the column
+    let (base_left, _) = extent(&glyphs[..2], true);
+    let (annotation_left, _) = extent(&glyphs[2..], true);
+    assert_eq!(base_left, 0.);
+    assert!((annotation_left - 32.).abs() < 0.01);
+}
@@ -0,0 +1,127 @@
+#![cfg(feature = "shaping")]
+
+use huozi::{
+    Huozi,
+    layout::{
+        ColorSpace, KinsokuStyle, LayoutDirection, LayoutResult, LayoutStyle, PunctuationStyle,
+    },
+    parser::{Segment, TextStyle},
+};
+
+mod common;
+
+fn layout(text: &str, font_features: &[&str]) -> Vec<huozi::glyph_vertices::GlyphVertices> {
+    layout_with(
+        text,
+        LayoutStyle {
+            font_features: font_features.iter().map(|f| f.to_string()).collect(),
+            ..style()
+        },
+    )
+}
+
+fn style() -> LayoutStyle {
+    LayoutStyle {
+        punctuation: PunctuationStyle {
+            compression: false,
+            hanging: false,
+            hanging_tolerance: 0.5,
+        },
+        ..Default::default()
+    }
+}
+
+fn layout_with(text: &str, layout_style: LayoutStyle) -> Vec<huozi::glyph_vertices::GlyphVertices> {
+    let LayoutResult { glyphs, .. } = Huozi::new(common::test_font())
+        .layout_plain(
+            &vec![Segment::dummy(text)],
+            &layout_style,
+            &TextStyle::default(),
+            ColorSpace::SRGB,
+        )
+        .unwrap();
+
+    glyphs
+}
+
+#[test]
+fn combining_marks_are_shaped_into_one_glyph() {
+    let glyphs = layout("e\u{301}", &[]);
+
+    assert_eq!(glyphs.len(), 1);
+}
+
+#[test]
+fn font_features_apply_to_shaped_advances() {
+    let full_width = layout("，中", &[]);
+    let half_width = layout("，中", &["halt"]);
+
+    assert_eq!(full_width[0].width, 96);
+    assert_eq!(half_width[0].width, 48);
+    assert_eq!(half_width[1].x, 48);
+}
+
+#[test]
+fn line_breaks_are_kept_between_shaped_items() {
+    let glyphs = layout("中\n文", &[]);
+
+    assert_eq!(glyphs.len(), 2);
+    assert_eq!(glyphs[0].row, 0);
+    assert_eq!(glyphs[1].row, 1);
+}
+
+#[test]
+fn right_to_left_clusters_do_not_overlap() {
+    // Hebrew is shaped right to left, so the glyphs come in the reverse order of the text
+    let glyphs = layout("\u{5d0}\u{5d1}\u{5d2}", &[]);
+    let mut byte_ranges = glyphs
+        .iter()
+        .map(|glyph| glyph.byte_range.clone())
+        .collect::<Vec<_>>();
+    byte_ranges.sort_by_key(|range| range.start);
+
+    assert_eq!(byte_ranges, [0..2, 2..4, 4..6]);
+}
+
+#[test]
+fn vertical_text_is_shaped_with_vertical_features() {
+    let vertical = |font_features: &[&str]| LayoutStyle {
+        direction: LayoutDirection::Vertical,
+        font_features: font_features.iter().map(|f| f.to_string()).collect(),
+        ..style()
+    };
+    let full_height = layout_with("。中", vertical(&[]));
+    let half_height = layout_with("。中", vertical(&["vhal"]));
+
+    assert_eq!(full_height[1].y, 96);
+    assert_eq!(half_height[0].height, 48);
+    assert_eq!(half_height[1].y, 48);
+
+    // `vert` substitutes an upright vertical form for the bracket, which is rotated otherwise
+    let glyphs = layout_with("（中）", vertical(&[]));
+    for glyph in [&glyphs[0], &glyphs[2]] {
+        assert_eq!(glyph.fill[0].position[0], glyph.fill[1].position[0]);
+        assert_eq!(glyph.height, 96);
+    }
+}
+
+#[test]
+fn lines_do_not_break_inside_a_cluster() {
+    // the combining mark has no precomposed form, so it is shaped into a glyph of its own,
+    // and the box fits two characters of 32px
+    let layout_style = LayoutStyle {
+        box_width: Some(64.),
+        kinsoku: KinsokuStyle {
+            pull: false,
+            ..Default::default()
+        },
+        ..style()
+    };
+    let glyphs = layout_with("中文\u{301}。", layout_style);
+
+    assert_eq!(glyphs.len(), 4);
+    assert_eq!(glyphs[2].byte_range, 3..8);
+    // the full stop may not start a line, and takes the whole cluster to the next line with it
+    let rows = glyphs.iter().map(|glyph| glyph.row).collect::<Vec<_>>();
+    assert_eq!(rows, [0, 1, 1, 1]);
+}
@@ -0,0 +1,132 @@
+use huozi::{
+    Huozi,
+    glyph_vertices::GlyphVertices,
+    layout::{ColorSpace, LayoutResult, LayoutStyle, PunctuationStyle, TextAlign},
+    parser::{Segment, TextStyle},
+};
+
+mod common;
+
+fn layout(text: &str, align: TextAlign, box_width: Option<f64>) -> (Vec<GlyphVertices>, u32) {
+    let layout_style = LayoutStyle {
+        box_width,
+        align,
+        punctuation: PunctuationStyle {
+            compression: false,
+            hanging: true,
+            hanging_tolerance: 1.0,
+        },
+        ..Default::default()
+    };
+    let text_style = TextStyle {
+        font_size: 96.0,
+        line_height: 1.0,
+        ..Default::default()
+    };
+
+    let LayoutResult { glyphs, width, .. } = Huozi::new(common::test_font())
+        .layout_plain(
+            &vec![Segment::dummy(text)],
+            &layout_style,
+            &text_style,
+            ColorSpace::SRGB,
+        )
+        .unwrap();
+
+    (glyphs, width)
+}
+
+fn line_end(glyph: &GlyphVertices) -> u32 {
+    glyph.x + glyph.width
+}
+
+/// positions are rounded to integers, so allow a difference of one pixel.
+fn assert_close(left: u32, right: u32) {
+    assert!(
+        left.abs_diff(right) <= 1,
+        "{} is not close to {}",
+        left,
+        right
+    );
+}
+
+#[test]
+fn start_alignment_keeps_lines_in_place() {
+    let (glyphs, _) = layout("中文", TextAlign::Start, Some(960.));
+
+    assert_eq!(glyphs[0].x, 0);
+}
+
+#[test]
+fn center_and_end_move_lines_in_the_box() {
+    let (start, _) = layout("中文", TextAlign::Start, Some(960.));
+    let (center, _) = layout("中文", TextAlign::Center, Some(960.));
+    let (end, width) = layout("中文", TextAlign::End, Some(960.));
+    let slack = 960 - line_end(&start[1]);
+
+    assert_close(center[0].x, slack / 2);
+    assert_close(end[0].x, slack);
+    assert_close(line_end(&end[1]), 960);
+    assert_eq!(width, 960);
+}
+
+#[test]
+fn lines_without_box_width_align_to_the_longest_line() {
+    let (start, _) = layout("中文字\n中", TextAlign::Start, None);
+    let (end, _) = layout("中文字\n中", TextAlign::End, None);
+
+    assert_close(line_end(&end[3]), line_end(&start[2]));
+}
+
+#[test]
+fn justify_fills_wrapped_lines_but_not_the_last_one() {
+    let (start, _) = layout("中文字", TextAlign::Start, None);
+    let box_width = line_end(&start[2]) as f64 + 40.;
+
+    let (glyphs, _) = layout("中文字中文字", TextAlign::Justify, Some(box_width));
+
+    // the first line is wrapped after three characters and stretched
+    assert_eq!(glyphs[3].row, 1);
+    assert_eq!(glyphs[0].x, 0);
+    assert_close(glyphs[1].x, start[1].x + 20);
+    assert_close(line_end(&glyphs[2]), box_width as u32);
+    // the last line is kept at the start
+    assert_eq!(glyphs[4].x, start[1].x);
+}
+
+#[test]
+fn justify_all_fills_the_last_line() {
+    let (justify, _) = layout("中文字\n中文", TextAlign::Justify, None);
+    let (justify_all, _) = layout("中文字\n中文", TextAlign::JustifyAll, None);
+
+    assert_close(line_end(&justify_all[4]), line_end(&justify_all[2]));
+    assert!(line_end(&justify[4]) < line_end(&justify[2]));
+}
+
+#[test]
+fn justify_expands_latin_spaces_before_cjk_gaps() {
+    let (start, _) = layout("中 a文字", TextAlign::Start, None);
+    let box_width = line_end(&start[4]) as f64 + 30.;
+
+    let (glyphs, _) = layout("中 a文字", TextAlign::JustifyAll, Some(box_width));
+
+    // the space and the boundary between `a` and `文` take the slack first,
+    // so the gap between `文` and `字` is kept
+    assert_eq!(glyphs[1].x, start[1].x);
+    assert_close(glyphs[2].x, start[2].x + 15);
+    assert_eq!(glyphs[4].x - glyphs[3].x, start[4].x - start[3].x);
+    assert_close(line_end(&glyphs[4]), box_width as u32);
+}
+
+#[test]
+fn hanging_punctuation_is_excluded_from_line_width() {
+    let (start, _) = layout("中文字", TextAlign::Start, None);
+    let box_width = line_end(&start[2]) as f64;
+
+    let (glyphs, _) = layout("中文字。", TextAlign::Center, Some(box_width));
+
+    // the line fits the box without the hung `。`, so it stays in place
+    assert_eq!(glyphs[3].row, 0);
+    assert_eq!(glyphs[0].x, 0);
+    assert_close(glyphs[3].x, box_width as u32);
+}
@@ -0,0 +1,95 @@
+use huozi::{
+    Huozi,
+    glyph_vertices::GlyphVertices,
+    layout::{ColorSpace, LayoutDirection, LayoutResult, LayoutStyle},
+    parser::{Segment, TextStyle},
+};
+
+mod common;
+
+fn layout(
+    text: &str,
+    box_width: Option<f64>,
+    box_height: Option<f64>,
+) -> (Vec<GlyphVertices>, u32, u32) {
+    let layout_style = LayoutStyle {
+        direction: LayoutDirection::Vertical,
+        box_width,
+        box_height,
+        ..Default::default()
+    };
+    let text_style = TextStyle {
+        font_size: 96.0,
+        line_height: 1.0,
+        ..Default::default()
+    };
+
+    let LayoutResult {
+        glyphs,
+        width,
+        height,
+        ..
+    } = Huozi::new(common::test_font())
+        .layout_plain(
+            &vec![Segment::dummy(text)],
+            &layout_style,
+            &text_style,
+            ColorSpace::SRGB,
+        )
+        .unwrap();
+
+    (glyphs, width, height)
+}
+
+#[test]
+fn columns_go_from_right_to_left() {
+    let (glyphs, width, height) = layout("中文\n字", None, None);
+
+    assert_eq!(glyphs.len(), 3);
+    assert_eq!((glyphs[0].col, glyphs[0].row), (0, 0));
+    assert_eq!((glyphs[1].col, glyphs[1].row), (1, 0));
+    assert_eq!((glyphs[2].col, glyphs[2].row), (0, 1));
+
+    // the first column is on the right, and characters go downwards in a column
+    assert_eq!((glyphs[0].x, glyphs[0].y), (96, 0));
+    assert_eq!((glyphs[1].x, glyphs[1].y), (96, 96));
+    assert_eq!((glyphs[2].x, glyphs[2].y), (0, 0));
+    assert_eq!((glyphs[0].width, glyphs[0].height), (96, 96));
+    assert_eq!((width, height), (192, 192));
+}
+
+#[test]
+fn columns_wrap_by_box_height() {
+    let (glyphs, width, height) = layout("中文字", None, Some(192.));
+
+    assert_eq!(glyphs[2].row, 1);
+    assert_eq!(glyphs[2].col, 0);
+    assert_eq!(glyphs[2].y, 0);
+    assert_eq!((width, height), (192, 192));
+}
+
+#[test]
+fn columns_start_at_the_right_edge_of_the_box() {
+    let (glyphs, width, _) = layout("中", Some(300.), None);
+
+    assert_eq!(glyphs[0].x + glyphs[0].width, 300);
+    assert!(glyphs[0].fill.iter().all(|vertex| vertex.position[0] > 0.));
+    assert_eq!(width, 96);
+}
+
+#[test]
+fn latin_is_rotated_and_ideographs_are_upright() {
+    let (glyphs, _, _) = layout("a中", None, None);
+
+    // the left edge of an upright glyph is vertical on the screen
+    let upright = &glyphs[1].fill;
+    assert_eq!(upright[0].position[0], upright[1].position[0]);
+
+    // the left edge of a rotated glyph becomes its top edge on the screen
+    let rotated = &glyphs[0].fill;
+    assert_eq!(rotated[0].position[1], rotated[1].position[1]);
+    assert!(rotated[0].position[0] > rotated[1].position[0]);
+
+    // rotated glyphs advance by their horizontal advance
+    assert!(glyphs[1].y < 96);
+}
@@ -0,0 +1,113 @@
+use huozi::{
+    Huozi,
+    glyph_vertices::GlyphVertices,
+    layout::{ColorSpace, LayoutResult, LayoutStyle, WordBreak},
+    parser::{Segment, TextStyle},
+};
+
+mod common;
+
+fn layout(text: &str, layout_style: &LayoutStyle) -> (Vec<GlyphVertices>, u32) {
+    let text_style = TextStyle {
+        font_size: 32.0,
+        line_height: 1.0,
+        ..Default::default()
+    };
+
+    let LayoutResult { glyphs, width, .. } = Huozi::new(common::test_font())
+        .layout_plain(
+            &vec![Segment::dummy(text)],
+            layout_style,
+            &text_style,
+            ColorSpace::SRGB,
+        )
+        .unwrap();
+
+    (glyphs, width)
+}
+
+/// rows of the glyphs when the text is laid out in a box which just fits `fitting` in a line.
+fn rows(text: &str, fitting: &str, word_break: WordBreak) -> Vec<u32> {
+    let (_, width) = layout(fitting, &LayoutStyle::default());
+    let layout_style = LayoutStyle {
+        box_width: Some(width as f64 + 1.),
+        word_break,
+        ..Default::default()
+    };
+
+    let (glyphs, _) = layout(text, &layout_style);
+    glyphs.iter().map(|glyph| glyph.row).collect()
+}
+
+#[test]
+fn latin_words_wrap_as_units() {
+    assert_eq!(
+        rows("hello world", "hello wo", WordBreak::Normal),
+        [0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1]
+    );
+}
+
+#[test]
+fn cjk_breaks_between_any_characters() {
+    assert_eq!(rows("中文字", "中文", WordBreak::Normal), [0, 0, 1]);
+    assert_eq!(
+        rows("中文hello", "中文hel", WordBreak::Normal),
+        [0, 0, 1, 1, 1, 1, 1]
+    );
+}
+
+#[test]
+fn numbers_and_urls_are_kept_together() {
+    assert_eq!(
+        rows("no 12345", "no 123", WordBreak::Normal),
+        [0, 0, 0, 1, 1, 1, 1, 1]
+    );
+
+    let rows = rows("see https://a.io/b", "see https://a.io", WordBreak::Normal);
+    assert_eq!(rows[..4], [0, 0, 0, 0]);
+    assert!(rows[4..].iter().all(|&row| row == 1));
+}
+
+#[test]
+fn words_longer_than_the_line_break_between_characters() {
+    assert_eq!(
+        rows("abcdefgh", "abcd", WordBreak::Normal),
+        [0, 0, 0, 0, 1, 1, 1, 1]
+    );
+}
+
+#[test]
+fn break_all_breaks_inside_words() {
+    assert_eq!(
+        rows("hello world", "hello wo", WordBreak::BreakAll),
+        [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1]
+    );
+}
+
+#[test]
+fn zero_width_space_is_a_break_opportunity() {
+    assert_eq!(
+        rows("abc\u{200B}def", "abc\u{200B}d", WordBreak::Normal),
+        [0, 0, 0, 0, 1, 1, 1]
+    );
+}
+
+#[test]
+fn soft_hyphen_is_visible_only_at_the_end_of_a_wrapped_line() {
+    assert_eq!(
+        rows("abc\u{AD}def", "abc\u{AD}d", WordBreak::Normal),
+        [0, 0, 0, 0, 1, 1, 1]
+    );
+
+    let (_, width) = layout("abc\u{AD}d", &LayoutStyle::default());
+    let wrapped_style = LayoutStyle {
+        box_width: Some(width as f64 + 1.),
+        ..Default::default()
+    };
+    let (wrapped, _) = layout("abc\u{AD}def", &wrapped_style);
+    let (unwrapped, _) = layout("abc\u{AD}def", &LayoutStyle::default());
+
+    assert!(!wrapped[3].fill.is_empty());
+    assert!(unwrapped[3].fill.is_empty());
+    assert_eq!(unwrapped[3].width, 0);
+}